@@ -0,0 +1,74 @@
+use anyhow::{Context, Result};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A record of already-processed videos, stored in the same format as
+/// yt-dlp's `--download-archive` (`<extractor> <id>` per line).
+#[derive(Debug)]
+pub struct Archive {
+    path: PathBuf,
+    lines: Vec<String>,
+    ids: HashSet<String>,
+}
+
+impl Archive {
+    /// Loads the archive at `path`. A missing file is treated as empty.
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => {
+                return Err(e).with_context(|| format!("Failed to read archive {}", path.display()))
+            }
+        };
+
+        let lines: Vec<String> = contents
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(String::from)
+            .collect();
+
+        // Accept both yt-dlp's "<extractor> <id>" lines and bare IDs.
+        let ids = lines
+            .iter()
+            .filter_map(|line| line.split_whitespace().last())
+            .map(String::from)
+            .collect();
+
+        Ok(Archive {
+            path: path.to_path_buf(),
+            lines,
+            ids,
+        })
+    }
+
+    pub fn contains(&self, id: &str) -> bool {
+        self.ids.contains(id)
+    }
+
+    /// Adds `id` to the archive and rewrites the file atomically by writing a
+    /// temporary file next to it and renaming it into place.
+    pub fn record(&mut self, extractor: &str, id: &str) -> Result<()> {
+        if !self.ids.insert(id.to_string()) {
+            return Ok(());
+        }
+        self.lines
+            .push(format!("{} {}", extractor.to_lowercase(), id));
+
+        let mut contents = self.lines.join("\n");
+        contents.push('\n');
+
+        let mut tmp_name = self.path.file_name().unwrap_or_default().to_os_string();
+        tmp_name.push(".tmp");
+        let tmp_path = self.path.with_file_name(tmp_name);
+
+        fs::write(&tmp_path, contents)
+            .with_context(|| format!("Failed to write {}", tmp_path.display()))?;
+        fs::rename(&tmp_path, &self.path)
+            .with_context(|| format!("Failed to update archive {}", self.path.display()))?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,174 @@
+use anyhow::Result;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::executables::Executables;
+use crate::Args;
+
+pub const DEFAULT_BITRATE: &str = "192k";
+
+/// Formats the audio-only variant can be exported in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AudioFormat {
+    #[default]
+    Mp3,
+    Aac,
+    Opus,
+    Flac,
+    Wav,
+}
+
+impl AudioFormat {
+    pub fn codec(self) -> &'static str {
+        match self {
+            AudioFormat::Mp3 => "libmp3lame",
+            AudioFormat::Aac => "aac",
+            AudioFormat::Opus => "libopus",
+            AudioFormat::Flac => "flac",
+            AudioFormat::Wav => "pcm_s16le",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            AudioFormat::Mp3 => "mp3",
+            AudioFormat::Aac => "m4a",
+            AudioFormat::Opus => "opus",
+            AudioFormat::Flac => "flac",
+            AudioFormat::Wav => "wav",
+        }
+    }
+
+    /// Lossless formats have no bitrate setting.
+    pub fn is_lossless(self) -> bool {
+        matches!(self, AudioFormat::Flac | AudioFormat::Wav)
+    }
+
+    /// Whether ffmpeg can store cover art in this format.
+    pub fn supports_cover_art(self) -> bool {
+        matches!(
+            self,
+            AudioFormat::Mp3 | AudioFormat::Aac | AudioFormat::Flac
+        )
+    }
+}
+
+/// Channel counts `--audio-channels` can convert to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+pub enum AudioChannels {
+    #[value(name = "1")]
+    #[serde(rename = "1")]
+    Mono,
+    #[value(name = "2")]
+    #[serde(rename = "2")]
+    Stereo,
+    /// Keep the source's channels.
+    #[serde(rename = "keep")]
+    Keep,
+}
+
+impl AudioChannels {
+    /// The number of channels to convert to, or `None` to keep the source's.
+    pub fn count(self) -> Option<u32> {
+        match self {
+            AudioChannels::Mono => Some(1),
+            AudioChannels::Stereo => Some(2),
+            AudioChannels::Keep => None,
+        }
+    }
+}
+
+/// `-ac 1` alone would leave the mix to the decoder; this averages the left
+/// and right channels of a stereo mix instead, first folding any other
+/// layout (including mono) to stereo so the pan always has both channels.
+const MONO_DOWNMIX: &str = "aformat=channel_layouts=stereo,pan=mono|c0=0.5*c0+0.5*c1";
+
+/// The filter that mixes audio down to `channels`, if one is needed
+/// besides `-ac`.
+pub fn downmix_filter(channels: Option<u32>) -> Option<&'static str> {
+    (channels == Some(1)).then_some(MONO_DOWNMIX)
+}
+
+/// `-ac` arguments converting to `channels`.
+pub fn channel_args(channels: Option<u32>) -> Vec<String> {
+    channels
+        .map(|channels| vec!["-ac".to_string(), channels.to_string()])
+        .unwrap_or_default()
+}
+
+/// How audio-only clips are encoded.
+#[derive(Debug, Clone)]
+pub struct AudioSettings {
+    pub format: AudioFormat,
+    pub bitrate: String,
+    pub sample_rate: Option<u32>,
+    pub channels: Option<u32>,
+}
+
+impl Default for AudioSettings {
+    fn default() -> Self {
+        AudioSettings {
+            format: AudioFormat::default(),
+            bitrate: DEFAULT_BITRATE.to_string(),
+            sample_rate: None,
+            channels: None,
+        }
+    }
+}
+
+impl AudioSettings {
+    pub fn from_args(args: &Args) -> Self {
+        AudioSettings {
+            format: args.audio_format,
+            bitrate: args.audio_bitrate.clone(),
+            sample_rate: args.audio_samplerate.or(args.sample_rate),
+            channels: args.audio_channels.and_then(AudioChannels::count),
+        }
+    }
+
+    fn ffmpeg_args(&self) -> Vec<String> {
+        let mut ffmpeg_args = vec!["-vn".to_string()];
+        if let Some(filter) = downmix_filter(self.channels) {
+            ffmpeg_args.extend(["-af".to_string(), filter.to_string()]);
+        }
+        ffmpeg_args.extend(["-c:a".to_string(), self.format.codec().to_string()]);
+        if !self.format.is_lossless() {
+            ffmpeg_args.extend(["-b:a".to_string(), self.bitrate.clone()]);
+        }
+        if let Some(sample_rate) = self.sample_rate {
+            ffmpeg_args.extend(["-ar".to_string(), sample_rate.to_string()]);
+        }
+        ffmpeg_args.extend(channel_args(self.channels));
+        ffmpeg_args
+    }
+}
+
+/// Encodes `duration` seconds of audio from `video_path`, starting at
+/// `start`, to `output`. `output_args` are added just before the output path.
+pub fn encode_audio_clip(
+    executables: &Executables,
+    video_path: &Path,
+    start: f64,
+    duration: f64,
+    output: &Path,
+    settings: &AudioSettings,
+    output_args: &[String],
+) -> Result<()> {
+    executables.run(
+        executables
+            .ffmpeg()
+            .args([
+                "-i",
+                video_path.to_str().unwrap(),
+                "-ss",
+                &format!("{:.3}", start),
+                "-t",
+                &format!("{:.3}", duration),
+            ])
+            .args(settings.ffmpeg_args())
+            .args(output_args)
+            .args(["-y", output.to_str().unwrap()]),
+    )
+}
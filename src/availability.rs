@@ -0,0 +1,89 @@
+use std::fmt;
+
+use crate::VideoInfo;
+
+/// Process exit code when a video can't be downloaded because of who may
+/// watch it, or because it's gone.
+pub const EXIT_UNAVAILABLE: u8 = 3;
+
+/// A video that can't be downloaded, with yt-dlp's `availability` value
+/// (or `unavailable` for deleted videos).
+#[derive(Debug, Clone)]
+pub struct Unavailable {
+    pub availability: String,
+}
+
+impl Unavailable {
+    fn new(availability: &str) -> Self {
+        Unavailable {
+            availability: availability.to_string(),
+        }
+    }
+
+    /// Whether signing in with cookies could make the video downloadable.
+    fn needs_cookies(&self) -> bool {
+        matches!(
+            self.availability.as_str(),
+            "members_only" | "premium_only" | "subscriber_only" | "needs_auth"
+        )
+    }
+}
+
+impl fmt::Display for Unavailable {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let reason = match self.availability.as_str() {
+            "private" => "This video is private",
+            "members_only" => "This video is only available to channel members",
+            "premium_only" => "This video is only available with YouTube Premium",
+            "subscriber_only" => "This video is only available to subscribers",
+            "needs_auth" => "This video requires signing in, e.g. because it's age-restricted",
+            "unavailable" => "This video is unavailable; it was probably deleted",
+            other => return write!(f, "This video can't be downloaded ({})", other),
+        };
+        write!(f, "{}", reason)?;
+        if self.needs_cookies() {
+            write!(
+                f,
+                ". If your account has access, pass its cookies with e.g. --cookies-from-browser chrome"
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for Unavailable {}
+
+/// Checks the video's `availability` before anything is downloaded. Videos
+/// that need an account are let through when cookies are given.
+pub fn check(video_info: &VideoInfo, has_cookies: bool) -> Result<(), Unavailable> {
+    match video_info.availability.as_deref() {
+        None | Some("public") | Some("unlisted") => Ok(()),
+        Some(availability) => {
+            let unavailable = Unavailable::new(availability);
+            if has_cookies && unavailable.needs_cookies() {
+                Ok(())
+            } else {
+                Err(unavailable)
+            }
+        }
+    }
+}
+
+/// Recognizes yt-dlp's error messages for videos that can't be fetched at
+/// all, which never get as far as reporting an `availability`.
+pub fn from_ytdlp_error(stderr: &str) -> Option<Unavailable> {
+    let availability = if stderr.contains("Private video") {
+        "private"
+    } else if stderr.contains("members-only") || stderr.contains("Join this channel") {
+        "members_only"
+    } else if stderr.contains("Premium") {
+        "premium_only"
+    } else if stderr.contains("Sign in to confirm your age") {
+        "needs_auth"
+    } else if stderr.contains("Video unavailable") || stderr.contains("has been removed") {
+        "unavailable"
+    } else {
+        return None;
+    };
+    Some(Unavailable::new(availability))
+}
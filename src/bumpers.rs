@@ -0,0 +1,125 @@
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::encode::EncodeSettings;
+use crate::executables::Executables;
+use crate::probe::{self, MediaInfo};
+use crate::{concat_files, progress};
+
+/// Intro and outro clips joined onto every chapter clip with
+/// `--prepend-clip` and `--append-clip`.
+pub struct Bumpers<'a> {
+    pub prepend: Option<&'a Path>,
+    pub append: Option<&'a Path>,
+}
+
+/// Joins the bumpers onto every clip in place, in the order
+/// prepend, chapter, append. Everything is re-encoded with `encode`.
+pub fn concat_clips(
+    executables: &Executables,
+    clips: &[PathBuf],
+    bumpers: &Bumpers,
+    encode: &EncodeSettings,
+) -> Result<()> {
+    // The chapter clips are already deinterlaced, denoised, tone mapped and
+    // watermarked, and the bumpers are decoded in software, so only the codec
+    // settings carry over.
+    let encode = EncodeSettings {
+        tone_map: None,
+        hwaccel: None,
+        watermark: None,
+        deinterlace: None,
+        denoise: None,
+        ..encode.clone()
+    };
+
+    let Some(first) = clips.first() else {
+        return Ok(());
+    };
+
+    // The concat demuxer needs every input to share resolution, frame rate
+    // and sample rate. All clips come from the same video, so the bumpers are
+    // conformed to the first one once and reused.
+    let info = probe::probe(executables, first)?;
+    let temp_dir = tempfile::tempdir().context("Failed to create temporary directory")?;
+    let extension = first.extension().unwrap_or_default().to_string_lossy();
+    let conformed = |name: &str, input: Option<&Path>| -> Result<Option<PathBuf>> {
+        input
+            .map(|input| {
+                let output = temp_dir.path().join(format!("{}.{}", name, extension));
+                conform(executables, input, &info, &encode, &output)?;
+                Ok(output)
+            })
+            .transpose()
+    };
+    let prepend = conformed("prepend", bumpers.prepend)?;
+    let append = conformed("append", bumpers.append)?;
+
+    let pb = progress::bar(clips.len());
+
+    for clip in clips {
+        let name = clip.file_name().unwrap_or_default().to_string_lossy();
+        pb.set_message(format!("Adding intro/outro: {}", name));
+
+        let inputs: Vec<PathBuf> = prepend
+            .iter()
+            .chain([clip])
+            .chain(&append)
+            .cloned()
+            .collect();
+        let joined = temp_dir.path().join(format!("joined.{}", extension));
+        let result =
+            concat_files(executables, &inputs, &joined, Some(&encode), &[]).and_then(|()| {
+                fs::copy(&joined, clip).context("Failed to replace clip with joined version")
+            });
+
+        if let Err(e) = result {
+            pb.finish_and_clear();
+            return Err(e).with_context(|| format!("Failed to add intro/outro to {}", name));
+        }
+
+        pb.inc(1);
+    }
+
+    pb.finish_with_message("Intro/outro added");
+
+    Ok(())
+}
+
+/// Re-encodes `input` to match `target`'s resolution, frame rate and sample
+/// rate. Other aspect ratios are letterboxed rather than stretched.
+fn conform(
+    executables: &Executables,
+    input: &Path,
+    target: &MediaInfo,
+    encode: &EncodeSettings,
+    output: &Path,
+) -> Result<()> {
+    let (width, height) = target
+        .resolution()
+        .context("Could not determine the resolution of the chapter clip")?;
+
+    let mut filter = format!(
+        "scale={w}:{h}:force_original_aspect_ratio=decrease,pad={w}:{h}:(ow-iw)/2:(oh-ih)/2,setsar=1",
+        w = width,
+        h = height
+    );
+    if let Some(rate) = target.frame_rate() {
+        filter.push_str(&format!(",fps={}", rate));
+    }
+
+    let mut command = executables.ffmpeg();
+    command
+        .args(["-i", input.to_str().unwrap()])
+        .args(encode.video_args(Some(&filter)))
+        .args(encode.audio_args(None));
+    if let Some(rate) = target.sample_rate() {
+        command.args(["-ar", rate]);
+    }
+    command.args(["-y", output.to_str().unwrap()]);
+
+    executables
+        .run(&mut command)
+        .with_context(|| format!("Failed to conform {}", input.display()))
+}
@@ -0,0 +1,786 @@
+use anyhow::{Context, Result};
+use regex::Regex;
+use std::fmt;
+use std::fs;
+use std::ops::Range;
+use std::path::Path;
+
+use crate::Chapter;
+
+/// A set of 1-based chapter numbers parsed from a spec like `5-12` or `1,3,5-7`.
+#[derive(Debug, Clone)]
+pub struct ChapterSelection {
+    ranges: Vec<(usize, usize)>,
+}
+
+impl ChapterSelection {
+    pub fn parse(spec: &str) -> Result<Self> {
+        let mut ranges = Vec::new();
+
+        for part in spec.split(',').map(str::trim) {
+            if part.is_empty() {
+                anyhow::bail!("Empty entry in chapter selection \"{}\"", spec);
+            }
+
+            let (start, end) = match part.split_once('-') {
+                Some((start, "")) => (parse_number(start, spec)?, usize::MAX),
+                Some((start, end)) => (parse_number(start, spec)?, parse_number(end, spec)?),
+                None => {
+                    let n = parse_number(part, spec)?;
+                    (n, n)
+                }
+            };
+
+            if start > end {
+                anyhow::bail!("Invalid chapter range \"{}\": start is after end", part);
+            }
+
+            ranges.push((start, end));
+        }
+
+        Ok(ChapterSelection { ranges })
+    }
+
+    pub fn contains(&self, number: usize) -> bool {
+        self.ranges
+            .iter()
+            .any(|&(start, end)| (start..=end).contains(&number))
+    }
+}
+
+fn parse_number(value: &str, spec: &str) -> Result<usize> {
+    let n: usize = value
+        .trim()
+        .parse()
+        .with_context(|| format!("Invalid chapter number \"{}\" in \"{}\"", value, spec))?;
+
+    if n == 0 {
+        anyhow::bail!("Chapter numbers start at 1 (got 0 in \"{}\")", spec);
+    }
+
+    Ok(n)
+}
+
+/// Narrows `chapters` to the selected chapter numbers, then to titles matching
+/// `filter`. Chapters keep their original numbers.
+pub fn select_chapters(
+    chapters: Vec<Chapter>,
+    selection: Option<&ChapterSelection>,
+    filter: Option<&Regex>,
+) -> Vec<Chapter> {
+    chapters
+        .into_iter()
+        .filter(|chapter| selection.is_none_or(|s| s.contains(chapter.number)))
+        .filter(|chapter| filter.is_none_or(|re| re.is_match(&chapter.title)))
+        .collect()
+}
+
+/// Chapters excluded with `--skip-chapter` and `--skip-chapter-pattern`.
+#[derive(Debug, Clone)]
+pub struct ChapterSkips {
+    /// 1-based chapter numbers.
+    pub numbers: Vec<usize>,
+    pub pattern: Option<Regex>,
+}
+
+impl ChapterSkips {
+    pub fn matches(&self, chapter: &Chapter) -> bool {
+        self.numbers.contains(&chapter.number)
+            || self
+                .pattern
+                .as_ref()
+                .is_some_and(|re| re.is_match(&chapter.title))
+    }
+}
+
+/// Closes the gaps left by the `skipped` chapter numbers, so the chapter
+/// after a skipped one takes its number.
+pub fn renumber(chapters: &mut [Chapter], skipped: &[usize]) {
+    for chapter in chapters {
+        let before = skipped.iter().filter(|&&n| n < chapter.number).count();
+        chapter.number -= before;
+    }
+}
+
+/// Groups consecutive chapters whose titles match `pattern`, returning the
+/// index ranges of every group of two or more chapters.
+pub fn merge_groups(chapters: &[Chapter], pattern: &Regex) -> Vec<Range<usize>> {
+    let mut groups = Vec::new();
+    let mut start = None;
+
+    for (i, chapter) in chapters.iter().enumerate() {
+        match (pattern.is_match(&chapter.title), start) {
+            (true, None) => start = Some(i),
+            (false, Some(group_start)) => {
+                if i - group_start > 1 {
+                    groups.push(group_start..i);
+                }
+                start = None;
+            }
+            _ => {}
+        }
+    }
+
+    if let Some(group_start) = start {
+        if chapters.len() - group_start > 1 {
+            groups.push(group_start..chapters.len());
+        }
+    }
+
+    groups
+}
+
+/// Loads chapters from a `--chapters-file`: a JSON array in the same shape
+/// as yt-dlp's `chapters` field, e.g.
+///
+/// ```json
+/// [
+///   {"title": "Intro", "start_time": 0.0, "end_time": 45.3},
+///   {"title": "Main Topic", "start_time": 45.3, "end_time": 310.0}
+/// ]
+/// ```
+///
+/// or, for `.yml`/`.yaml` files, the same list in YAML:
+///
+/// ```yaml
+/// - title: Intro
+///   start_time: 0.0
+///   end_time: 45.3
+/// ```
+///
+/// Times are in seconds. Chapters must be in order and must not overlap.
+pub fn load_chapters_file(path: &Path) -> Result<Vec<Chapter>> {
+    let contents = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read chapters file {}", path.display()))?;
+    let is_yaml = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("yml") || ext.eq_ignore_ascii_case("yaml"));
+
+    let parsed = if is_yaml {
+        serde_yaml::from_str(&contents).map_err(|e| {
+            let line = e.location().map(|location| location.line());
+            (line, e.to_string())
+        })
+    } else {
+        serde_json::from_str(&contents).map_err(|e| (Some(e.line()), e.to_string()))
+    };
+    let chapters: Vec<Chapter> = parsed.map_err(|(line, error)| {
+        let location = match line {
+            Some(line) => format!("{}:{}", path.display(), line),
+            None => path.display().to_string(),
+        };
+        anyhow::anyhow!(
+            "Invalid chapters file {}: expected a {} list of \
+             {{\"title\", \"start_time\", \"end_time\"}} entries ({})",
+            location,
+            if is_yaml { "YAML" } else { "JSON" },
+            error
+        )
+    })?;
+
+    if chapters.is_empty() {
+        anyhow::bail!("Chapters file {} contains no chapters", path.display());
+    }
+
+    let mut previous_end = 0.0;
+    for (i, chapter) in chapters.iter().enumerate() {
+        let describe = || {
+            format!(
+                "Chapter {} (\"{}\") in {}",
+                i + 1,
+                chapter.title,
+                path.display()
+            )
+        };
+
+        if chapter.start_time < 0.0 {
+            anyhow::bail!("{} starts before 0s", describe());
+        }
+        if chapter.end_time <= chapter.start_time {
+            anyhow::bail!(
+                "{} ends at {}s, which is not after its start at {}s",
+                describe(),
+                chapter.end_time,
+                chapter.start_time
+            );
+        }
+        if chapter.start_time < previous_end {
+            anyhow::bail!(
+                "{} starts at {}s, before the previous chapter ends at {}s",
+                describe(),
+                chapter.start_time,
+                previous_end
+            );
+        }
+        previous_end = chapter.end_time;
+    }
+
+    for (i, chapter) in chapters.iter().enumerate() {
+        // Warn once per title, at its second occurrence.
+        let earlier = chapters[..i].iter().filter(|c| c.title == chapter.title);
+        if earlier.count() == 1 {
+            estatus!(
+                "Warning: chapter title \"{}\" appears more than once in {}",
+                chapter.title,
+                path.display()
+            );
+        }
+    }
+
+    Ok(chapters)
+}
+
+/// Malformed chapter data found by `validate_chapters`. Chapters are
+/// identified by their number.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChapterWarning {
+    /// The chapter ends where it starts.
+    ZeroDuration(usize),
+    /// The chapter ends before it starts.
+    NegativeDuration(usize),
+    /// Chapter `b` starts before chapter `a`, the one before it, ends.
+    Overlap { a: usize, b: usize },
+    /// The chapter starts before the one before it.
+    OutOfOrder(usize),
+}
+
+impl fmt::Display for ChapterWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ChapterWarning::ZeroDuration(n) => write!(f, "chapter {} is empty", n),
+            ChapterWarning::NegativeDuration(n) => write!(f, "chapter {} ends before it starts", n),
+            ChapterWarning::Overlap { a, b } => write!(f, "chapters {} and {} overlap", a, b),
+            ChapterWarning::OutOfOrder(n) => {
+                write!(f, "chapter {} starts before the chapter before it", n)
+            }
+        }
+    }
+}
+
+/// Checks chapter data as it came from yt-dlp, which can have empty,
+/// overlapping or out of order chapters.
+pub fn validate_chapters(chapters: &[Chapter]) -> Vec<ChapterWarning> {
+    let mut warnings = Vec::new();
+    for (i, chapter) in chapters.iter().enumerate() {
+        if chapter.end_time == chapter.start_time {
+            warnings.push(ChapterWarning::ZeroDuration(chapter.number));
+        } else if chapter.end_time < chapter.start_time {
+            warnings.push(ChapterWarning::NegativeDuration(chapter.number));
+        }
+
+        let Some(previous) = i.checked_sub(1).map(|i| &chapters[i]) else {
+            continue;
+        };
+        if chapter.start_time < previous.start_time {
+            warnings.push(ChapterWarning::OutOfOrder(chapter.number));
+        } else if chapter.start_time < previous.end_time {
+            warnings.push(ChapterWarning::Overlap {
+                a: previous.number,
+                b: chapter.number,
+            });
+        }
+    }
+    warnings
+}
+
+/// Part of the video that no chapter covers.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Gap {
+    pub start: f64,
+    pub end: f64,
+    /// Number of the chapter before the gap, `None` before the first chapter.
+    pub after_chapter: Option<usize>,
+    /// Number of the chapter after the gap, `None` after the last chapter.
+    pub before_chapter: Option<usize>,
+}
+
+impl Gap {
+    pub fn duration(&self) -> f64 {
+        self.end - self.start
+    }
+
+    /// The clip name for `--extract-gaps`, numbered by the chapters before
+    /// the gap: `gap_00_pregap`, `gap_01_between_ch1_ch2`, `gap_03_postgap`.
+    pub fn clip_name(&self) -> String {
+        let position = match (self.after_chapter, self.before_chapter) {
+            (Some(a), Some(b)) => format!("between_ch{}_ch{}", a, b),
+            (None, _) => "pregap".to_string(),
+            (Some(_), None) => "postgap".to_string(),
+        };
+        format!("gap_{:02}_{}", self.after_chapter.unwrap_or(0), position)
+    }
+
+    /// Where the gap is, e.g. `between chapters 1 and 2`.
+    pub fn describe(&self) -> String {
+        match (self.after_chapter, self.before_chapter) {
+            (Some(a), Some(b)) => format!("between chapters {} and {}", a, b),
+            (None, Some(b)) => format!("before chapter {}", b),
+            (Some(a), None) => format!("after chapter {}", a),
+            (None, None) => "without chapters".to_string(),
+        }
+    }
+
+    /// The gap as a chapter, so it can be split like one.
+    pub fn to_chapter(&self, number: usize) -> Chapter {
+        Chapter {
+            title: format!("Gap {}", self.describe()),
+            start_time: self.start,
+            end_time: self.end,
+            number,
+        }
+    }
+}
+
+/// The stretches of the video that `chapters` don't cover: before the first
+/// chapter, between chapters and, when the `duration` is known, after the
+/// last one. Gaps shorter than `min_duration` seconds are left out.
+pub fn find_gaps(chapters: &[Chapter], duration: Option<f64>, min_duration: f64) -> Vec<Gap> {
+    let mut sorted: Vec<&Chapter> = chapters.iter().collect();
+    sorted.sort_by(|a, b| a.start_time.total_cmp(&b.start_time));
+
+    let mut gaps = Vec::new();
+    let mut covered_until = 0.0;
+    let mut previous: Option<&Chapter> = None;
+    for chapter in sorted {
+        if chapter.start_time > covered_until {
+            gaps.push(Gap {
+                start: covered_until,
+                end: chapter.start_time,
+                after_chapter: previous.map(|c| c.number),
+                before_chapter: Some(chapter.number),
+            });
+        }
+        if chapter.end_time >= covered_until {
+            covered_until = chapter.end_time;
+            previous = Some(chapter);
+        }
+    }
+    if let Some(duration) = duration.filter(|&duration| duration > covered_until) {
+        gaps.push(Gap {
+            start: covered_until,
+            end: duration,
+            after_chapter: previous.map(|c| c.number),
+            before_chapter: None,
+        });
+    }
+
+    gaps.retain(|gap| gap.duration() >= min_duration);
+    gaps
+}
+
+/// Extracts chapters from timestamp lines in a video description, such as
+/// `0:00 Intro`, `(5:32) - Main Topic` or `1:02:03 Outro`. Each chapter ends
+/// where the next one starts and the last one ends at `duration`. Timestamps
+/// that don't move forward are ignored.
+pub fn parse_description(description: &str, duration: f64) -> Vec<Chapter> {
+    let starts = timestamp_lines(description, duration);
+
+    let ends: Vec<f64> = starts
+        .iter()
+        .skip(1)
+        .map(|line| line.start)
+        .chain([duration])
+        .collect();
+
+    starts
+        .into_iter()
+        .zip(ends)
+        .map(|(line, end_time)| Chapter {
+            title: line.title,
+            start_time: line.start,
+            end_time,
+            number: 0,
+        })
+        .collect()
+}
+
+/// The part of `description` that belongs to each chapter: from the
+/// chapter's timestamp line up to the next timestamp line. Chapters are
+/// matched to timestamp lines by title; `None` for chapters without one.
+pub fn description_sections(description: &str, chapters: &[Chapter]) -> Vec<Option<String>> {
+    let lines: Vec<&str> = description.lines().collect();
+    let timestamps = timestamp_lines(description, f64::INFINITY);
+
+    chapters
+        .iter()
+        .map(|chapter| {
+            let i = timestamps
+                .iter()
+                .position(|line| line.title == chapter.title)?;
+            let end = timestamps.get(i + 1).map_or(lines.len(), |next| next.index);
+            Some(
+                lines[timestamps[i].index..end]
+                    .join("\n")
+                    .trim()
+                    .to_string(),
+            )
+        })
+        .collect()
+}
+
+/// A chapter timestamp line of a video description.
+struct TimestampLine {
+    /// 0-based line number.
+    index: usize,
+    start: f64,
+    title: String,
+}
+
+fn timestamp_lines(description: &str, duration: f64) -> Vec<TimestampLine> {
+    let line_pattern =
+        Regex::new(r"^\s*[\[(]?((?:\d{1,2}:)?\d{1,2}:\d{2})[\])]?\s*[-–—:|]?\s*(.+?)\s*$").unwrap();
+
+    let mut starts: Vec<TimestampLine> = Vec::new();
+    for (index, line) in description.lines().enumerate() {
+        let Some(captures) = line_pattern.captures(line) else {
+            continue;
+        };
+        let start = parse_timestamp(&captures[1]);
+        if start >= duration || starts.last().is_some_and(|last| start <= last.start) {
+            continue;
+        }
+        starts.push(TimestampLine {
+            index,
+            start,
+            title: captures[2].to_string(),
+        });
+    }
+
+    starts
+}
+
+/// Builds chapters from `--clip` ranges like `1:00-2:30` or `12.5-20`, titled
+/// with `titles` where given and otherwise after the range itself.
+pub fn clip_chapters(ranges: &[String], titles: &[Option<String>]) -> Result<Vec<Chapter>> {
+    ranges
+        .iter()
+        .enumerate()
+        .map(|(i, range)| {
+            let (start, end) = range
+                .split_once('-')
+                .with_context(|| format!("Invalid --clip \"{}\": expected START-END", range))?;
+            let start_time = parse_clip_time(start)
+                .with_context(|| format!("Invalid start time in --clip \"{}\"", range))?;
+            let end_time = parse_clip_time(end)
+                .with_context(|| format!("Invalid end time in --clip \"{}\"", range))?;
+            if end_time <= start_time {
+                anyhow::bail!("Invalid --clip \"{}\": end must be after start", range);
+            }
+
+            let title = titles.get(i).cloned().flatten().unwrap_or_else(|| {
+                format!("{}-{}", range_label(start_time), range_label(end_time))
+            });
+
+            Ok(Chapter {
+                title,
+                start_time,
+                end_time,
+                number: 0,
+            })
+        })
+        .collect()
+}
+
+/// Seconds in an `HH:MM:SS(.mmm)`, `MM:SS(.mmm)` or `SS(.mmm)` time.
+fn parse_clip_time(time: &str) -> Result<f64> {
+    let time = time.trim();
+    let valid = !time.is_empty()
+        && time.split(':').count() <= 3
+        && time
+            .split(':')
+            .all(|part| part.parse::<f64>().is_ok_and(|n| n >= 0.0));
+    if !valid {
+        anyhow::bail!("'{}' is not a time like 1:02:03, 2:03 or 123.5", time);
+    }
+    Ok(parse_timestamp(time))
+}
+
+/// A filename-safe label for a clip boundary, e.g. `00h01m30s`.
+fn range_label(seconds: f64) -> String {
+    let seconds = seconds.round() as u64;
+    format!(
+        "{:02}h{:02}m{:02}s",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+/// Seconds in a colon-separated timestamp such as `MM:SS` or `H:MM:SS`.
+fn parse_timestamp(timestamp: &str) -> f64 {
+    timestamp.split(':').fold(0.0, |total, part| {
+        total * 60.0 + part.parse::<f64>().unwrap_or(0.0)
+    })
+}
+
+/// The range of durations, in seconds, that chapters must fall within.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DurationLimits {
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+}
+
+impl DurationLimits {
+    /// Why `chapter` falls outside the limits, e.g. `3.2s, below minimum 10s`,
+    /// or `None` when it is within them.
+    pub fn violation(&self, chapter: &Chapter) -> Option<String> {
+        let duration = chapter.end_time - chapter.start_time;
+
+        if let Some(min) = self.min.filter(|&min| duration < min) {
+            return Some(format!("{:.1}s, below minimum {}s", duration, min));
+        }
+        if let Some(max) = self.max.filter(|&max| duration > max) {
+            return Some(format!("{:.1}s, above maximum {}s", duration, max));
+        }
+
+        None
+    }
+}
+
+/// The time span covering all of `chapters`, as `(start, end)` seconds.
+pub fn time_span(chapters: &[Chapter]) -> Option<(f64, f64)> {
+    let start = chapters.iter().map(|c| c.start_time).reduce(f64::min)?;
+    let end = chapters.iter().map(|c| c.end_time).reduce(f64::max)?;
+    Some((start, end))
+}
+
+/// Extends every chapter by `pre_pad` seconds before and `post_pad` seconds
+/// after, clamped to the start and (when known) the end of the video.
+pub fn pad_chapters(
+    chapters: &[Chapter],
+    pre_pad: f64,
+    post_pad: f64,
+    video_duration: Option<f64>,
+) -> Vec<Chapter> {
+    chapters
+        .iter()
+        .map(|chapter| {
+            let end_time = chapter.end_time + post_pad;
+            Chapter {
+                start_time: (chapter.start_time - pre_pad).max(0.0),
+                end_time: video_duration.map_or(end_time, |duration| end_time.min(duration)),
+                ..chapter.clone()
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Chapters numbered from 1, from `(title, start, end)` triples.
+    fn chapters(spans: &[(&str, f64, f64)]) -> Vec<Chapter> {
+        spans
+            .iter()
+            .enumerate()
+            .map(|(i, &(title, start_time, end_time))| Chapter {
+                title: title.to_string(),
+                start_time,
+                end_time,
+                number: i + 1,
+            })
+            .collect()
+    }
+
+    fn spans(chapters: &[Chapter]) -> Vec<(&str, f64, f64)> {
+        chapters
+            .iter()
+            .map(|c| (c.title.as_str(), c.start_time, c.end_time))
+            .collect()
+    }
+
+    #[test]
+    fn parses_description_timestamps() {
+        let description = "Thanks for watching!\n\
+                           0:00 Intro\n\
+                           (5:32) - Main Topic\n\
+                           [1:02:03] | Outro\n\
+                           Follow me at example.com";
+        assert_eq!(
+            spans(&parse_description(description, 4000.0)),
+            [
+                ("Intro", 0.0, 332.0),
+                ("Main Topic", 332.0, 3723.0),
+                ("Outro", 3723.0, 4000.0)
+            ]
+        );
+    }
+
+    #[test]
+    fn ignores_timestamps_that_go_backwards_or_past_the_end() {
+        let description = "0:00 Intro\n2:00 Main\n1:00 Mentioned earlier\n9:00 Too late";
+        assert_eq!(
+            spans(&parse_description(description, 300.0)),
+            [("Intro", 0.0, 120.0), ("Main", 120.0, 300.0)]
+        );
+    }
+
+    #[test]
+    fn builds_chapters_from_clip_ranges() {
+        let ranges = ["1:00-2:30.5".to_string(), "12.5-20".to_string()];
+        let titles = [Some("Highlight".to_string())];
+        assert_eq!(
+            spans(&clip_chapters(&ranges, &titles).unwrap()),
+            [
+                ("Highlight", 60.0, 150.5),
+                ("00h00m13s-00h00m20s", 12.5, 20.0)
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_clip_ranges() {
+        for range in ["1:00", "2:00-1:00", "a-1:00", "1:00:00:00-2", "-5-10"] {
+            assert!(
+                clip_chapters(&[range.to_string()], &[]).is_err(),
+                "{} was accepted",
+                range
+            );
+        }
+    }
+
+    #[test]
+    fn accepts_well_formed_chapters() {
+        let chapters = chapters(&[
+            ("Intro", 0.0, 30.0),
+            ("Main", 30.0, 90.0),
+            ("Outro", 95.0, 100.0),
+        ]);
+        assert!(validate_chapters(&chapters).is_empty());
+    }
+
+    #[test]
+    fn warns_about_empty_chapters() {
+        let chapters = chapters(&[("Intro", 0.0, 30.0), ("Empty", 30.0, 30.0)]);
+        assert_eq!(
+            validate_chapters(&chapters),
+            [ChapterWarning::ZeroDuration(2)]
+        );
+    }
+
+    #[test]
+    fn warns_about_chapters_ending_before_they_start() {
+        let chapters = chapters(&[("Backwards", 30.0, 10.0), ("Main", 30.0, 60.0)]);
+        assert_eq!(
+            validate_chapters(&chapters),
+            [ChapterWarning::NegativeDuration(1)]
+        );
+    }
+
+    #[test]
+    fn warns_about_overlapping_chapters() {
+        let chapters = chapters(&[("Intro", 0.0, 35.0), ("Main", 30.0, 60.0)]);
+        assert_eq!(
+            validate_chapters(&chapters),
+            [ChapterWarning::Overlap { a: 1, b: 2 }]
+        );
+    }
+
+    #[test]
+    fn warns_about_out_of_order_chapters() {
+        let chapters = chapters(&[("Main", 30.0, 60.0), ("Intro", 0.0, 30.0)]);
+        assert_eq!(
+            validate_chapters(&chapters),
+            [ChapterWarning::OutOfOrder(2)]
+        );
+    }
+
+    fn gap_spans(gaps: &[Gap]) -> Vec<(f64, f64, Option<usize>, Option<usize>)> {
+        gaps.iter()
+            .map(|gap| (gap.start, gap.end, gap.after_chapter, gap.before_chapter))
+            .collect()
+    }
+
+    #[test]
+    fn finds_gaps_before_between_and_after_chapters() {
+        let chapters = chapters(&[("Intro", 5.0, 30.0), ("Main", 40.0, 90.0)]);
+        assert_eq!(
+            gap_spans(&find_gaps(&chapters, Some(100.0), 0.0)),
+            [
+                (0.0, 5.0, None, Some(1)),
+                (30.0, 40.0, Some(1), Some(2)),
+                (90.0, 100.0, Some(2), None)
+            ]
+        );
+        // Without a duration, the end of the video is unknown.
+        assert_eq!(find_gaps(&chapters, None, 0.0).len(), 2);
+    }
+
+    #[test]
+    fn leaves_out_gaps_shorter_than_the_minimum() {
+        let chapters = chapters(&[("Intro", 0.5, 30.0), ("Main", 40.0, 99.5)]);
+        assert_eq!(
+            gap_spans(&find_gaps(&chapters, Some(100.0), 1.0)),
+            [(30.0, 40.0, Some(1), Some(2))]
+        );
+    }
+
+    #[test]
+    fn overlapping_chapters_leave_no_gap() {
+        let chapters = chapters(&[
+            ("Long", 0.0, 60.0),
+            ("Inside", 10.0, 20.0),
+            ("Overlapping", 50.0, 80.0),
+            ("After", 85.0, 100.0),
+        ]);
+        assert_eq!(
+            gap_spans(&find_gaps(&chapters, Some(100.0), 0.0)),
+            [(80.0, 85.0, Some(3), Some(4))]
+        );
+    }
+
+    #[test]
+    fn merges_runs_of_matching_chapters() {
+        let chapters = chapters(&[
+            ("Intro", 0.0, 1.0),
+            ("Part 1", 1.0, 2.0),
+            ("Part 2", 2.0, 3.0),
+            ("Break", 3.0, 4.0),
+            ("Part 3", 4.0, 5.0),
+            ("Outro", 5.0, 6.0),
+        ]);
+        let pattern = Regex::new("^Part").unwrap();
+        // A lone match isn't a group.
+        let groups = merge_groups(&chapters, &pattern);
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0], 1..3);
+    }
+
+    #[test]
+    fn merges_runs_at_either_end() {
+        let chapters = chapters(&[
+            ("Part 1", 0.0, 1.0),
+            ("Part 2", 1.0, 2.0),
+            ("Break", 2.0, 3.0),
+            ("Part 3", 3.0, 4.0),
+            ("Part 4", 4.0, 5.0),
+            ("Part 5", 5.0, 6.0),
+        ]);
+        let pattern = Regex::new("^Part").unwrap();
+        assert_eq!(merge_groups(&chapters, &pattern), [0..2, 3..6]);
+    }
+
+    #[test]
+    fn pads_chapters_within_the_video() {
+        let chapters = chapters(&[
+            ("Intro", 1.0, 30.0),
+            ("Main", 30.0, 90.0),
+            ("Outro", 90.0, 99.0),
+        ]);
+        assert_eq!(
+            spans(&pad_chapters(&chapters, 2.0, 3.0, Some(100.0))),
+            [
+                ("Intro", 0.0, 33.0),
+                ("Main", 28.0, 93.0),
+                ("Outro", 88.0, 100.0)
+            ]
+        );
+        // Without a duration, the end can't be clamped.
+        assert_eq!(
+            spans(&pad_chapters(&chapters[2..], 0.0, 3.0, None)),
+            [("Outro", 90.0, 102.0)]
+        );
+    }
+}
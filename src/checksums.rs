@@ -0,0 +1,111 @@
+use anyhow::{Context, Result};
+use sha2::{Digest, Sha256};
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+
+use crate::watch::STATE_FILE;
+use crate::FULL_VIDEO_STEM;
+
+pub const CHECKSUM_FILE: &str = "checksums.sha256";
+
+/// Writes the SHA-256 of every file under `output_dir` to `checksums.sha256`
+/// in `sha256sum` format. The downloaded full video is left out.
+pub fn write_checksums(output_dir: &Path) -> Result<PathBuf> {
+    let mut files = Vec::new();
+    collect_files(output_dir, &mut files)?;
+    files.sort();
+
+    let mut contents = String::new();
+    for path in files {
+        let relative = path.strip_prefix(output_dir).unwrap_or(&path);
+        let name = relative.to_string_lossy();
+        // The --watch state is rewritten after the checksums.
+        if name == CHECKSUM_FILE || name == STATE_FILE || name.starts_with(FULL_VIDEO_STEM) {
+            continue;
+        }
+        contents.push_str(&format!("{}  {}\n", hash_file(&path)?, name));
+    }
+
+    let checksum_path = output_dir.join(CHECKSUM_FILE);
+    fs::write(&checksum_path, contents)
+        .with_context(|| format!("Failed to write {}", checksum_path.display()))?;
+
+    Ok(checksum_path)
+}
+
+/// Checks every file listed in `path`, which is either a `checksums.sha256`
+/// file or a directory containing one. Returns the files that are missing or
+/// don't match.
+pub fn verify_checksums(path: &Path) -> Result<Vec<String>> {
+    let checksum_path = if path.is_dir() {
+        path.join(CHECKSUM_FILE)
+    } else {
+        path.to_path_buf()
+    };
+    let base_dir = checksum_path.parent().unwrap_or(Path::new("."));
+
+    let file = File::open(&checksum_path)
+        .with_context(|| format!("Failed to open {}", checksum_path.display()))?;
+
+    let mut failures = Vec::new();
+    for line in BufReader::new(file).lines() {
+        let line = line.with_context(|| format!("Failed to read {}", checksum_path.display()))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let (expected, name) = line
+            .split_once("  ")
+            .with_context(|| format!("Invalid checksum line: {}", line))?;
+
+        match hash_file(&base_dir.join(name)) {
+            Ok(actual) if actual.eq_ignore_ascii_case(expected) => status!("{}: OK", name),
+            Ok(_) => {
+                status!("{}: FAILED", name);
+                failures.push(name.to_string());
+            }
+            Err(_) => {
+                status!("{}: MISSING", name);
+                failures.push(name.to_string());
+            }
+        }
+    }
+
+    Ok(failures)
+}
+
+/// Hashes `path` without reading it into memory all at once.
+fn hash_file(path: &Path) -> Result<String> {
+    let mut file =
+        File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let mut hasher = Sha256::new();
+    let mut buf = [0; 64 * 1024];
+    loop {
+        let n = file
+            .read(&mut buf)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect())
+}
+
+pub fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    for entry in fs::read_dir(dir).with_context(|| format!("Failed to read {}", dir.display()))? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_files(&path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
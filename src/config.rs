@@ -0,0 +1,156 @@
+use anyhow::{Context, Result};
+use clap::parser::ValueSource;
+use clap::{ArgMatches, CommandFactory};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::Args;
+
+/// Flags that only make sense on the command line and are never read from
+/// or written to a config file.
+const CLI_ONLY_KEYS: &[&str] = &[
+    "url",
+    "config",
+    "profile",
+    "print_config",
+    "print_profiles",
+    "version",
+    "verify_checksums",
+];
+
+/// Persistent settings loaded from `config.toml`.
+///
+/// Any `Args` flag can be given a default value using its long name (either
+/// `keep_full` or `keep-full`). Named sets of flags can be defined as
+/// `[profiles.NAME]` tables and applied with `--profile NAME`.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct Config {
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub profiles: BTreeMap<String, toml::Table>,
+
+    /// Default values for command-line flags.
+    #[serde(flatten)]
+    pub flags: toml::Table,
+}
+
+impl Config {
+    /// Loads the config from `path`, or from the default location when `path`
+    /// is `None`. A missing default config file is not an error.
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        let (path, explicit) = match path {
+            Some(path) => (path.to_path_buf(), true),
+            None => match default_path() {
+                Some(path) => (path, false),
+                None => return Ok(Config::default()),
+            },
+        };
+
+        if !explicit && !path.exists() {
+            return Ok(Config::default());
+        }
+
+        let contents = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read config file {}", path.display()))?;
+
+        toml::from_str(&contents)
+            .with_context(|| format!("Failed to parse config file {}", path.display()))
+    }
+
+    /// Layers the flags of profile `name` over the config's own, so that
+    /// command-line flags override the profile and the profile overrides the
+    /// rest of the config.
+    pub fn with_profile(mut self, name: Option<&str>) -> Result<Self> {
+        let Some(name) = name else {
+            return Ok(self);
+        };
+
+        let profile = self.profiles.get(name).with_context(|| {
+            let available: Vec<&str> = self.profiles.keys().map(String::as_str).collect();
+            format!(
+                "Unknown profile '{}'. Available profiles: {}",
+                name,
+                if available.is_empty() {
+                    "none".to_string()
+                } else {
+                    available.join(", ")
+                }
+            )
+        })?;
+
+        for (key, value) in profile.clone() {
+            // Replace the base value whichever spelling it used.
+            self.flags.remove(&key.replace('-', "_"));
+            self.flags.remove(&key.replace('_', "-"));
+            self.flags.insert(key, value);
+        }
+
+        Ok(self)
+    }
+
+    /// Renders every profile as TOML.
+    pub fn profiles_toml(&self) -> Result<String> {
+        let profiles = Config {
+            profiles: self.profiles.clone(),
+            flags: toml::Table::new(),
+        };
+        toml::to_string_pretty(&profiles).context("Failed to serialize profiles")
+    }
+
+    /// Fills in every flag that was not given on the command line with the
+    /// value from this config. Command-line values always win.
+    pub fn apply(&self, args: Args, matches: &ArgMatches) -> Result<Args> {
+        let command = Args::command();
+        let mut merged = toml::Table::try_from(&args).context("Failed to serialize arguments")?;
+
+        for (key, value) in &self.flags {
+            let id = key.replace('-', "_");
+
+            if CLI_ONLY_KEYS.contains(&id.as_str())
+                || !command
+                    .get_arguments()
+                    .any(|arg| arg.get_id() == id.as_str())
+            {
+                anyhow::bail!("Unknown config key '{}'", key);
+            }
+
+            if matches.value_source(&id) != Some(ValueSource::CommandLine) {
+                merged.insert(id, value.clone());
+            }
+        }
+
+        let mut merged: Args = merged.try_into().context("Invalid value in config file")?;
+        merged.command = args.command;
+        Ok(merged)
+    }
+
+    /// Whether the flag `id` was given on the command line or in this config.
+    pub fn is_set(&self, id: &str, matches: &ArgMatches) -> bool {
+        matches.value_source(id) == Some(ValueSource::CommandLine)
+            || self.flags.contains_key(id)
+            || self.flags.contains_key(&id.replace('_', "-"))
+    }
+
+    /// Renders the effective configuration (config file merged with
+    /// command-line flags) as TOML.
+    pub fn to_effective_toml(&self, args: &Args) -> Result<String> {
+        let mut table = toml::Table::try_from(args).context("Failed to serialize arguments")?;
+        for key in CLI_ONLY_KEYS {
+            table.remove(*key);
+        }
+
+        let effective = Config {
+            profiles: BTreeMap::new(),
+            flags: table,
+        };
+
+        toml::to_string_pretty(&effective).context("Failed to serialize configuration")
+    }
+}
+
+/// `~/.config/yt-clipper/config.toml` on Linux, `%APPDATA%\yt-clipper\config.toml`
+/// on Windows and the platform equivalent elsewhere.
+pub fn default_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("yt-clipper").join("config.toml"))
+}
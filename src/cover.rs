@@ -0,0 +1,92 @@
+use anyhow::{Context, Result};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use url::Url;
+
+use crate::audio::AudioFormat;
+use crate::executables::Executables;
+use crate::progress;
+
+/// Downloads the video thumbnail at `url` and embeds it as cover art into
+/// every clip in `clips`, which must all be `format` audio files.
+pub fn embed_cover_art(
+    executables: &Executables,
+    url: &str,
+    proxy: Option<&Url>,
+    clips: &[PathBuf],
+    format: AudioFormat,
+) -> Result<()> {
+    let cover = download(url, proxy)?;
+
+    let pb = progress::bar(clips.len());
+
+    for clip in clips {
+        let name = clip.file_name().unwrap_or_default().to_string_lossy();
+        pb.set_message(format!("Embedding cover art: {}", name));
+
+        if let Err(e) = embed(executables, clip, cover.path(), format) {
+            pb.finish_and_clear();
+            return Err(e).with_context(|| format!("Failed to embed cover art into {}", name));
+        }
+
+        pb.inc(1);
+    }
+
+    pb.finish_with_message("Cover art embedded");
+
+    Ok(())
+}
+
+/// Downloads `url` to a temporary file, which is deleted when dropped.
+fn download(url: &str, proxy: Option<&Url>) -> Result<tempfile::NamedTempFile> {
+    let mut config = ureq::Agent::config_builder();
+    if let Some(proxy) = proxy {
+        let proxy = ureq::Proxy::new(proxy.as_str()).context("Unsupported proxy")?;
+        config = config.proxy(Some(proxy));
+    }
+    let agent = config.build().new_agent();
+
+    let mut response = agent
+        .get(url)
+        .call()
+        .context("Failed to download thumbnail")?;
+
+    let mut file = tempfile::NamedTempFile::new().context("Failed to create temporary file")?;
+    io::copy(&mut response.body_mut().as_reader(), &mut file)
+        .context("Failed to download thumbnail")?;
+
+    Ok(file)
+}
+
+/// Re-muxes `clip` with `cover` as an attached picture. The audio is copied;
+/// the picture is converted to JPEG, which every player understands.
+fn embed(executables: &Executables, clip: &Path, cover: &Path, format: AudioFormat) -> Result<()> {
+    let mut tmp_name = clip.file_stem().unwrap_or_default().to_os_string();
+    tmp_name.push(".cover.");
+    tmp_name.push(clip.extension().unwrap_or_default());
+    let tmp_path = clip.with_file_name(tmp_name);
+
+    let mut command = executables.ffmpeg();
+    command
+        .args(["-i", clip.to_str().unwrap(), "-i", cover.to_str().unwrap()])
+        .args([
+            "-map", "0:a", "-map", "1:v", "-c:a", "copy", "-c:v", "mjpeg",
+        ])
+        .args(["-disposition:v:0", "attached_pic"])
+        .args(["-metadata:s:v:0", "comment=Cover (front)"]);
+    // ID3v2.3 is the newest version older players can read.
+    if format == AudioFormat::Mp3 {
+        command.args(["-id3v2_version", "3"]);
+    }
+    command.args(["-y", tmp_path.to_str().unwrap()]);
+
+    if let Err(e) = executables.run(&mut command) {
+        let _ = fs::remove_file(&tmp_path);
+        return Err(e);
+    }
+
+    fs::rename(&tmp_path, clip).context("Failed to replace clip with tagged version")?;
+
+    Ok(())
+}
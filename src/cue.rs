@@ -0,0 +1,104 @@
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::audio::AudioFormat;
+use crate::executables::Executables;
+use crate::probe::{self, MediaInfo};
+use crate::Chapter;
+
+pub const CUE_FILE: &str = "playlist.cue";
+
+/// File name (without extension) of the `--concat-audio` output.
+pub const CONCAT_STEM: &str = "full_audio";
+
+/// CUE frames per second.
+const FRAMES_PER_SECOND: u64 = 75;
+
+/// Album-level details of the cue sheet.
+pub struct CueAlbum<'a> {
+    pub title: &'a str,
+    pub performer: Option<&'a str>,
+    pub format: AudioFormat,
+}
+
+/// Writes a CUE sheet of the audio-only `clips` to `output`. Each clip is
+/// its own `FILE`, unless `concatenated` names a file holding all of them in
+/// order, in which case every track indexes into that one file. Clips that
+/// don't exist are left out.
+pub fn write_cue_sheet(
+    executables: &Executables,
+    clips: &[(PathBuf, &Chapter)],
+    concatenated: Option<&Path>,
+    album: &CueAlbum,
+    output: &Path,
+) -> Result<()> {
+    let file_type = file_type(album.format);
+    let mut cue = String::new();
+    if let Some(performer) = album.performer {
+        cue.push_str(&format!("PERFORMER {}\n", quote(performer)));
+    }
+    cue.push_str(&format!("TITLE {}\n", quote(album.title)));
+    if let Some(path) = concatenated {
+        cue.push_str(&format!("FILE {} {}\n", quote(&file_name(path)), file_type));
+    }
+
+    let mut offset = 0.0;
+    let clips = clips.iter().filter(|(path, _)| path.exists());
+    for (track, (path, chapter)) in clips.enumerate() {
+        let start = if concatenated.is_some() {
+            offset
+        } else {
+            cue.push_str(&format!("FILE {} {}\n", quote(&file_name(path)), file_type));
+            0.0
+        };
+        cue.push_str(&format!("  TRACK {:02} AUDIO\n", track + 1));
+        cue.push_str(&format!("    TITLE {}\n", quote(&chapter.title)));
+        if let Some(performer) = album.performer {
+            cue.push_str(&format!("    PERFORMER {}\n", quote(performer)));
+        }
+        cue.push_str(&format!("    INDEX 01 {}\n", cue_time(start)));
+
+        if concatenated.is_some() {
+            // The clips' real lengths, which can differ from the chapter's
+            // after --speed or --trim-silence.
+            offset += probe::probe(executables, path)
+                .ok()
+                .as_ref()
+                .and_then(MediaInfo::duration)
+                .unwrap_or(chapter.end_time - chapter.start_time);
+        }
+    }
+
+    fs::write(output, cue).with_context(|| format!("Failed to write {}", output.display()))
+}
+
+/// The CUE `FILE` type: players take `WAVE` for any format CUE has no name for.
+fn file_type(format: AudioFormat) -> &'static str {
+    match format {
+        AudioFormat::Mp3 => "MP3",
+        _ => "WAVE",
+    }
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// CUE strings can't escape quotes, so double quotes become single quotes.
+fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('"', "'"))
+}
+
+/// Formats seconds as CUE's `MM:SS:FF`, with 75 frames per second.
+fn cue_time(seconds: f64) -> String {
+    let frames = (seconds * FRAMES_PER_SECOND as f64).round() as u64;
+    format!(
+        "{:02}:{:02}:{:02}",
+        frames / (FRAMES_PER_SECOND * 60),
+        frames / FRAMES_PER_SECOND % 60,
+        frames % FRAMES_PER_SECOND
+    )
+}
@@ -0,0 +1,254 @@
+use anyhow::{Context, Result};
+use rusqlite::types::ValueRef;
+use rusqlite::{params, Connection};
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+
+use crate::Chapter;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS videos (
+    id TEXT PRIMARY KEY,
+    url TEXT NOT NULL,
+    title TEXT NOT NULL,
+    processed_at TEXT NOT NULL,
+    output_dir TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS clips (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    video_id TEXT NOT NULL REFERENCES videos(id) ON DELETE CASCADE,
+    chapter_index INTEGER NOT NULL,
+    title TEXT NOT NULL,
+    start_time REAL NOT NULL,
+    end_time REAL NOT NULL,
+    file_path TEXT NOT NULL,
+    file_size_bytes INTEGER,
+    UNIQUE (video_id, chapter_index)
+);
+";
+
+/// The `--db` database of processed videos and the clips made from them.
+pub struct Database {
+    conn: Connection,
+}
+
+impl Database {
+    /// Opens the database at `path`, creating it and its tables if needed.
+    pub fn open(path: &Path) -> Result<Self> {
+        let conn = Connection::open(path)
+            .with_context(|| format!("Failed to open database {}", path.display()))?;
+        conn.execute_batch(SCHEMA)
+            .context("Failed to create database tables")?;
+        Ok(Database { conn })
+    }
+
+    /// Records that a video is being processed. With `replace_clips`, the
+    /// clips of any earlier run of the same video are forgotten; without it
+    /// (`--incremental`, which only extracts new chapters) they are kept.
+    pub fn record_video(
+        &self,
+        id: &str,
+        url: &str,
+        title: &str,
+        output_dir: &Path,
+        replace_clips: bool,
+    ) -> Result<()> {
+        self.conn
+            .execute(
+                "INSERT INTO videos (id, url, title, processed_at, output_dir)
+                 VALUES (?1, ?2, ?3, datetime('now'), ?4)
+                 ON CONFLICT (id) DO UPDATE SET
+                     url = excluded.url,
+                     title = excluded.title,
+                     processed_at = excluded.processed_at,
+                     output_dir = excluded.output_dir",
+                params![id, url, title, absolute(output_dir)],
+            )
+            .context("Failed to record video in database")?;
+        if replace_clips {
+            self.conn
+                .execute("DELETE FROM clips WHERE video_id = ?1", params![id])
+                .context("Failed to clear old clips from database")?;
+        }
+        Ok(())
+    }
+
+    /// Records (or updates) the clip made from `chapter`, including its
+    /// current file size.
+    pub fn record_clip(&self, video_id: &str, chapter: &Chapter, path: &Path) -> Result<()> {
+        let size = fs::metadata(path)
+            .ok()
+            .map(|metadata| metadata.len() as i64);
+        self.conn
+            .execute(
+                "INSERT INTO clips
+                     (video_id, chapter_index, title, start_time, end_time, file_path, file_size_bytes)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+                 ON CONFLICT (video_id, chapter_index) DO UPDATE SET
+                     title = excluded.title,
+                     start_time = excluded.start_time,
+                     end_time = excluded.end_time,
+                     file_path = excluded.file_path,
+                     file_size_bytes = excluded.file_size_bytes",
+                params![
+                    video_id,
+                    chapter.number as i64,
+                    chapter.title,
+                    chapter.start_time,
+                    chapter.end_time,
+                    absolute(path),
+                    size
+                ],
+            )
+            .context("Failed to record clip in database")?;
+        Ok(())
+    }
+
+    /// Runs `sql` and prints the rows it returns as a table.
+    pub fn print_query(&self, sql: &str) -> Result<()> {
+        let mut statement = self.conn.prepare(sql).context("Invalid query")?;
+        let columns: Vec<String> = statement
+            .column_names()
+            .into_iter()
+            .map(String::from)
+            .collect();
+
+        let mut rows: Vec<Vec<String>> = Vec::new();
+        let mut results = statement.query([]).context("Query failed")?;
+        while let Some(row) = results.next().context("Query failed")? {
+            rows.push(
+                (0..columns.len())
+                    .map(|i| row.get_ref(i).map(format_value))
+                    .collect::<rusqlite::Result<_>>()?,
+            );
+        }
+
+        if columns.is_empty() {
+            println!("OK");
+            return Ok(());
+        }
+
+        let widths: Vec<usize> = (0..columns.len())
+            .map(|i| {
+                rows.iter()
+                    .map(|row| row[i].chars().count())
+                    .chain([columns[i].chars().count()])
+                    .max()
+                    .unwrap_or(0)
+            })
+            .collect();
+
+        let print_row = |cells: &[String]| {
+            let line: Vec<String> = cells
+                .iter()
+                .zip(&widths)
+                .map(|(cell, &width)| format!("{:<width$}", cell, width = width))
+                .collect();
+            println!("{}", line.join("  ").trim_end());
+        };
+
+        print_row(&columns);
+        print_row(
+            &widths
+                .iter()
+                .map(|&width| "-".repeat(width))
+                .collect::<Vec<_>>(),
+        );
+        for row in &rows {
+            print_row(row);
+        }
+        println!("({} rows)", rows.len());
+
+        Ok(())
+    }
+}
+
+/// A video's handle on the shared database, used while splitting its chapters.
+#[derive(Clone, Copy)]
+pub struct VideoRecord<'a> {
+    pub database: &'a Mutex<Database>,
+    pub video_id: &'a str,
+}
+
+impl VideoRecord<'_> {
+    /// Records a finished clip. Failing to do so only produces a warning.
+    pub fn clip(&self, chapter: &Chapter, path: &Path) {
+        if let Err(e) = self
+            .database
+            .lock()
+            .unwrap()
+            .record_clip(self.video_id, chapter, path)
+        {
+            estatus!("Warning: {:#}", e);
+        }
+    }
+}
+
+fn format_value(value: ValueRef) -> String {
+    match value {
+        ValueRef::Null => "NULL".to_string(),
+        ValueRef::Integer(i) => i.to_string(),
+        ValueRef::Real(f) => f.to_string(),
+        ValueRef::Text(text) => String::from_utf8_lossy(text).into_owned(),
+        ValueRef::Blob(blob) => format!("<{} bytes>", blob.len()),
+    }
+}
+
+fn absolute(path: &Path) -> String {
+    fs::canonicalize(path)
+        .unwrap_or_else(|_| path.to_path_buf())
+        .to_string_lossy()
+        .into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn clip_count(database: &Database) -> i64 {
+        database
+            .conn
+            .query_row("SELECT COUNT(*) FROM clips", [], |row| row.get(0))
+            .unwrap()
+    }
+
+    #[test]
+    fn keeps_earlier_clips_unless_replacing_them() {
+        let dir = tempfile::tempdir().unwrap();
+        let database = Database::open(&dir.path().join("clips.db")).unwrap();
+        let chapter = |number| Chapter {
+            title: format!("Chapter {}", number),
+            start_time: 0.0,
+            end_time: 1.0,
+            number,
+        };
+        let record = |replace_clips| {
+            database
+                .record_video(
+                    "id",
+                    "https://youtu.be/id",
+                    "Video",
+                    dir.path(),
+                    replace_clips,
+                )
+                .unwrap()
+        };
+
+        record(true);
+        for number in [1, 2] {
+            let clip = dir.path().join(format!("{}.mp4", number));
+            database.record_clip("id", &chapter(number), &clip).unwrap();
+        }
+        assert_eq!(clip_count(&database), 2);
+
+        // An incremental run only records the chapters it adds.
+        record(false);
+        let clip = dir.path().join("3.mp4");
+        database.record_clip("id", &chapter(3), &clip).unwrap();
+        assert_eq!(clip_count(&database), 3);
+
+        record(true);
+        assert_eq!(clip_count(&database), 0);
+    }
+}
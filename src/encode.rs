@@ -0,0 +1,724 @@
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::audio::{self, AudioChannels};
+use crate::speed::SpeedSettings;
+use crate::watermark::Watermark;
+use crate::Args;
+
+/// x264/x265 speed presets, from fastest to best compression.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Preset {
+    Ultrafast,
+    Superfast,
+    Veryfast,
+    Faster,
+    Fast,
+    Medium,
+    Slow,
+    Slower,
+    Veryslow,
+}
+
+impl Preset {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Preset::Ultrafast => "ultrafast",
+            Preset::Superfast => "superfast",
+            Preset::Veryfast => "veryfast",
+            Preset::Faster => "faster",
+            Preset::Fast => "fast",
+            Preset::Medium => "medium",
+            Preset::Slow => "slow",
+            Preset::Slower => "slower",
+            Preset::Veryslow => "veryslow",
+        }
+    }
+}
+
+/// Video codecs clips can be re-encoded to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VideoCodec {
+    H264,
+    Hevc,
+    Av1,
+    Vp9,
+}
+
+impl VideoCodec {
+    pub fn encoder(self) -> &'static str {
+        match self {
+            VideoCodec::H264 => "libx264",
+            VideoCodec::Hevc => "libx265",
+            VideoCodec::Av1 => "libaom-av1",
+            VideoCodec::Vp9 => "libvpx-vp9",
+        }
+    }
+}
+
+/// Opus bitrate used by `--webm` unless `--audio-bitrate` is given.
+pub const WEBM_AUDIO_BITRATE: &str = "128k";
+
+/// Default `--video-codec`.
+pub const DEFAULT_VIDEO_CODEC: &str = "libx264";
+
+/// Default `--audio-codec`.
+pub const DEFAULT_AUDIO_CODEC: &str = "aac";
+
+/// The CRF used when `--crf` is not given. x265, libaom and libvpx use
+/// different scales than x264, so each gets its own visually similar default.
+fn default_crf(video_codec: &str) -> u32 {
+    match video_codec {
+        "libx265" => 28,
+        "libaom-av1" => 30,
+        "libvpx-vp9" => 33,
+        _ => 23,
+    }
+}
+
+/// Curves the `tonemap` filter can use to map HDR brightness onto SDR.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ToneMapAlgorithm {
+    Hable,
+    Reinhard,
+    Mobius,
+    Linear,
+}
+
+impl ToneMapAlgorithm {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ToneMapAlgorithm::Hable => "hable",
+            ToneMapAlgorithm::Reinhard => "reinhard",
+            ToneMapAlgorithm::Mobius => "mobius",
+            ToneMapAlgorithm::Linear => "linear",
+        }
+    }
+
+    /// Converts HDR (PQ/HLG, BT.2020) frames to 8-bit BT.709 SDR.
+    fn filter(self) -> String {
+        format!(
+            "zscale=t=linear:npl=100,format=gbrpf32le,zscale=p=bt709,\
+             tonemap=tonemap={}:desat=0,zscale=t=bt709:m=bt709:r=tv,format=yuv420p",
+            self.as_str()
+        )
+    }
+}
+
+/// Filters `--deinterlace` can use.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DeinterlaceMode {
+    Yadif,
+    /// Slower than yadif, with fewer artifacts on motion.
+    Bwdif,
+}
+
+impl DeinterlaceMode {
+    /// Outputs one frame per field, so the motion of both fields is kept.
+    fn filter(self) -> &'static str {
+        match self {
+            DeinterlaceMode::Yadif => "yadif=1",
+            DeinterlaceMode::Bwdif => "bwdif=1",
+        }
+    }
+}
+
+/// Noise reduction filters `--denoise` can use.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DenoiseFilter {
+    /// Fast spatial/temporal denoiser.
+    Hqdn3d,
+    /// Non-local means: much slower, but keeps more detail.
+    Nlmeans,
+}
+
+/// `hqdn3d`'s luma spatial, chroma spatial, luma temporal and chroma temporal
+/// strengths at `--denoise-strength 1`.
+const HQDN3D_DEFAULTS: [f64; 4] = [2.0, 1.5, 6.0, 4.5];
+
+/// A `--denoise` filter at a `--denoise-strength`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Denoise {
+    pub filter: DenoiseFilter,
+    /// Multiplies every parameter of the filter.
+    pub strength: f64,
+}
+
+impl Denoise {
+    pub fn from_args(args: &Args) -> Option<Self> {
+        args.denoise.then_some(Denoise {
+            filter: args.denoise_filter,
+            strength: args.denoise_strength,
+        })
+    }
+
+    pub fn filter(&self) -> String {
+        match self.filter {
+            DenoiseFilter::Hqdn3d => {
+                let params: Vec<String> = HQDN3D_DEFAULTS
+                    .iter()
+                    .map(|param| format!("{}", param * self.strength))
+                    .collect();
+                format!("hqdn3d={}", params.join(":"))
+            }
+            DenoiseFilter::Nlmeans => format!("nlmeans=s={}", self.strength),
+        }
+    }
+}
+
+/// Parses a `--denoise-strength` multiplier.
+pub fn parse_denoise_strength(value: &str) -> Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(strength) if strength > 0.0 && strength.is_finite() => Ok(strength),
+        _ => Err(format!(
+            "invalid strength \"{}\", expected a positive number such as 1.5",
+            value
+        )),
+    }
+}
+
+/// How `--scale` fits the video into the target size.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ScaleMethod {
+    /// Scale to fit inside the size and pad the rest with black bars.
+    Fit,
+    /// Scale to cover the size and crop what sticks out.
+    Fill,
+    /// Scale to exactly the size, ignoring the aspect ratio.
+    Stretch,
+}
+
+/// A `--scale` target size.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Scale {
+    pub width: u32,
+    pub height: u32,
+    pub method: ScaleMethod,
+}
+
+impl Scale {
+    pub fn from_args(args: &Args) -> Option<Self> {
+        args.scale.map(|(width, height)| Scale {
+            width,
+            height,
+            method: args.scale_method,
+        })
+    }
+
+    pub fn filter(&self) -> String {
+        let (w, h) = (self.width, self.height);
+        match self.method {
+            ScaleMethod::Fit => format!(
+                "scale={w}:{h}:force_original_aspect_ratio=decrease,pad={w}:{h}:-1:-1:color=black"
+            ),
+            ScaleMethod::Fill => {
+                format!("scale={w}:{h}:force_original_aspect_ratio=increase,crop={w}:{h}")
+            }
+            ScaleMethod::Stretch => format!("scale={w}:{h}"),
+        }
+    }
+}
+
+/// Parses a `--scale` size such as `1920x1080`. Both sides have to be even,
+/// which most encoders require.
+pub fn parse_scale(value: &str) -> Result<(u32, u32), String> {
+    match value
+        .split_once('x')
+        .and_then(|(w, h)| Some((w.parse::<u32>().ok()?, h.parse::<u32>().ok()?)))
+    {
+        Some((w, h)) if w > 0 && h > 0 && w % 2 == 0 && h % 2 == 0 => Ok((w, h)),
+        _ => Err(format!(
+            "invalid size \"{}\", expected an even WIDTHxHEIGHT such as 1920x1080",
+            value
+        )),
+    }
+}
+
+/// An ffmpeg filter chain, built up in order. Empty filters are left out.
+#[derive(Debug, Clone, Default)]
+pub struct FilterChain {
+    filters: Vec<String>,
+}
+
+impl FilterChain {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends `filter`, if there is one.
+    pub fn push(mut self, filter: Option<impl Into<String>>) -> Self {
+        if let Some(filter) = filter.map(Into::into) {
+            if !filter.is_empty() {
+                self.filters.push(filter);
+            }
+        }
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.filters.is_empty()
+    }
+
+    /// The filters joined with commas.
+    pub fn build(&self) -> String {
+        self.filters.join(",")
+    }
+}
+
+/// Hardware video encoders that can replace the software codec.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HwAccel {
+    Nvenc,
+    Vaapi,
+    Videotoolbox,
+    Amf,
+}
+
+impl HwAccel {
+    pub fn encoder(self) -> &'static str {
+        match self {
+            HwAccel::Nvenc => "h264_nvenc",
+            HwAccel::Vaapi => "h264_vaapi",
+            HwAccel::Videotoolbox => "h264_videotoolbox",
+            HwAccel::Amf => "h264_amf",
+        }
+    }
+
+    /// The name this backend is listed under in `ffmpeg -hwaccels`.
+    pub fn hwaccel_name(self) -> &'static str {
+        match self {
+            HwAccel::Nvenc => "cuda",
+            HwAccel::Vaapi => "vaapi",
+            HwAccel::Videotoolbox => "videotoolbox",
+            HwAccel::Amf => "d3d11va",
+        }
+    }
+
+    /// Hardware encoders don't support CRF, so map it onto each encoder's
+    /// constant quality option.
+    fn quality_args(self, crf: u32) -> Vec<String> {
+        match self {
+            HwAccel::Nvenc => vec!["-cq".into(), crf.to_string()],
+            HwAccel::Vaapi => vec!["-qp".into(), crf.to_string()],
+            // VideoToolbox quality runs from 1 (worst) to 100 (best).
+            HwAccel::Videotoolbox => {
+                vec![
+                    "-q:v".into(),
+                    100u32.saturating_sub(crf * 2).max(1).to_string(),
+                ]
+            }
+            HwAccel::Amf => vec![
+                "-rc".into(),
+                "cqp".into(),
+                "-qp_i".into(),
+                crf.to_string(),
+                "-qp_p".into(),
+                crf.to_string(),
+            ],
+        }
+    }
+}
+
+/// Containers that clips can be written in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Container {
+    #[default]
+    Mp4,
+    Mkv,
+    Webm,
+}
+
+impl Container {
+    pub fn extension(self) -> &'static str {
+        match self {
+            Container::Mp4 => "mp4",
+            Container::Mkv => "mkv",
+            Container::Webm => "webm",
+        }
+    }
+
+    /// WebM only holds VP8/VP9/AV1 video and Vorbis/Opus audio, so stream
+    /// copying a typical H.264/AAC source into it fails.
+    pub fn requires_reencode(self) -> bool {
+        self == Container::Webm
+    }
+
+    /// The codec soft subtitle tracks are stored with.
+    pub fn subtitle_codec(self) -> &'static str {
+        match self {
+            Container::Mp4 => "mov_text",
+            Container::Mkv => "srt",
+            Container::Webm => "webvtt",
+        }
+    }
+}
+
+/// Parses a `--fps` frame rate.
+pub fn parse_fps(value: &str) -> Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(fps) if fps > 0.0 && fps.is_finite() => Ok(fps),
+        _ => Err(format!(
+            "invalid frame rate \"{}\", expected a positive number such as 30 or 29.97",
+            value
+        )),
+    }
+}
+
+/// Whether a `source` frame rate differs from `target` by more than
+/// rounding, so 29.97 and 30000/1001 count as the same rate.
+pub fn needs_fps_conversion(source: f64, target: f64) -> bool {
+    (source - target).abs() > 0.01
+}
+
+/// Codec settings used when clips are re-encoded instead of stream copied.
+#[derive(Debug, Clone)]
+pub struct EncodeSettings {
+    pub video_codec: String,
+    pub audio_codec: String,
+    pub crf: u32,
+    pub preset: Option<Preset>,
+    pub audio_bitrate: String,
+    pub hwaccel: Option<HwAccel>,
+    /// Tone map HDR video to SDR with this algorithm.
+    pub tone_map: Option<ToneMapAlgorithm>,
+    pub watermark: Option<Watermark>,
+    /// Convert to this frame rate.
+    pub fps: Option<f64>,
+    /// Resample audio to this rate in Hz.
+    pub sample_rate: Option<u32>,
+    /// Resample with the SoX resampler instead of ffmpeg's default.
+    pub high_quality_resample: bool,
+    /// Convert audio to this many channels.
+    pub channels: Option<u32>,
+    /// Scale the video to this size.
+    pub scale: Option<Scale>,
+    /// Deinterlace with this filter.
+    pub deinterlace: Option<DeinterlaceMode>,
+    pub denoise: Option<Denoise>,
+}
+
+impl Default for EncodeSettings {
+    /// The `--reencode` defaults: H.264 and AAC at the default quality.
+    fn default() -> Self {
+        EncodeSettings {
+            video_codec: DEFAULT_VIDEO_CODEC.to_string(),
+            audio_codec: DEFAULT_AUDIO_CODEC.to_string(),
+            crf: default_crf(DEFAULT_VIDEO_CODEC),
+            preset: None,
+            audio_bitrate: audio::DEFAULT_BITRATE.to_string(),
+            hwaccel: None,
+            tone_map: None,
+            watermark: None,
+            fps: None,
+            sample_rate: None,
+            high_quality_resample: false,
+            channels: None,
+            scale: None,
+            deinterlace: None,
+            denoise: None,
+        }
+    }
+}
+
+impl EncodeSettings {
+    /// Settings from `args`. WebM output always uses VP9 and Opus, which no
+    /// hardware encoder backend produces.
+    pub fn new(args: &Args) -> Self {
+        if args.container == Container::Webm {
+            return EncodeSettings {
+                video_codec: "libvpx-vp9".to_string(),
+                audio_codec: "libopus".to_string(),
+                crf: args.crf.unwrap_or_else(|| default_crf("libvpx-vp9")),
+                preset: None,
+                audio_bitrate: args.audio_bitrate.clone(),
+                hwaccel: None,
+                tone_map: args.tone_map.then_some(args.tone_map_algorithm),
+                watermark: Watermark::from_args(args),
+                fps: args.fps,
+                sample_rate: args.sample_rate,
+                high_quality_resample: args.high_quality_resample,
+                channels: args.audio_channels.and_then(AudioChannels::count),
+                scale: Scale::from_args(args),
+                deinterlace: args.deinterlace.then_some(args.deinterlace_mode),
+                denoise: Denoise::from_args(args),
+            };
+        }
+
+        let video_codec = args
+            .codec
+            .map(|codec| codec.encoder().to_string())
+            .unwrap_or_else(|| args.video_codec.clone());
+
+        EncodeSettings {
+            crf: args.crf.unwrap_or_else(|| default_crf(&video_codec)),
+            video_codec,
+            audio_codec: args.audio_codec.clone(),
+            preset: args.preset,
+            audio_bitrate: args.audio_bitrate.clone(),
+            hwaccel: args.hwaccel,
+            tone_map: args.tone_map.then_some(args.tone_map_algorithm),
+            watermark: Watermark::from_args(args),
+            fps: args.fps,
+            sample_rate: args.sample_rate,
+            high_quality_resample: args.high_quality_resample,
+            channels: args.audio_channels.and_then(AudioChannels::count),
+            scale: Scale::from_args(args),
+            deinterlace: args.deinterlace.then_some(args.deinterlace_mode),
+            denoise: Denoise::from_args(args),
+        }
+    }
+
+    /// Returns `None` when clips should be stream copied.
+    pub fn from_args(args: &Args) -> Option<Self> {
+        Self::for_source(args, None, false)
+    }
+
+    /// Like `from_args`, but leaves out the `--fps` conversion when the
+    /// source video (at `source_fps`, if known) already has that frame rate,
+    /// and deinterlaces when `--detect-interlace` found the source `interlaced`.
+    pub fn for_source(args: &Args, source_fps: Option<f64>, interlaced: bool) -> Option<Self> {
+        let fps = args
+            .fps
+            .filter(|&target| source_fps.is_none_or(|source| needs_fps_conversion(source, target)));
+        (args.reencode
+            || fps.is_some()
+            || args.accurate_seek
+            || args.codec.is_some()
+            || args.tone_map
+            || args.scale.is_some()
+            || args.deinterlace
+            || interlaced
+            || args.denoise
+            || args.watermark_image.is_some()
+            || args.watermark_text.is_some()
+            || SpeedSettings::from_args(args).is_active()
+            || args.container.requires_reencode())
+        .then(|| Self {
+            fps,
+            deinterlace: (args.deinterlace || interlaced).then_some(args.deinterlace_mode),
+            ..Self::new(args)
+        })
+    }
+
+    /// Arguments that must come before `-i`, such as hardware device setup.
+    pub fn input_args(&self) -> Vec<String> {
+        match self.hwaccel {
+            Some(HwAccel::Vaapi) => vec![
+                "-vaapi_device".to_string(),
+                "/dev/dri/renderD128".to_string(),
+            ],
+            _ => Vec::new(),
+        }
+    }
+
+    /// The filter that moves frames to the GPU, needed before hardware
+    /// encoders that only accept hardware frames.
+    pub fn upload_filter(&self) -> Option<&'static str> {
+        match self.hwaccel {
+            Some(HwAccel::Vaapi) => Some("format=nv12,hwupload"),
+            _ => None,
+        }
+    }
+
+    /// The HDR to SDR conversion, which has to run before any other filter.
+    pub fn tone_map_filter(&self) -> Option<String> {
+        self.tone_map.map(ToneMapAlgorithm::filter)
+    }
+
+    /// The filters every re-encoded video starts with: deinterlacing,
+    /// denoising and tone mapping, then `--scale`, so later filters such as
+    /// format variant crops work on the scaled frame.
+    pub fn base_filters(&self) -> FilterChain {
+        FilterChain::new()
+            .push(self.deinterlace.map(DeinterlaceMode::filter))
+            .push(self.denoise.map(|denoise| denoise.filter()))
+            .push(self.tone_map_filter())
+            .push(self.scale.map(|scale| scale.filter()))
+    }
+
+    /// Video encoder arguments, applying `filter` (if any) before encoding.
+    pub fn video_args(&self, filter: Option<&str>) -> Vec<String> {
+        let mut graph = self
+            .base_filters()
+            .push(filter)
+            .push(self.fps.map(|fps| format!("fps={}", fps)))
+            .build();
+        if let Some(watermark) = &self.watermark {
+            graph = watermark.apply(&graph);
+        }
+        if let Some(upload) = self.upload_filter() {
+            if !graph.is_empty() {
+                graph.push(',');
+            }
+            graph.push_str(upload);
+        }
+
+        let mut ffmpeg_args = Vec::new();
+        if !graph.is_empty() {
+            ffmpeg_args.extend(["-vf".to_string(), graph]);
+        }
+        ffmpeg_args.extend(self.video_codec_args());
+
+        ffmpeg_args
+    }
+
+    /// Encoder and quality arguments, without any filters.
+    pub fn video_codec_args(&self) -> Vec<String> {
+        let mut ffmpeg_args = self.encoder_args();
+        // Tag tone mapped output as SDR so players don't treat it as HDR.
+        if self.tone_map.is_some() {
+            for flag in ["-color_primaries", "-color_trc", "-colorspace"] {
+                ffmpeg_args.extend([flag.to_string(), "bt709".to_string()]);
+            }
+        }
+        ffmpeg_args
+    }
+
+    fn encoder_args(&self) -> Vec<String> {
+        match self.hwaccel {
+            Some(hwaccel) => {
+                let mut ffmpeg_args = vec!["-c:v".to_string(), hwaccel.encoder().to_string()];
+                ffmpeg_args.extend(hwaccel.quality_args(self.crf));
+                ffmpeg_args
+            }
+            None => {
+                let mut ffmpeg_args = vec![
+                    "-c:v".to_string(),
+                    self.video_codec.clone(),
+                    "-crf".to_string(),
+                    self.crf.to_string(),
+                ];
+                match self.video_codec.as_str() {
+                    // libvpx only treats -crf as constant quality when the bitrate is 0.
+                    "libvpx-vp9" => ffmpeg_args.extend(["-b:v".to_string(), "0".to_string()]),
+                    "libx265" => ffmpeg_args
+                        .extend(["-x265-params".to_string(), "log-level=quiet".to_string()]),
+                    "libaom-av1" => ffmpeg_args.extend(["-cpu-used".to_string(), "4".to_string()]),
+                    _ => {}
+                }
+                // libaom has its own speed setting (-cpu-used) instead of presets.
+                if let Some(preset) = self.preset.filter(|_| self.video_codec != "libaom-av1") {
+                    ffmpeg_args.extend(["-preset".to_string(), preset.as_str().to_string()]);
+                }
+                ffmpeg_args
+            }
+        }
+    }
+
+    /// Audio encoder arguments, applying `filter` (if any) before encoding.
+    pub fn audio_args(&self, filter: Option<&str>) -> Vec<String> {
+        let resample = (self.sample_rate.is_some() && self.high_quality_resample)
+            .then_some("aresample=resampler=soxr");
+        let filters: Vec<&str> = filter
+            .into_iter()
+            .chain(resample)
+            .chain(audio::downmix_filter(self.channels))
+            .collect();
+
+        let mut ffmpeg_args = Vec::new();
+        if !filters.is_empty() {
+            ffmpeg_args.extend(["-af".to_string(), filters.join(",")]);
+        }
+        ffmpeg_args.extend([
+            "-c:a".to_string(),
+            self.audio_codec.clone(),
+            "-b:a".to_string(),
+            self.audio_bitrate.clone(),
+        ]);
+        if let Some(sample_rate) = self.sample_rate {
+            ffmpeg_args.extend(["-ar".to_string(), sample_rate.to_string()]);
+        }
+        ffmpeg_args.extend(audio::channel_args(self.channels));
+        ffmpeg_args
+    }
+
+    pub fn ffmpeg_args(&self) -> Vec<String> {
+        let mut ffmpeg_args = self.video_args(None);
+        ffmpeg_args.extend(self.audio_args(None));
+        ffmpeg_args
+    }
+}
+
+/// Output codec arguments for a chapter clip: either the re-encode settings or
+/// a plain stream copy.
+pub fn clip_codec_args(encode: Option<&EncodeSettings>) -> Vec<String> {
+    match encode {
+        Some(encode) => encode.ffmpeg_args(),
+        None => ["-c", "copy", "-avoid_negative_ts", "1"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect(),
+    }
+}
+
+/// Output codec arguments that stream copy the video but re-encode the
+/// audio with `audio`, e.g. to change its sample rate.
+pub fn copy_video_args(audio: &EncodeSettings) -> Vec<String> {
+    let mut ffmpeg_args = vec!["-c:v".to_string(), "copy".to_string()];
+    ffmpeg_args.extend(audio.audio_args(None));
+    ffmpeg_args.extend(["-avoid_negative_ts".to_string(), "1".to_string()]);
+    ffmpeg_args
+}
+
+/// Parses a `--sample-rate` in Hz.
+pub fn parse_sample_rate(value: &str) -> Result<u32, String> {
+    match value.parse::<u32>() {
+        Ok(rate) if (8000..=384_000).contains(&rate) => Ok(rate),
+        _ => Err(format!(
+            "invalid sample rate \"{}\", expected a rate in Hz such as 44100 or 48000",
+            value
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_even_scale_sizes() {
+        assert_eq!(parse_scale("1920x1080"), Ok((1920, 1080)));
+        for size in ["1921x1080", "0x720", "1280", "1280x", "wide x tall"] {
+            assert!(parse_scale(size).is_err(), "{} was accepted", size);
+        }
+    }
+
+    #[test]
+    fn scales_with_each_method() {
+        let scale = |method| {
+            Scale {
+                width: 1080,
+                height: 1920,
+                method,
+            }
+            .filter()
+        };
+        assert_eq!(
+            scale(ScaleMethod::Fit),
+            "scale=1080:1920:force_original_aspect_ratio=decrease,pad=1080:1920:-1:-1:color=black"
+        );
+        assert_eq!(
+            scale(ScaleMethod::Fill),
+            "scale=1080:1920:force_original_aspect_ratio=increase,crop=1080:1920"
+        );
+        assert_eq!(scale(ScaleMethod::Stretch), "scale=1080:1920");
+    }
+
+    #[test]
+    fn filter_chain_skips_empty_filters() {
+        let chain = FilterChain::new()
+            .push(Some("yadif"))
+            .push(None::<String>)
+            .push(Some("scale=1280:720"));
+        assert_eq!(chain.build(), "yadif,scale=1280:720");
+        assert!(FilterChain::new().push(Some("")).is_empty());
+    }
+}
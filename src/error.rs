@@ -0,0 +1,62 @@
+use std::fmt;
+
+use crate::ChapterResult;
+
+/// Errors returned by the library functions, one variant per stage so
+/// callers can tell what went wrong. The wrapped errors carry the details,
+/// such as the tail of yt-dlp's or ffmpeg's output.
+#[derive(Debug)]
+pub enum Error {
+    /// The configuration is invalid, e.g. a bad clip name template or proxy URL.
+    Config(anyhow::Error),
+    /// yt-dlp couldn't fetch or parse the video's information.
+    VideoInfo(anyhow::Error),
+    /// Downloading the video failed.
+    Download(anyhow::Error),
+    /// Splitting stopped before every chapter was tried, e.g. because the
+    /// output directory couldn't be created or a chapter failed without
+    /// `continue_on_error`.
+    Split(anyhow::Error),
+    /// Some chapters couldn't be cut, listed as `ChapterResult::Failure`s.
+    /// Only returned with `continue_on_error`, after every other chapter has
+    /// been cut.
+    ChapterFailures(Vec<ChapterResult>),
+    /// Creating a format variant failed.
+    Formats(anyhow::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Config(e) => write!(f, "Invalid configuration: {:#}", e),
+            Error::VideoInfo(e) => write!(f, "Failed to get video information: {:#}", e),
+            Error::Download(e) => write!(f, "Failed to download video: {:#}", e),
+            Error::Split(e) => write!(f, "Failed to split video: {:#}", e),
+            Error::ChapterFailures(failures) => {
+                write!(f, "{} chapters failed: ", failures.len())?;
+                let failed: Vec<String> = failures
+                    .iter()
+                    .map(|failure| {
+                        let error = failure.error().unwrap_or_default();
+                        format!("Chapter {} ({})", failure.number(), error)
+                    })
+                    .collect();
+                write!(f, "{}", failed.join(", "))
+            }
+            Error::Formats(e) => write!(f, "Failed to create format variants: {:#}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Config(e)
+            | Error::VideoInfo(e)
+            | Error::Download(e)
+            | Error::Split(e)
+            | Error::Formats(e) => Some(e.as_ref()),
+            Error::ChapterFailures(_) => None,
+        }
+    }
+}
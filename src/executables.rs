@@ -0,0 +1,470 @@
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::env;
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::IpAddr;
+use std::path::PathBuf;
+use std::process::{Command, ExitStatus, Stdio};
+use std::thread;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt};
+use url::Url;
+
+use crate::hooks::shell_quote;
+use crate::{log, Args};
+
+/// How many lines of stderr are included in the error of a failed command.
+const ERROR_TAIL_LINES: usize = 20;
+
+/// ffmpeg options that yt-clipper sets itself and that extra arguments may
+/// silently override.
+const MANAGED_FFMPEG_ARGS: &[&str] = &[
+    "-c",
+    "-c:v",
+    "-c:a",
+    "-c:s",
+    "-codec",
+    "-vcodec",
+    "-acodec",
+    "-vf",
+    "-af",
+    "-filter_complex",
+    "-lavfi",
+    "-map",
+    "-ss",
+    "-t",
+    "-an",
+    "-vn",
+];
+
+/// Browsers yt-dlp can read cookies from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Browser {
+    Chrome,
+    Firefox,
+    Safari,
+    Edge,
+}
+
+impl Browser {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Browser::Chrome => "chrome",
+            Browser::Firefox => "firefox",
+            Browser::Safari => "safari",
+            Browser::Edge => "edge",
+        }
+    }
+}
+
+/// Default `--fragment-retries`.
+pub const DEFAULT_FRAGMENT_RETRIES: u32 = 10;
+
+/// How yt-dlp connects to the network.
+#[derive(Debug, Clone)]
+pub struct NetworkOptions {
+    pub proxy: Option<Url>,
+    pub geo_bypass: bool,
+    pub socket_timeout: Option<u64>,
+    pub source_address: Option<IpAddr>,
+    /// Retries for each fragment of a fragmented (DASH/HLS) download.
+    pub fragment_retries: u32,
+}
+
+impl Default for NetworkOptions {
+    fn default() -> Self {
+        NetworkOptions {
+            proxy: None,
+            geo_bypass: false,
+            socket_timeout: None,
+            source_address: None,
+            fragment_retries: DEFAULT_FRAGMENT_RETRIES,
+        }
+    }
+}
+
+impl NetworkOptions {
+    pub fn from_args(args: &Args, proxy: Option<&Url>) -> Self {
+        NetworkOptions {
+            proxy: proxy.cloned(),
+            geo_bypass: args.geo_bypass,
+            socket_timeout: args.socket_timeout,
+            source_address: args.source_address,
+            fragment_retries: args.fragment_retries,
+        }
+    }
+
+    /// Arguments for every yt-dlp invocation.
+    fn ytdlp_args(&self) -> Vec<String> {
+        let mut ytdlp_args = Vec::new();
+        if let Some(proxy) = &self.proxy {
+            ytdlp_args.extend(["--proxy".to_string(), proxy.to_string()]);
+        }
+        if self.geo_bypass {
+            ytdlp_args.push("--geo-bypass".to_string());
+        }
+        if let Some(timeout) = self.socket_timeout {
+            ytdlp_args.extend(["--socket-timeout".to_string(), timeout.to_string()]);
+        }
+        if let Some(address) = self.source_address {
+            ytdlp_args.extend(["--source-address".to_string(), address.to_string()]);
+        }
+        ytdlp_args
+    }
+
+    /// Arguments that only apply to downloads.
+    pub fn download_args(&self) -> Vec<String> {
+        vec![
+            "--fragment-retries".to_string(),
+            self.fragment_retries.to_string(),
+        ]
+    }
+}
+
+/// Paths of the external programs yt-clipper runs, and the extra arguments
+/// the user asked to pass to them.
+#[derive(Debug, Clone)]
+pub struct Executables {
+    pub ffmpeg: PathBuf,
+    pub ffprobe: PathBuf,
+    pub ytdlp: PathBuf,
+    /// Added just before the output path of every clip and format variant.
+    pub ffmpeg_extra_args: Vec<String>,
+    pub network: NetworkOptions,
+    /// Authentication and network arguments added to every yt-dlp invocation.
+    pub ytdlp_base_args: Vec<String>,
+    /// Added to every yt-dlp invocation.
+    pub ytdlp_extra_args: Vec<String>,
+    /// Show the output of every command in the terminal.
+    pub verbose: bool,
+}
+
+impl Executables {
+    /// The programs found through `YT_CLIPPER_FFMPEG` / `YT_CLIPPER_FFPROBE`
+    /// / `YT_CLIPPER_YTDLP` or in `PATH`, run with `network` and no extra
+    /// arguments.
+    pub fn new(network: NetworkOptions) -> Self {
+        Executables {
+            ffmpeg: resolve(None, "YT_CLIPPER_FFMPEG", "ffmpeg"),
+            ffprobe: resolve(None, "YT_CLIPPER_FFPROBE", "ffprobe"),
+            ytdlp: resolve(None, "YT_CLIPPER_YTDLP", "yt-dlp"),
+            ffmpeg_extra_args: Vec::new(),
+            ytdlp_base_args: network.ytdlp_args(),
+            network,
+            ytdlp_extra_args: Vec::new(),
+            verbose: false,
+        }
+    }
+
+    /// Takes each path from its flag, then from `YT_CLIPPER_FFMPEG` /
+    /// `YT_CLIPPER_FFPROBE` / `YT_CLIPPER_YTDLP`, and otherwise looks the
+    /// program up in `PATH`.
+    pub fn from_args(args: &Args, network: NetworkOptions) -> Self {
+        Executables {
+            ffmpeg: resolve(args.ffmpeg_path.as_ref(), "YT_CLIPPER_FFMPEG", "ffmpeg"),
+            ffprobe: resolve(args.ffprobe_path.as_ref(), "YT_CLIPPER_FFPROBE", "ffprobe"),
+            ytdlp: resolve(args.ytdlp_path.as_ref(), "YT_CLIPPER_YTDLP", "yt-dlp"),
+            ffmpeg_extra_args: split_args(args.ffmpeg_extra_args.as_deref()),
+            ytdlp_base_args: ytdlp_base_args(args, &network),
+            network,
+            ytdlp_extra_args: split_args(args.ytdlp_extra_args.as_deref()),
+            verbose: args.verbose,
+        }
+    }
+
+    pub fn ffmpeg(&self) -> Command {
+        Command::new(&self.ffmpeg)
+    }
+
+    pub fn ffprobe(&self) -> Command {
+        Command::new(&self.ffprobe)
+    }
+
+    /// A yt-dlp command with the base and extra arguments already added.
+    pub fn ytdlp(&self) -> Command {
+        let mut command = Command::new(&self.ytdlp);
+        command
+            .args(&self.ytdlp_base_args)
+            .args(&self.ytdlp_extra_args);
+        command
+    }
+
+    /// Runs `command` to completion. Its output is shown in verbose mode and
+    /// copied to the log file; otherwise the end of stderr is only reported
+    /// when the command fails.
+    pub fn run(&self, command: &mut Command) -> Result<()> {
+        let name = program_name(command);
+        let command_line = command_line(command);
+
+        let mut child = command
+            .stdout(self.stdout())
+            .stderr(Stdio::piped())
+            .spawn()
+            .with_context(|| format!("Failed to execute {}", name))?;
+
+        let verbose = self.verbose;
+        let stdout = child.stdout.take();
+        let stderr = child.stderr.take().expect("stderr is piped");
+
+        let tail = thread::scope(|scope| {
+            if let Some(stdout) = stdout {
+                scope.spawn(move || forward(stdout, verbose, io::stdout()));
+            }
+
+            let mut tail = VecDeque::with_capacity(ERROR_TAIL_LINES);
+            for line in BufReader::new(stderr).split(b'\n').map_while(Result::ok) {
+                record_line(&mut tail, &line, verbose);
+            }
+            tail
+        });
+
+        let status = child
+            .wait()
+            .with_context(|| format!("Failed to execute {}", name))?;
+
+        check_status(&name, command_line, status, tail, verbose)
+    }
+
+    /// Like `run`, but waits for `command` without blocking the thread, so
+    /// many commands can run at once on one async runtime.
+    pub async fn run_async(&self, command: Command) -> Result<()> {
+        let name = program_name(&command);
+        let command_line = command_line(&command);
+
+        let mut command = tokio::process::Command::from(command);
+        let mut child = command
+            .stdout(self.stdout())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .with_context(|| format!("Failed to execute {}", name))?;
+
+        let verbose = self.verbose;
+        let stdout = child.stdout.take();
+        let stderr = child.stderr.take().expect("stderr is piped");
+
+        let forward_stdout = async {
+            if let Some(mut stdout) = stdout {
+                let mut buf = [0; 8192];
+                while let Ok(n) = stdout.read(&mut buf).await {
+                    if n == 0 {
+                        break;
+                    }
+                    if verbose {
+                        let _ = io::stdout().write_all(&buf[..n]);
+                    }
+                    log::write(&buf[..n]);
+                }
+            }
+        };
+        let read_stderr = async {
+            let mut tail = VecDeque::with_capacity(ERROR_TAIL_LINES);
+            let mut lines = tokio::io::BufReader::new(stderr).split(b'\n');
+            while let Ok(Some(line)) = lines.next_segment().await {
+                record_line(&mut tail, &line, verbose);
+            }
+            tail
+        };
+        let ((), tail) = tokio::join!(forward_stdout, read_stderr);
+
+        let status = child
+            .wait()
+            .await
+            .with_context(|| format!("Failed to execute {}", name))?;
+
+        check_status(&name, command_line, status, tail, verbose)
+    }
+
+    /// Where a command's stdout goes: only read when it is shown or logged.
+    fn stdout(&self) -> Stdio {
+        if self.verbose || log::is_enabled() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        }
+    }
+
+    /// The extra ffmpeg arguments that override options yt-clipper sets itself.
+    pub fn conflicting_ffmpeg_args(&self) -> Vec<&str> {
+        self.ffmpeg_extra_args
+            .iter()
+            .map(String::as_str)
+            .filter(|arg| MANAGED_FFMPEG_ARGS.contains(arg))
+            .collect()
+    }
+}
+
+pub(crate) fn program_name(command: &Command) -> String {
+    PathBuf::from(command.get_program())
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// Logs a line of stderr (and shows it when `verbose`), keeping the last
+/// `ERROR_TAIL_LINES` lines in `tail` for the error message.
+fn record_line(tail: &mut VecDeque<String>, line: &[u8], verbose: bool) {
+    let mut line = String::from_utf8_lossy(line).into_owned();
+    line.push('\n');
+    if verbose {
+        let _ = io::stderr().write_all(line.as_bytes());
+    }
+    log::write(line.as_bytes());
+
+    if tail.len() == ERROR_TAIL_LINES {
+        tail.pop_front();
+    }
+    tail.push_back(line);
+}
+
+/// A command that exited unsuccessfully.
+#[derive(Debug)]
+pub struct CommandError {
+    /// The full command line, quoted for a POSIX shell.
+    pub command_line: String,
+    message: String,
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for CommandError {}
+
+/// Fails with the end of stderr when a command exited unsuccessfully.
+fn check_status(
+    name: &str,
+    command_line: String,
+    status: ExitStatus,
+    tail: VecDeque<String>,
+    verbose: bool,
+) -> Result<()> {
+    if status.success() {
+        return Ok(());
+    }
+
+    let output: String = tail.into_iter().collect();
+    let message = if output.trim().is_empty() || verbose {
+        format!("{} exited with {}", name, status)
+    } else {
+        format!("{} exited with {}:\n{}", name, status, output.trim_end())
+    };
+    Err(CommandError {
+        command_line,
+        message,
+    }
+    .into())
+}
+
+fn command_line(command: &Command) -> String {
+    std::iter::once(command.get_program())
+        .chain(command.get_args())
+        .map(|arg| {
+            let arg = arg.to_string_lossy();
+            let plain = !arg.is_empty()
+                && arg
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || "_-./:=,+%@".contains(c));
+            if plain {
+                arg.into_owned()
+            } else {
+                shell_quote(&arg)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Copies `reader` into the log file, and to `terminal` when `verbose`.
+fn forward(mut reader: impl Read, verbose: bool, mut terminal: impl Write) {
+    let mut buf = [0; 8192];
+    while let Ok(n) = reader.read(&mut buf) {
+        if n == 0 {
+            break;
+        }
+        if verbose {
+            let _ = terminal.write_all(&buf[..n]);
+        }
+        log::write(&buf[..n]);
+    }
+}
+
+/// The yt-dlp arguments for cookies and the network.
+fn ytdlp_base_args(args: &Args, network: &NetworkOptions) -> Vec<String> {
+    let mut base_args = Vec::new();
+    if let Some(cookies) = &args.cookies {
+        base_args.extend(["--cookies".to_string(), cookies.display().to_string()]);
+    }
+    if let Some(browser) = args.cookies_from_browser {
+        base_args.extend([
+            "--cookies-from-browser".to_string(),
+            browser.as_str().to_string(),
+        ]);
+    }
+    base_args.extend(network.ytdlp_args());
+    base_args
+}
+
+/// Checks that `--ffmpeg-extra-args` / `--ytdlp-extra-args` split into
+/// arguments, i.e. that its quotes are balanced.
+pub fn parse_extra_args(value: &str) -> Result<String, String> {
+    match shlex::split(value) {
+        Some(_) => Ok(value.to_string()),
+        None => Err("unbalanced quotes or trailing backslash".to_string()),
+    }
+}
+
+/// Splits extra arguments the way a shell would, so quoted filter graphs
+/// and paths with spaces stay one argument. Values from a config file
+/// haven't been through [`parse_extra_args`], so a malformed one is split
+/// on whitespace instead, with a warning.
+fn split_args(args: Option<&str>) -> Vec<String> {
+    let Some(args) = args else {
+        return Vec::new();
+    };
+    shlex::split(args).unwrap_or_else(|| {
+        estatus!(
+            "Warning: unbalanced quotes in extra arguments `{}`, splitting them on whitespace",
+            args
+        );
+        args.split_whitespace().map(String::from).collect()
+    })
+}
+
+fn resolve(flag: Option<&PathBuf>, env_var: &str, default: &str) -> PathBuf {
+    flag.cloned()
+        .or_else(|| env::var_os(env_var).map(PathBuf::from))
+        .unwrap_or_else(|| PathBuf::from(default))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_args_keeps_quoted_arguments_together() {
+        assert_eq!(
+            split_args(Some(
+                "-vf 'scale=1280:-2, eq=contrast=1.1' -metadata \"title=A B\""
+            )),
+            [
+                "-vf",
+                "scale=1280:-2, eq=contrast=1.1",
+                "-metadata",
+                "title=A B"
+            ]
+        );
+        assert!(split_args(None).is_empty());
+    }
+
+    #[test]
+    fn parse_extra_args_rejects_unbalanced_quotes() {
+        assert!(parse_extra_args("--cookies 'My Cookies.txt'").is_ok());
+        assert!(parse_extra_args("-vf 'scale=1280:-2").is_err());
+    }
+}
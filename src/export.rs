@@ -0,0 +1,58 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::fs::File;
+use std::path::Path;
+
+use crate::Chapter;
+
+/// One spreadsheet row per chapter, for `--export-csv` and `--export-tsv`.
+#[derive(Serialize)]
+struct Row<'a> {
+    video_id: &'a str,
+    video_title: &'a str,
+    chapter_index: usize,
+    chapter_title: &'a str,
+    start_time: f64,
+    end_time: f64,
+    duration_seconds: f64,
+}
+
+/// A chapter table shared by every video processed in one run.
+pub struct ChapterExport {
+    writer: csv::Writer<File>,
+}
+
+impl ChapterExport {
+    /// Creates (or truncates) `path` and writes the header row.
+    pub fn create(path: &Path, delimiter: u8) -> Result<Self> {
+        let writer = csv::WriterBuilder::new()
+            .delimiter(delimiter)
+            .from_path(path)
+            .with_context(|| format!("Failed to create {}", path.display()))?;
+        Ok(ChapterExport { writer })
+    }
+
+    /// Appends a row for each chapter and flushes, so an interrupted batch
+    /// still leaves every finished video in the file.
+    pub fn write_video(
+        &mut self,
+        video_id: &str,
+        video_title: &str,
+        chapters: &[Chapter],
+    ) -> Result<()> {
+        for chapter in chapters {
+            self.writer.serialize(Row {
+                video_id,
+                video_title,
+                chapter_index: chapter.number,
+                chapter_title: &chapter.title,
+                start_time: chapter.start_time,
+                end_time: chapter.end_time,
+                duration_seconds: chapter.end_time - chapter.start_time,
+            })?;
+        }
+        self.writer
+            .flush()
+            .context("Failed to write chapter export")
+    }
+}
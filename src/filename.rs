@@ -0,0 +1,327 @@
+use clap::ValueEnum;
+use sanitize_filename::sanitize;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::{self, PathBuf};
+
+use crate::Args;
+
+/// How titles are turned into file names.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SanitizeMode {
+    /// Remove characters that are invalid on any common filesystem.
+    #[default]
+    Strict,
+    /// Only remove characters that are invalid on this platform, keeping
+    /// everything else (CJK, accents, symbols).
+    Unicode,
+    /// Transliterate to ASCII ("Café" becomes "Cafe"), then apply `Strict`.
+    Ascii,
+}
+
+/// Default `--max-filename-length`. Windows tools still often fail on paths
+/// over 260 characters, so names are kept shorter there.
+pub const DEFAULT_MAX_LENGTH: usize = if cfg!(windows) { 100 } else { 200 };
+
+/// Longest path used on Windows without the `\\?\` long path prefix.
+const LONG_PATH_THRESHOLD: usize = 240;
+
+/// How chapter numbers are written in file names.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NumberingStyle {
+    /// 01, 02, 03, ...
+    #[default]
+    Arabic,
+    /// I, II, III, ...
+    Roman,
+    /// a, b, ..., z, aa, ab, ...
+    Alpha,
+    /// No number, like `--no-number`.
+    None,
+}
+
+/// Title cleanup and numbering applied when chapters become file names.
+#[derive(Debug, Clone)]
+pub struct FilenameOptions {
+    pub mode: SanitizeMode,
+    pub strip_emoji: bool,
+    pub numbering: NumberingStyle,
+    /// Longest file or directory name, in characters, before shortening.
+    pub max_length: usize,
+    /// Replaces the spaces in chapter titles.
+    pub word_separator: Option<String>,
+}
+
+impl Default for FilenameOptions {
+    fn default() -> Self {
+        FilenameOptions {
+            mode: SanitizeMode::default(),
+            strip_emoji: false,
+            numbering: NumberingStyle::default(),
+            max_length: DEFAULT_MAX_LENGTH,
+            word_separator: None,
+        }
+    }
+}
+
+impl FilenameOptions {
+    pub fn from_args(args: &Args) -> Self {
+        FilenameOptions {
+            mode: args.filename_sanitize,
+            strip_emoji: args.strip_emoji,
+            numbering: args.numbering_style,
+            max_length: args.max_filename_length,
+            word_separator: args.word_separator.clone(),
+        }
+    }
+
+    /// `title` made safe to use as a single path component. May be empty.
+    pub fn clean(&self, title: &str) -> String {
+        let title: String = if self.strip_emoji {
+            title.chars().filter(|&c| !is_emoji(c)).collect()
+        } else {
+            title.to_string()
+        };
+
+        let cleaned = match self.mode {
+            SanitizeMode::Strict => sanitize(&title),
+            SanitizeMode::Unicode => title.chars().filter(|&c| !is_illegal(c)).collect(),
+            SanitizeMode::Ascii => sanitize(unidecode::unidecode(&title)),
+        };
+
+        cleaned.trim().trim_end_matches('.').trim_end().to_string()
+    }
+
+    /// A cleaned chapter title for a clip name, with its spaces replaced by
+    /// the word separator. May be empty.
+    pub fn clean_chapter_title(&self, title: &str) -> String {
+        let title = self.clean(title);
+        match &self.word_separator {
+            Some(separator) => title.split_whitespace().collect::<Vec<_>>().join(separator),
+            None => title,
+        }
+    }
+
+    /// `name` with every `/`-separated component shortened to `max_length`
+    /// characters. A shortened component ends in `~` and a hash of the whole
+    /// component, so long titles that start the same still get distinct names.
+    pub fn shorten(&self, name: &str) -> String {
+        name.split('/')
+            .map(|component| shorten_component(component, self.max_length))
+            .collect::<Vec<_>>()
+            .join(path::MAIN_SEPARATOR_STR)
+    }
+}
+
+fn shorten_component(component: &str, max_length: usize) -> String {
+    if component.chars().count() <= max_length {
+        return component.to_string();
+    }
+
+    let hash: String = Sha256::digest(component.as_bytes())
+        .iter()
+        .take(4)
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    let kept: String = component
+        .chars()
+        .take(max_length.saturating_sub(hash.len() + 1))
+        .collect();
+    format!("{}~{}", kept.trim_end().trim_end_matches('.'), hash)
+}
+
+/// Parses `--separator` and `--word-separator`, which can't contain path
+/// separators or characters file names can't hold.
+pub fn parse_separator(value: &str) -> Result<String, String> {
+    let invalid = |c: char| {
+        c == '\\' || is_illegal(c) || matches!(c, '<' | '>' | ':' | '"' | '|' | '?' | '*')
+    };
+    match value.chars().find(|&c| invalid(c)) {
+        Some(c) => Err(format!(
+            "\"{}\" can't be used in file names",
+            c.escape_default()
+        )),
+        None => Ok(value.to_string()),
+    }
+}
+
+pub fn parse_max_length(value: &str) -> Result<usize, String> {
+    match value.parse::<usize>() {
+        Ok(length) if length >= 16 => Ok(length),
+        _ => Err(format!(
+            "invalid length \"{}\", expected a number of characters of at least 16",
+            value
+        )),
+    }
+}
+
+/// On Windows, `dir` as an absolute path with the `\\?\` prefix when the
+/// files inside it could pass the 260 character `MAX_PATH` limit, which
+/// would otherwise make creating them fail. Elsewhere, and for short paths,
+/// `dir` is returned unchanged.
+pub fn long_path_safe(dir: PathBuf, max_length: usize) -> PathBuf {
+    if !cfg!(windows) {
+        return dir;
+    }
+    let Ok(absolute) = path::absolute(&dir) else {
+        return dir;
+    };
+    // Room for a subdirectory such as `thumbnails`, a name and its extension.
+    if absolute.as_os_str().len() + max_length + 20 <= LONG_PATH_THRESHOLD {
+        return dir;
+    }
+
+    let absolute = absolute.to_string_lossy();
+    if absolute.starts_with(r"\\?\") {
+        PathBuf::from(absolute.as_ref())
+    } else if let Some(share) = absolute.strip_prefix(r"\\") {
+        PathBuf::from(format!(r"\\?\UNC\{}", share))
+    } else {
+        PathBuf::from(format!(r"\\?\{}", absolute))
+    }
+}
+
+/// Warns when Windows' own long path support is off, which limits ffmpeg and
+/// yt-dlp to paths of 260 characters unless they get a `\\?\` path.
+#[cfg(windows)]
+pub fn warn_if_long_paths_disabled() {
+    let enabled = std::process::Command::new("reg")
+        .args([
+            "query",
+            r"HKLM\SYSTEM\CurrentControlSet\Control\FileSystem",
+            "/v",
+            "LongPathsEnabled",
+        ])
+        .output()
+        .is_ok_and(|output| String::from_utf8_lossy(&output.stdout).contains("0x1"));
+
+    if !enabled {
+        estatus!("Warning: Windows long path support is off, so paths over 260 characters may fail. Names are shortened to --max-filename-length; set LongPathsEnabled in the registry to lift the limit");
+    }
+}
+
+/// `n` written in `style`, without padding. Roman numerals past 3999 carry
+/// on with more `M`s.
+pub fn format_chapter_number(n: usize, style: NumberingStyle) -> String {
+    match style {
+        NumberingStyle::Arabic => n.to_string(),
+        NumberingStyle::Roman if n > 0 => roman(n),
+        NumberingStyle::Alpha if n > 0 => alpha(n),
+        NumberingStyle::Roman | NumberingStyle::Alpha => n.to_string(),
+        NumberingStyle::None => String::new(),
+    }
+}
+
+fn roman(mut n: usize) -> String {
+    const NUMERALS: [(usize, &str); 13] = [
+        (1000, "M"),
+        (900, "CM"),
+        (500, "D"),
+        (400, "CD"),
+        (100, "C"),
+        (90, "XC"),
+        (50, "L"),
+        (40, "XL"),
+        (10, "X"),
+        (9, "IX"),
+        (5, "V"),
+        (4, "IV"),
+        (1, "I"),
+    ];
+
+    let mut numeral = String::new();
+    for (value, symbol) in NUMERALS {
+        while n >= value {
+            numeral.push_str(symbol);
+            n -= value;
+        }
+    }
+    numeral
+}
+
+/// Spreadsheet-style column letters: a to z, then aa, ab, ...
+fn alpha(mut n: usize) -> String {
+    let mut letters = Vec::new();
+    while n > 0 {
+        n -= 1;
+        letters.push(b'a' + (n % 26) as u8);
+        n /= 26;
+    }
+    letters.iter().rev().map(|&b| b as char).collect()
+}
+
+/// Lowercase ASCII letters and digits joined by single dashes, which is safe
+/// in both paths and URLs.
+pub fn slug(name: &str) -> String {
+    let mut slug = String::new();
+    for c in name.chars() {
+        if c.is_ascii_alphanumeric() {
+            slug.push(c.to_ascii_lowercase());
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    let slug = slug.trim_end_matches('-');
+    if slug.is_empty() {
+        "chapter".to_string()
+    } else {
+        slug.to_string()
+    }
+}
+
+/// Characters the filesystem itself rejects in a file name.
+fn is_illegal(c: char) -> bool {
+    c == '/'
+        || c.is_control()
+        || (cfg!(windows) && matches!(c, '\\' | '<' | '>' | ':' | '"' | '|' | '?' | '*'))
+}
+
+/// Emoji, pictographs and the invisible characters that join or style them.
+fn is_emoji(c: char) -> bool {
+    matches!(
+        c as u32,
+        0x1F000..=0x1FAFF // Pictographs, emoticons, transport, flags, ...
+            | 0x2600..=0x27BF // Miscellaneous symbols and dingbats
+            | 0x2B00..=0x2BFF // Stars, arrows and other symbols
+            | 0xFE00..=0xFE0F // Variation selectors
+            | 0x200D // Zero-width joiner
+            | 0xE0020..=0xE007F // Tag characters used by subdivision flags
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_roman_numbers() {
+        let roman = |n| format_chapter_number(n, NumberingStyle::Roman);
+        assert_eq!(roman(1), "I");
+        assert_eq!(roman(4), "IV");
+        assert_eq!(roman(9), "IX");
+        assert_eq!(roman(14), "XIV");
+        assert_eq!(roman(3999), "MMMCMXCIX");
+        assert_eq!(roman(4000), "MMMM");
+    }
+
+    #[test]
+    fn formats_alpha_numbers() {
+        let alpha = |n| format_chapter_number(n, NumberingStyle::Alpha);
+        assert_eq!(alpha(1), "a");
+        assert_eq!(alpha(26), "z");
+        assert_eq!(alpha(27), "aa");
+        assert_eq!(alpha(52), "az");
+        assert_eq!(alpha(53), "ba");
+        assert_eq!(alpha(702), "zz");
+        assert_eq!(alpha(703), "aaa");
+    }
+
+    #[test]
+    fn formats_zero_and_no_number() {
+        assert_eq!(format_chapter_number(0, NumberingStyle::Roman), "0");
+        assert_eq!(format_chapter_number(0, NumberingStyle::Alpha), "0");
+        assert_eq!(format_chapter_number(7, NumberingStyle::None), "");
+    }
+}
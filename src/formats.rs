@@ -0,0 +1,500 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::audio::{self, AudioSettings};
+use crate::encode::{Container, EncodeSettings};
+use crate::executables::Executables;
+use crate::metadata::VideoMetadata;
+use crate::plan::PlannedVariant;
+use crate::progress;
+use crate::{create_parent_dir, Args, Chapter};
+
+const STORIES_WIDTH: u32 = 1080;
+const STORIES_HEIGHT: u32 = 1920;
+
+/// Default `--gif-fps`.
+pub const DEFAULT_GIF_FPS: u32 = 10;
+
+/// Default `--gif-width`.
+pub const DEFAULT_GIF_WIDTH: u32 = 480;
+
+/// Which part of the frame the vertical variant keeps.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(into = "String", try_from = "String")]
+pub enum Gravity {
+    #[default]
+    Center,
+    Left,
+    Right,
+    /// Top-left corner of the crop, in pixels.
+    Offset(u32, u32),
+}
+
+impl Gravity {
+    /// The `x:y` position of a full-height 9:16 crop.
+    fn crop_position(self) -> String {
+        match self {
+            Gravity::Center => "(iw-ih*9/16)/2:0".to_string(),
+            Gravity::Left => "0:0".to_string(),
+            Gravity::Right => "iw-ih*9/16:0".to_string(),
+            Gravity::Offset(x, y) => format!("{}:{}", x, y),
+        }
+    }
+}
+
+impl FromStr for Gravity {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "center" => Ok(Gravity::Center),
+            "left" => Ok(Gravity::Left),
+            "right" => Ok(Gravity::Right),
+            _ => value
+                .split_once(':')
+                .and_then(|(x, y)| Some(Gravity::Offset(x.parse().ok()?, y.parse().ok()?)))
+                .ok_or_else(|| {
+                    format!(
+                        "invalid gravity \"{}\", expected center, left, right or X:Y",
+                        value
+                    )
+                }),
+        }
+    }
+}
+
+impl TryFrom<String> for Gravity {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl fmt::Display for Gravity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Gravity::Center => f.write_str("center"),
+            Gravity::Left => f.write_str("left"),
+            Gravity::Right => f.write_str("right"),
+            Gravity::Offset(x, y) => write!(f, "{}:{}", x, y),
+        }
+    }
+}
+
+impl From<Gravity> for String {
+    fn from(gravity: Gravity) -> Self {
+        gravity.to_string()
+    }
+}
+
+/// The alternative versions generated for every chapter with `--formats`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FormatVariant {
+    Vertical,
+    AudioOnly,
+    NoAudio,
+    Square,
+    Stories,
+    Gif,
+}
+
+impl FormatVariant {
+    pub const ALL: [FormatVariant; 6] = [
+        FormatVariant::Vertical,
+        FormatVariant::AudioOnly,
+        FormatVariant::NoAudio,
+        FormatVariant::Square,
+        FormatVariant::Stories,
+        FormatVariant::Gif,
+    ];
+
+    pub fn dir_name(self) -> &'static str {
+        match self {
+            FormatVariant::Vertical => "vertical",
+            FormatVariant::AudioOnly => "audio_only",
+            FormatVariant::NoAudio => "no_audio",
+            FormatVariant::Square => "square",
+            FormatVariant::Stories => "stories",
+            FormatVariant::Gif => "gif",
+        }
+    }
+
+    /// Where this variant of the clip named `base_filename` is written.
+    pub fn output_path(
+        self,
+        formats_dir: &Path,
+        base_filename: &str,
+        options: &FormatOptions,
+    ) -> PathBuf {
+        formats_dir.join(self.dir_name()).join(format!(
+            "{}.{}",
+            base_filename,
+            self.extension(options)
+        ))
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            FormatVariant::Vertical => "Vertical",
+            FormatVariant::AudioOnly => "Audio only",
+            FormatVariant::NoAudio => "No audio",
+            FormatVariant::Square => "Square",
+            FormatVariant::Stories => "Stories",
+            FormatVariant::Gif => "GIF",
+        }
+    }
+
+    pub fn extension(self, options: &FormatOptions) -> &'static str {
+        match self {
+            FormatVariant::AudioOnly => options.audio.format.extension(),
+            FormatVariant::Gif => "gif",
+            _ => options.container.extension(),
+        }
+    }
+
+    /// Whether ffmpeg has to encode video for this variant (and so needs any
+    /// hardware device setup before `-i`).
+    fn encodes_video(self) -> bool {
+        matches!(
+            self,
+            FormatVariant::Vertical | FormatVariant::Square | FormatVariant::Stories
+        )
+    }
+
+    fn metadata_args(self, chapter: &Chapter, options: &FormatOptions) -> Vec<String> {
+        match (&options.metadata, self) {
+            (Some(metadata), FormatVariant::AudioOnly) => metadata.audio_args(chapter),
+            (Some(metadata), _) => metadata.clip_args(chapter),
+            (None, _) => Vec::new(),
+        }
+    }
+
+    /// ffmpeg arguments placed after the input and seek arguments.
+    fn output_args(self, encode: &EncodeSettings, options: &FormatOptions) -> Vec<String> {
+        let mut ffmpeg_args = match self {
+            FormatVariant::Vertical => encode.video_args(Some(&format!(
+                "crop=ih*9/16:ih:{}",
+                options.vertical_gravity.crop_position()
+            ))),
+            FormatVariant::Square => encode.video_args(Some("crop=min(iw\\,ih):min(iw\\,ih)")),
+            FormatVariant::Stories if options.blur_background => {
+                let mut ffmpeg_args = vec![
+                    "-filter_complex".to_string(),
+                    blurred_stories_filter(encode),
+                    "-map".to_string(),
+                    "[v]".to_string(),
+                    "-map".to_string(),
+                    "0:a?".to_string(),
+                ];
+                ffmpeg_args.extend(encode.video_codec_args());
+                ffmpeg_args
+            }
+            FormatVariant::Stories => encode.video_args(Some(&format!(
+                "scale={w}:{h}:force_original_aspect_ratio=decrease,pad={w}:{h}:-1:-1:color=black",
+                w = STORIES_WIDTH,
+                h = STORIES_HEIGHT
+            ))),
+            FormatVariant::NoAudio
+                if options.container.requires_reencode()
+                    || !encode.base_filters().is_empty()
+                    || encode.watermark.is_some() =>
+            {
+                let mut ffmpeg_args = vec!["-an".to_string()];
+                ffmpeg_args.extend(encode.video_args(None));
+                ffmpeg_args
+            }
+            FormatVariant::NoAudio => ["-an", "-c:v", "copy"]
+                .iter()
+                .map(|s| s.to_string())
+                .collect(),
+            FormatVariant::AudioOnly => unreachable!("audio is encoded by encode_audio_clip"),
+            FormatVariant::Gif => unreachable!("GIFs are created by create_gif"),
+        };
+
+        if self != FormatVariant::NoAudio {
+            if options.container.requires_reencode() || options.resample_audio {
+                ffmpeg_args.extend(encode.audio_args(None));
+            } else {
+                ffmpeg_args.extend(["-c:a".to_string(), "copy".to_string()]);
+            }
+        }
+        ffmpeg_args.extend(["-avoid_negative_ts".to_string(), "1".to_string()]);
+
+        ffmpeg_args
+    }
+}
+
+/// Pads the video to 9:16 using a blurred, zoomed copy of itself as the
+/// background instead of solid bars.
+fn blurred_stories_filter(encode: &EncodeSettings) -> String {
+    let base_filters = encode.base_filters();
+    let tone_map = if base_filters.is_empty() {
+        String::new()
+    } else {
+        base_filters.build() + ","
+    };
+    let mut filter = format!(
+        "[0:v]{tone_map}split=2[bg][fg];\
+         [bg]scale={w}:{h}:force_original_aspect_ratio=increase,crop={w}:{h},boxblur=20:5[blurred];\
+         [fg]scale={w}:{h}:force_original_aspect_ratio=decrease[scaled];\
+         [blurred][scaled]overlay=(W-w)/2:(H-h)/2",
+        tone_map = tone_map,
+        w = STORIES_WIDTH,
+        h = STORIES_HEIGHT
+    );
+
+    if let Some(watermark) = &encode.watermark {
+        filter = watermark.apply(&filter);
+    }
+    if let Some(upload) = encode.upload_filter() {
+        filter.push(',');
+        filter.push_str(upload);
+    }
+    filter.push_str("[v]");
+
+    filter
+}
+
+/// Settings that only affect format variant generation.
+#[derive(Debug, Clone)]
+pub struct FormatOptions {
+    pub blur_background: bool,
+    pub vertical_gravity: Gravity,
+    pub gif_fps: u32,
+    pub gif_width: u32,
+    pub gif_max_duration: Option<f64>,
+    pub container: Container,
+    pub audio: AudioSettings,
+    pub metadata: Option<VideoMetadata>,
+    /// Re-encode the audio of variants that would otherwise copy it, to
+    /// convert the source to `--sample-rate` or `--audio-channels`.
+    pub resample_audio: bool,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        FormatOptions {
+            blur_background: false,
+            vertical_gravity: Gravity::default(),
+            gif_fps: DEFAULT_GIF_FPS,
+            gif_width: DEFAULT_GIF_WIDTH,
+            gif_max_duration: None,
+            container: Container::default(),
+            audio: AudioSettings::default(),
+            metadata: None,
+            resample_audio: false,
+        }
+    }
+}
+
+impl FormatOptions {
+    pub fn from_args(args: &Args, metadata: Option<VideoMetadata>) -> Self {
+        FormatOptions {
+            metadata,
+            blur_background: args.blur_background,
+            vertical_gravity: args.vertical_gravity,
+            gif_fps: args.gif_fps,
+            gif_width: args.gif_width,
+            gif_max_duration: args.gif_max_duration,
+            container: args.container,
+            audio: AudioSettings::from_args(args),
+            resample_audio: false,
+        }
+    }
+}
+
+/// GIFs longer than this get very large, so warn about them.
+const GIF_WARN_DURATION: f64 = 60.0;
+
+/// Creates a GIF using the two-pass palettegen/paletteuse pipeline, which
+/// gives far better colors than ffmpeg's default GIF palette.
+fn create_gif(
+    executables: &Executables,
+    video_path: &Path,
+    start_time: &str,
+    duration: &str,
+    output: &Path,
+    palette: &Path,
+    options: &FormatOptions,
+) -> Result<()> {
+    let filters = format!(
+        "fps={},scale={}:-1:flags=lanczos",
+        options.gif_fps, options.gif_width
+    );
+
+    executables
+        .run(executables.ffmpeg().args([
+            "-i",
+            video_path.to_str().unwrap(),
+            "-ss",
+            start_time,
+            "-t",
+            duration,
+            "-vf",
+            &format!("{},palettegen", filters),
+            "-y",
+            palette.to_str().unwrap(),
+        ]))
+        .context("Failed to generate GIF palette")?;
+
+    executables
+        .run(
+            executables
+                .ffmpeg()
+                .args([
+                    "-i",
+                    video_path.to_str().unwrap(),
+                    "-i",
+                    palette.to_str().unwrap(),
+                    "-ss",
+                    start_time,
+                    "-t",
+                    duration,
+                    "-lavfi",
+                    &format!("{} [x]; [x][1:v] paletteuse", filters),
+                ])
+                .args(&executables.ffmpeg_extra_args)
+                .args(["-y", output.to_str().unwrap()]),
+        )
+        .context("Failed to create GIF format")?;
+
+    Ok(())
+}
+
+pub fn planned_format_variants(formats_dir: &Path, options: &FormatOptions) -> Vec<PlannedVariant> {
+    FormatVariant::ALL
+        .iter()
+        .map(|variant| PlannedVariant {
+            name: variant.dir_name().to_string(),
+            directory: formats_dir.join(variant.dir_name()),
+            extension: variant.extension(options).to_string(),
+        })
+        .collect()
+}
+
+pub fn generate_format_variants(
+    executables: &Executables,
+    video_path: &Path,
+    chapters: &[Chapter],
+    clip_names: &[String],
+    formats_dir: &Path,
+    encode: &EncodeSettings,
+    options: &FormatOptions,
+) -> Result<()> {
+    let variants = FormatVariant::ALL;
+
+    for variant in &variants {
+        fs::create_dir_all(formats_dir.join(variant.dir_name()))?;
+    }
+
+    // Palettes are only needed between the two GIF passes.
+    let palette_dir = tempfile::tempdir().context("Failed to create temporary directory")?;
+
+    let total_tasks = chapters.len() * variants.len();
+    let pb = progress::bar(total_tasks);
+
+    for (chapter, base_filename) in chapters.iter().zip(clip_names) {
+        let duration = chapter.end_time - chapter.start_time;
+        let start_time = format!("{:.3}", chapter.start_time);
+        let duration_str = format!("{:.3}", duration);
+
+        for variant in &variants {
+            pb.set_message(format!("{}: {}", variant.label(), chapter.title));
+            let output = variant.output_path(formats_dir, base_filename, options);
+            create_parent_dir(&output)?;
+
+            if *variant == FormatVariant::Gif {
+                if options.gif_max_duration.is_some_and(|max| duration > max) {
+                    pb.suspend(|| {
+                        estatus!(
+                            "Skipping GIF for \"{}\" ({:.1}s exceeds --gif-max-duration)",
+                            chapter.title,
+                            duration
+                        )
+                    });
+                } else {
+                    if duration > GIF_WARN_DURATION {
+                        pb.suspend(|| {
+                            estatus!(
+                                "Warning: \"{}\" is {:.1}s long, its GIF will be very large",
+                                chapter.title,
+                                duration
+                            )
+                        });
+                    }
+                    let palette = palette_dir
+                        .path()
+                        .join(format!("palette_{}.png", chapter.number));
+                    create_gif(
+                        executables,
+                        video_path,
+                        &start_time,
+                        &duration_str,
+                        &output,
+                        &palette,
+                        options,
+                    )?;
+                }
+                pb.inc(1);
+                continue;
+            }
+
+            if *variant == FormatVariant::AudioOnly {
+                audio::encode_audio_clip(
+                    executables,
+                    video_path,
+                    chapter.start_time,
+                    duration,
+                    &output,
+                    &options.audio,
+                    &[
+                        variant.metadata_args(chapter, options),
+                        executables.ffmpeg_extra_args.clone(),
+                    ]
+                    .concat(),
+                )
+                .context("Failed to create audio only format")?;
+                pb.inc(1);
+                continue;
+            }
+
+            let input_args = if variant.encodes_video() {
+                encode.input_args()
+            } else {
+                Vec::new()
+            };
+
+            executables
+                .run(
+                    executables
+                        .ffmpeg()
+                        .args(input_args)
+                        .args([
+                            "-i",
+                            video_path.to_str().unwrap(),
+                            "-ss",
+                            &start_time,
+                            "-t",
+                            &duration_str,
+                        ])
+                        .args(variant.output_args(encode, options))
+                        .args(variant.metadata_args(chapter, options))
+                        .args(&executables.ffmpeg_extra_args)
+                        .args(["-y", output.to_str().unwrap()]),
+                )
+                .with_context(|| {
+                    format!("Failed to create {} format", variant.label().to_lowercase())
+                })?;
+            pb.inc(1);
+        }
+    }
+
+    pb.finish_with_message("All format variants generated");
+
+    Ok(())
+}
@@ -0,0 +1,136 @@
+use anyhow::{Context, Result};
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::encode::EncodeSettings;
+use crate::executables::Executables;
+use crate::filename::slug;
+use crate::{progress, Chapter};
+
+/// File names inside each chapter's HLS directory.
+const PLAYLIST_FILE: &str = "clip.m3u8";
+const SEGMENT_PATTERN: &str = "clip_%03d.ts";
+
+/// Name of the master playlist listing every chapter's playlist.
+pub const MASTER_PLAYLIST_FILE: &str = "index.m3u8";
+
+/// Settings for `--hls`.
+pub struct HlsOptions<'a> {
+    pub segment_duration: u32,
+    /// Absolute URL the `hls` directory is served from.
+    pub base_url: Option<&'a str>,
+}
+
+/// Converts every clip into an HLS stream in `hls_dir/<slug>/`, where the
+/// slug comes from the clip's file name, and writes a master playlist
+/// listing them all.
+pub fn generate_hls(
+    executables: &Executables,
+    clips: &[(PathBuf, &Chapter)],
+    hls_dir: &Path,
+    encode: &EncodeSettings,
+    options: &HlsOptions,
+) -> Result<()> {
+    // The clips already have every filter applied; only the codecs carry over.
+    let encode = EncodeSettings {
+        tone_map: None,
+        hwaccel: None,
+        watermark: None,
+        deinterlace: None,
+        denoise: None,
+        ..encode.clone()
+    };
+
+    let pb = progress::bar(clips.len());
+    let mut master = String::from("#EXTM3U\n#EXT-X-VERSION:3\n");
+
+    for (clip, chapter) in clips {
+        let name = clip.file_stem().unwrap_or_default().to_string_lossy();
+        pb.set_message(format!("HLS: {}", chapter.title));
+
+        let slug = slug(&name);
+        let output_dir = hls_dir.join(&slug);
+        if let Err(e) = segment(executables, clip, &output_dir, &slug, &encode, options) {
+            pb.finish_and_clear();
+            return Err(e).with_context(|| format!("Failed to create HLS stream for {}", name));
+        }
+
+        let duration = chapter.end_time - chapter.start_time;
+        let _ = writeln!(master, "# {}", chapter.title);
+        let _ = writeln!(
+            master,
+            "#EXT-X-STREAM-INF:BANDWIDTH={}",
+            bandwidth(&output_dir, duration)
+        );
+        let _ = writeln!(master, "{}", url(options.base_url, &slug, PLAYLIST_FILE));
+
+        pb.inc(1);
+    }
+
+    let master_path = hls_dir.join(MASTER_PLAYLIST_FILE);
+    fs::write(&master_path, master)
+        .with_context(|| format!("Failed to write {}", master_path.display()))?;
+
+    pb.finish_with_message("HLS streams created");
+
+    Ok(())
+}
+
+fn segment(
+    executables: &Executables,
+    clip: &Path,
+    output_dir: &Path,
+    slug: &str,
+    encode: &EncodeSettings,
+    options: &HlsOptions,
+) -> Result<()> {
+    fs::create_dir_all(output_dir)
+        .with_context(|| format!("Failed to create {}", output_dir.display()))?;
+
+    let mut command = executables.ffmpeg();
+    command
+        .args(["-i", clip.to_str().unwrap()])
+        .args(encode.ffmpeg_args())
+        // Start every segment on a keyframe so they can be cut evenly.
+        .args([
+            "-force_key_frames",
+            &format!("expr:gte(t,n_forced*{})", options.segment_duration),
+        ])
+        .args(["-hls_time", &options.segment_duration.to_string()])
+        .args(["-hls_playlist_type", "vod"])
+        .args([
+            "-hls_segment_filename",
+            output_dir.join(SEGMENT_PATTERN).to_str().unwrap(),
+        ]);
+    if let Some(base_url) = options.base_url {
+        command.args(["-hls_base_url", &url(Some(base_url), slug, "")]);
+    }
+    command.args(["-y", output_dir.join(PLAYLIST_FILE).to_str().unwrap()]);
+
+    executables.run(&mut command)
+}
+
+/// Average bitrate of a chapter's segments, which the master playlist requires.
+fn bandwidth(dir: &Path, duration: f64) -> u64 {
+    let bytes: u64 = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok()?.metadata().ok())
+        .map(|metadata| metadata.len())
+        .sum();
+    if duration > 0.0 {
+        (bytes as f64 * 8.0 / duration) as u64
+    } else {
+        0
+    }
+}
+
+/// `file` in the chapter directory `slug`, relative to the master playlist or
+/// under `base_url`.
+fn url(base_url: Option<&str>, slug: &str, file: &str) -> String {
+    match base_url {
+        Some(base_url) => format!("{}/{}/{}", base_url.trim_end_matches('/'), slug, file),
+        None => format!("{}/{}", slug, file),
+    }
+}
@@ -0,0 +1,108 @@
+use regex::{Captures, Regex};
+use std::path::Path;
+use std::process::Stdio;
+use std::sync::LazyLock;
+use tokio::process::Command;
+
+use crate::Chapter;
+
+static PLACEHOLDER: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\{(\w+)\}").unwrap());
+
+/// Runs a user's `--on-chapter-complete` or `--on-complete` command with
+/// `sh -c`. Each of `vars` is exported as `YT_CLIPPER_<NAME>`, and `{name}`
+/// in the command becomes a reference to that variable, so it can be quoted
+/// like one (`upload "{clip_path}"`) and a value can never run as shell
+/// code. A failing hook only produces a warning.
+pub async fn run(command: &str, vars: &[(&str, String)]) {
+    let expanded = expand(command, vars);
+
+    // The hook's output goes to stderr so it can't corrupt --json output.
+    let result = Command::new("sh")
+        .arg("-c")
+        .arg(&expanded)
+        .envs(vars.iter().map(|(name, value)| (env_name(name), value)))
+        .stdout(Stdio::from(std::io::stderr()))
+        .status()
+        .await;
+
+    match result {
+        Ok(status) if status.success() => {}
+        Ok(status) => estatus!("Warning: hook `{}` exited with {}", command, status),
+        Err(e) => estatus!("Warning: failed to run hook `{}`: {}", command, e),
+    }
+}
+
+/// A video's `--on-chapter-complete` command, run as each clip is written.
+#[derive(Clone, Copy)]
+pub struct ChapterHook<'a> {
+    pub command: &'a str,
+    pub video_title: &'a str,
+}
+
+impl ChapterHook<'_> {
+    pub async fn run(&self, chapter: &Chapter, clip: &Path) {
+        run(
+            self.command,
+            &[
+                ("clip_path", clip.display().to_string()),
+                ("chapter_title", chapter.title.clone()),
+                ("chapter_index", chapter.number.to_string()),
+                ("video_title", self.video_title.to_string()),
+                ("start_time", format!("{:.3}", chapter.start_time)),
+                ("end_time", format!("{:.3}", chapter.end_time)),
+            ],
+        )
+        .await
+    }
+}
+
+/// The environment variable a hook variable is exported as, e.g.
+/// `YT_CLIPPER_CLIP_PATH` for `clip_path`.
+fn env_name(name: &str) -> String {
+    format!("YT_CLIPPER_{}", name.to_ascii_uppercase())
+}
+
+fn expand(command: &str, vars: &[(&str, String)]) -> String {
+    PLACEHOLDER
+        .replace_all(command, |caps: &Captures| {
+            match vars.iter().find(|(name, _)| *name == &caps[1]) {
+                Some((name, _)) => format!("${{{}}}", env_name(name)),
+                None => caps[0].to_string(),
+            }
+        })
+        .into_owned()
+}
+
+pub(crate) fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn expands_placeholders_to_environment_variables() {
+        let vars = [("clip_path", "it's a clip.mp4".to_string())];
+        assert_eq!(
+            expand("upload \"{clip_path}\" {unknown}", &vars),
+            "upload \"${YT_CLIPPER_CLIP_PATH}\" {unknown}"
+        );
+    }
+
+    #[tokio::test]
+    async fn passes_values_without_shell_interpretation() {
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("out.txt");
+        let vars = [
+            ("clip_path", "a 'b' $(touch pwned) \"c\".mp4".to_string()),
+            ("output", output.display().to_string()),
+        ];
+        run("printf %s \"{clip_path}\" > \"{output}\"", &vars).await;
+        assert_eq!(
+            fs::read_to_string(&output).unwrap(),
+            "a 'b' $(touch pwned) \"c\".mp4"
+        );
+    }
+}
@@ -0,0 +1,78 @@
+use anyhow::Result;
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::checksums;
+
+/// How the clips of an earlier run compare to the chapters fetched now.
+#[derive(Debug, Default)]
+pub struct Comparison {
+    /// Indices of clip names that already exist.
+    pub existing: Vec<usize>,
+    /// Indices of clip names whose chapter already exists under another
+    /// number, with the existing name. The creator probably reordered chapters.
+    pub moved: Vec<(usize, String)>,
+}
+
+impl Comparison {
+    /// Whether the clip at `index` should be left alone.
+    pub fn skips(&self, index: usize) -> bool {
+        self.existing.contains(&index) || self.moved.iter().any(|(i, _)| *i == index)
+    }
+}
+
+/// Compares `names` (clip names without extension, relative to `clips_dir`)
+/// against the `extension` files already in `clips_dir`.
+pub fn compare(clips_dir: &Path, names: &[String], extension: &str) -> Result<Comparison> {
+    let mut files = Vec::new();
+    if clips_dir.is_dir() {
+        checksums::collect_files(clips_dir, &mut files)?;
+    }
+
+    let existing: Vec<String> = files
+        .iter()
+        .filter(|path| path.extension().is_some_and(|ext| ext == extension))
+        .filter_map(|path| {
+            let relative = path.strip_prefix(clips_dir).ok()?.with_extension("");
+            Some(relative.to_string_lossy().replace('\\', "/"))
+        })
+        .collect();
+
+    // Existing clips by their name without the chapter number.
+    let unnumbered: HashMap<String, &String> = existing
+        .iter()
+        .map(|name| (strip_number(name), name))
+        .collect();
+
+    let mut comparison = Comparison::default();
+    for (i, name) in names.iter().enumerate() {
+        if existing.contains(name) {
+            comparison.existing.push(i);
+        } else if let Some(previous) = unnumbered.get(&strip_number(name)) {
+            if !names.contains(previous) {
+                comparison.moved.push((i, previous.to_string()));
+            }
+        }
+    }
+
+    Ok(comparison)
+}
+
+/// `name` without the leading chapter number (and its separator) of its
+/// file name, so `01_Intro` and `03_Intro` compare equal.
+fn strip_number(name: &str) -> String {
+    let (dir, file) = match name.rsplit_once('/') {
+        Some((dir, file)) => (Some(dir), file),
+        None => (None, name),
+    };
+    let stripped = file.trim_start_matches(|c: char| c.is_ascii_digit());
+    let stripped = if stripped.len() < file.len() {
+        stripped.trim_start_matches(['_', '-', ' ', '.'])
+    } else {
+        file
+    };
+    match dir {
+        Some(dir) => format!("{}/{}", dir, stripped),
+        None => stripped.to_string(),
+    }
+}
@@ -0,0 +1,58 @@
+use anyhow::{Context, Result};
+use dialoguer::MultiSelect;
+use std::io::{self, IsTerminal};
+
+use crate::plan::format_timestamp;
+use crate::Chapter;
+
+/// Lets the user tick the chapters to process from `chapters`, starting with
+/// `preselected` (the ones the other selection flags picked) ticked.
+///
+/// Returns `preselected` unchanged with a warning when there is no terminal
+/// to ask on.
+pub fn pick_chapters(chapters: &[Chapter], preselected: Vec<Chapter>) -> Result<Vec<Chapter>> {
+    if !io::stdout().is_terminal() || !io::stdin().is_terminal() {
+        estatus!("Warning: --interactive needs a terminal, processing the selected chapters");
+        return Ok(preselected);
+    }
+
+    let items: Vec<String> = chapters
+        .iter()
+        .map(|chapter| {
+            format!(
+                "{:>3}. {} ({})",
+                chapter.number,
+                chapter.title,
+                format_timestamp(chapter.end_time - chapter.start_time)
+            )
+        })
+        .collect();
+    let defaults: Vec<bool> = chapters
+        .iter()
+        .map(|chapter| preselected.iter().any(|c| c.number == chapter.number))
+        .collect();
+
+    let picked = MultiSelect::new()
+        .with_prompt("Chapters to process (space to toggle, enter to confirm)")
+        .items(&items)
+        .defaults(&defaults)
+        .interact()
+        .context("Failed to read chapter selection")?;
+
+    if picked.is_empty() {
+        anyhow::bail!("No chapters selected");
+    }
+
+    // Keep the preselected (and possibly padded) copies where possible.
+    Ok(picked
+        .into_iter()
+        .map(|i| {
+            let number = chapters[i].number;
+            preselected
+                .iter()
+                .find(|c| c.number == number)
+                .cloned()
+                .unwrap_or_else(|| chapters[i].clone())
+        })
+        .collect())
+}
@@ -0,0 +1,97 @@
+use anyhow::{Context, Result};
+use std::path::Path;
+
+use crate::executables::Executables;
+
+/// How many frames `--detect-interlace` analyzes.
+const SAMPLE_FRAMES: u32 = 200;
+
+/// Whether `video` is interlaced, judged by the `idet` filter over its first
+/// frames. idet prints its totals to stderr, e.g.
+/// `Repeated Fields: Neither: 180 Top: 10 Bottom: 10` and
+/// `Multi frame detection: TFF: 150 BFF: 0 Progressive: 48 Undetermined: 2`;
+/// the video counts as interlaced when most frames have repeated or
+/// interlaced fields.
+pub fn detect_interlace(executables: &Executables, video: &Path) -> Result<bool> {
+    let output = executables
+        .ffmpeg()
+        .args(["-hide_banner", "-i", video.to_str().unwrap(), "-an"])
+        .args(["-vf", "idet", "-frames:v", &SAMPLE_FRAMES.to_string()])
+        .args(["-f", "null", "-"])
+        .output()
+        .context("Failed to execute ffmpeg")?;
+
+    if !output.status.success() {
+        anyhow::bail!("Interlace detection failed");
+    }
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let repeated = counts(&stderr, "Repeated Fields:");
+    let detected = counts(&stderr, "Multi frame detection:");
+    if repeated.is_none() && detected.is_none() {
+        anyhow::bail!("ffmpeg's idet filter printed no statistics");
+    }
+
+    let repeated_fields = repeated.is_some_and(|counts| {
+        count(&counts, "Top") + count(&counts, "Bottom") > count(&counts, "Neither")
+    });
+    let interlaced_frames = detected.is_some_and(|counts| {
+        count(&counts, "TFF") + count(&counts, "BFF") > count(&counts, "Progressive")
+    });
+    Ok(repeated_fields || interlaced_frames)
+}
+
+/// The `Name: count` pairs following `label` on the last line that has it.
+fn counts(stderr: &str, label: &str) -> Option<Vec<(String, u64)>> {
+    let line = stderr.lines().rev().find(|line| line.contains(label))?;
+    let rest = &line[line.find(label)? + label.len()..];
+    let words: Vec<&str> = rest.split_whitespace().collect();
+    Some(
+        words
+            .chunks(2)
+            .filter_map(|pair| match pair {
+                [name, count] => {
+                    Some((name.trim_end_matches(':').to_string(), count.parse().ok()?))
+                }
+                _ => None,
+            })
+            .collect(),
+    )
+}
+
+fn count(counts: &[(String, u64)], name: &str) -> u64 {
+    counts
+        .iter()
+        .find(|(counted, _)| counted == name)
+        .map_or(0, |(_, count)| *count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STDERR: &str = "[Parsed_idet_0 @ 0x1] Repeated Fields: Neither:   180 Top:    10 Bottom:    10\n\
+        [Parsed_idet_0 @ 0x1] Single frame detection: TFF:    90 BFF:     0 Progressive:   100 Undetermined:    10\n\
+        [Parsed_idet_0 @ 0x1] Multi frame detection: TFF:   150 BFF:     0 Progressive:    48 Undetermined:     2\n";
+
+    #[test]
+    fn reads_idet_counts() {
+        let detected = counts(STDERR, "Multi frame detection:").unwrap();
+        assert_eq!(count(&detected, "TFF"), 150);
+        assert_eq!(count(&detected, "Progressive"), 48);
+        assert_eq!(count(&detected, "Missing"), 0);
+
+        let repeated = counts(STDERR, "Repeated Fields:").unwrap();
+        assert_eq!(count(&repeated, "Neither"), 180);
+        assert_eq!(count(&repeated, "Bottom"), 10);
+    }
+
+    #[test]
+    fn reads_the_last_line_with_the_label() {
+        let stderr = "Multi frame detection: TFF: 1 BFF: 0 Progressive: 0\n\
+                      Multi frame detection: TFF: 5 BFF: 0 Progressive: 9\n";
+        let detected = counts(stderr, "Multi frame detection:").unwrap();
+        assert_eq!(count(&detected, "TFF"), 5);
+        assert!(counts(stderr, "Repeated Fields:").is_none());
+    }
+}
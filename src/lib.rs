@@ -0,0 +1,4026 @@
+//! Splits videos into one clip per chapter, using yt-dlp to fetch and
+//! download them and ffmpeg to cut them.
+//!
+//! The `yt-clipper` binary is a thin wrapper around [`run`]. To use the
+//! pieces from another program, build a [`ClipperConfig`] and call
+//! [`get_video_info`], [`download_video`], [`split_video_into_chapters`] and
+//! [`generate_format_variants`]. Splitting is async, so it needs a tokio
+//! runtime:
+//!
+//! ```no_run
+//! # async fn clip() -> Result<(), yt_clipper::Error> {
+//! let config = yt_clipper::ClipperConfig::new()?;
+//! let url = "https://www.youtube.com/watch?v=dQw4w9WgXcQ";
+//! let video = yt_clipper::get_video_info(&config, url)?;
+//! let path = yt_clipper::download_video(&config, url, "out".as_ref())?;
+//! let chapters = video.chapters.unwrap_or_default();
+//! let clips_dir = "out/clips".as_ref();
+//! yt_clipper::split_video_into_chapters(&config, &path, &video.title, &chapters, clips_dir)
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use anyhow::{Context, Result};
+use archive::Archive;
+use audio::AudioChannels;
+use availability::Unavailable;
+use bumpers::Bumpers;
+use chapters::{ChapterSelection, ChapterSkips, DurationLimits, Gap};
+use clap::{ArgMatches, CommandFactory, Parser, Subcommand, ValueHint};
+use clap_complete::Shell;
+use config::Config;
+use cue::CueAlbum;
+use db::{Database, VideoRecord};
+use encode::VideoCodec;
+use executables::{Browser, CommandError};
+use export::ChapterExport;
+use formats::FormatVariant;
+use futures::stream::{self, StreamExt};
+use hls::HlsOptions;
+use hooks::ChapterHook;
+use metadata::DateFormat;
+use nfo::{NfoClip, NfoVideo};
+use plan::{ListedChapter, Plan, PlannedClip, PlannedVariant};
+use probe::MediaInfo;
+use progress::Event;
+use regex::Regex;
+use retry::RetryPolicy;
+use serde::{Deserialize, Serialize};
+use silence::SilenceOptions;
+use speed::SpeedSettings;
+use std::collections::BTreeMap;
+use std::fs;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime};
+use tempfile::NamedTempFile;
+use template::{
+    DirContext, DirTemplate, Template, TemplateContext, DEFAULT_DIR_TEMPLATE, DEFAULT_TEMPLATE,
+};
+use thumbnails::{SpriteLayout, ThumbnailFormat};
+use tokio::task;
+use tracks::{AudioTrack, AudioTracks};
+use upload::S3Options;
+use watch::WatchState;
+
+/// File name (without extension) of the downloaded full video.
+const FULL_VIDEO_STEM: &str = "full_video";
+
+/// yt-dlp format selector used without `--format` or `--max-height`.
+const DEFAULT_FORMAT: &str = "bestvideo+bestaudio/best";
+
+/// Set when stdout is reserved for machine-readable output.
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+/// Prints a human-readable status message unless `QUIET` is set, copying it
+/// into the log file.
+macro_rules! status {
+    ($($arg:tt)*) => {
+        if !crate::QUIET.load(std::sync::atomic::Ordering::Relaxed) {
+            use std::io::Write as _;
+            let _ = writeln!(crate::log::stdout(), $($arg)*);
+        }
+    };
+}
+
+/// Prints a warning or error to stderr, copying it into the log file.
+macro_rules! estatus {
+    ($($arg:tt)*) => {{
+        use std::io::Write as _;
+        let _ = writeln!(crate::log::stderr(), $($arg)*);
+    }};
+}
+
+mod archive;
+mod audio;
+mod availability;
+mod bumpers;
+mod chapters;
+mod checksums;
+mod config;
+mod cover;
+mod cue;
+mod db;
+mod encode;
+mod error;
+mod executables;
+mod export;
+mod filename;
+mod formats;
+mod hls;
+mod hooks;
+mod incremental;
+mod interactive;
+mod interlace;
+mod log;
+mod loudnorm;
+mod m3u;
+mod metadata;
+mod nfo;
+mod plan;
+mod probe;
+mod progress;
+mod proxy;
+mod report;
+mod retry;
+mod scenes;
+mod silence;
+mod speed;
+mod sponsorblock;
+mod stems;
+mod subtitles;
+mod template;
+mod thumbnails;
+mod tracks;
+mod upload;
+mod versions;
+mod watch;
+mod watermark;
+mod waveforms;
+
+pub use audio::{AudioFormat, AudioSettings};
+pub use encode::{
+    Container, DeinterlaceMode, Denoise, DenoiseFilter, EncodeSettings, HwAccel, Preset, Scale,
+    ScaleMethod, ToneMapAlgorithm,
+};
+pub use error::Error;
+pub use executables::{Executables, NetworkOptions};
+pub use filename::{FilenameOptions, NumberingStyle, SanitizeMode};
+pub use formats::{FormatOptions, Gravity};
+pub use metadata::VideoMetadata;
+pub use report::ChapterResult;
+pub use watermark::{Watermark, WatermarkPosition};
+
+/// Command line options.
+#[derive(Parser, Debug, Serialize, Deserialize)]
+#[command(name = "yt-clipper")]
+#[command(about = "Split YouTube videos into chapters with multiple format variants", long_about = None)]
+#[command(subcommand_negates_reqs = true)]
+#[command(after_help = COMPLETIONS_HELP)]
+pub struct Args {
+    #[arg(value_name = "URL", value_hint = ValueHint::Url, required_unless_present_any = ["print_config", "print_profiles", "input_file", "verify_checksums", "version"])]
+    url: Option<String>,
+
+    /// Read URLs to process from FILE, one per line ("-" reads from stdin)
+    #[arg(short, long, value_name = "FILE")]
+    input_file: Option<PathBuf>,
+
+    /// Write URLs that failed to process to FILE so they can be retried
+    #[arg(long, value_name = "FILE")]
+    failed_output: Option<PathBuf>,
+
+    /// Treat the URL as a playlist and process every video in it (detected
+    /// automatically for playlist URLs)
+    #[arg(long)]
+    playlist: bool,
+
+    /// First playlist item to process (1-based)
+    #[arg(long, value_name = "N")]
+    playlist_start: Option<usize>,
+
+    /// Last playlist item to process
+    #[arg(long, value_name = "N")]
+    playlist_end: Option<usize>,
+
+    /// Directory under which the video-titled output directory is created
+    #[arg(long, value_name = "PATH")]
+    output_dir: Option<PathBuf>,
+
+    /// Write clips directly into --output-dir instead of a video-titled subdirectory
+    #[arg(long, conflicts_with_all = ["playlist", "input_file"])]
+    flat: bool,
+
+    /// Output directory (under --output-dir) for each video, e.g.
+    /// "{uploader}/{upload_date}_{title}". Placeholders: {title}, {uploader},
+    /// {upload_date}, {id}
+    #[arg(long, value_name = "TEMPLATE", default_value = DEFAULT_DIR_TEMPLATE, conflicts_with = "flat")]
+    dir_template: String,
+
+    /// Append the video ID to the output directory and downloaded file
+    /// names, yt-dlp style, e.g. "My Video [dQw4w9WgXcQ]"
+    #[arg(long)]
+    include_id: bool,
+
+    /// Start the output directory name with the upload date, e.g.
+    /// "20240315_My Video", so directories sort chronologically
+    #[arg(long, conflicts_with = "flat")]
+    date_prefix: bool,
+
+    /// How --date-prefix writes the date: ymd (20240315), iso (2024-03-15)
+    /// or unix (1710460800)
+    #[arg(long, value_enum, value_name = "FORMAT", default_value_t = DateFormat::Ymd)]
+    date_format: DateFormat,
+
+    #[arg(short, long)]
+    keep_full: bool,
+
+    #[arg(short, long)]
+    formats: bool,
+
+    /// Number of chapters to split concurrently (0 = one per CPU core)
+    #[arg(short, long, default_value_t = 1)]
+    jobs: usize,
+
+    /// Retry failed video info lookups and downloads this many times
+    #[arg(long, value_name = "N", default_value_t = 3)]
+    retries: u32,
+
+    /// Wait before the first retry, doubling for every retry after it
+    #[arg(long, value_name = "MILLISECONDS", default_value_t = 500)]
+    retry_delay_ms: u64,
+
+    /// Pause this long between chapters to ease disk contention on slow drives
+    #[arg(long, value_name = "MILLISECONDS")]
+    sleep_chapters: Option<u64>,
+
+    /// Limit the download speed, e.g. "5M" for 5 MB/s. Useful for leaving
+    /// network capacity free when processing in the background
+    #[arg(long, value_name = "RATE")]
+    bandwidth_limit: Option<String>,
+
+    /// Output filename template, e.g. "{video_title}/{num:03}-{title}".
+    /// Placeholders: {num}, {title}, {start}, {end}, {duration}, {video_title}.
+    /// {num} without a format spec is padded to --pad-width
+    #[arg(short, long, default_value = DEFAULT_TEMPLATE)]
+    template: String,
+
+    /// How chapter and video titles are cleaned up for file names
+    #[arg(long, value_enum, value_name = "MODE", default_value_t = SanitizeMode::Strict)]
+    filename_sanitize: SanitizeMode,
+
+    /// Remove emoji from titles before using them in file names
+    #[arg(long)]
+    strip_emoji: bool,
+
+    /// Zero-pad {num} to this many digits [default: 2, or more for videos with 100+ chapters]
+    #[arg(long, value_name = "N")]
+    pad_width: Option<usize>,
+
+    /// Name clips after the chapter title alone, without the number prefix
+    #[arg(long, conflicts_with = "template")]
+    no_number: bool,
+
+    /// Shorten file and directory names longer than this many characters,
+    /// keeping them unique with a hash [default: 100 on Windows, 200 elsewhere]
+    #[arg(long, value_name = "N", default_value_t = filename::DEFAULT_MAX_LENGTH, hide_default_value = true, value_parser = filename::parse_max_length)]
+    max_filename_length: usize,
+
+    /// Join the chapter number and title with this instead of "_", e.g. " - "
+    #[arg(long, value_name = "STRING", conflicts_with_all = ["template", "no_number"], value_parser = filename::parse_separator)]
+    separator: Option<String>,
+
+    /// Replace the spaces in chapter titles with this in clip names, e.g. "_"
+    /// [default: keep spaces]
+    #[arg(long, value_name = "STRING", value_parser = filename::parse_separator)]
+    word_separator: Option<String>,
+
+    /// How {num} is written in clip names; `none` leaves it out like --no-number
+    #[arg(long, value_enum, value_name = "STYLE", default_value_t = NumberingStyle::Arabic)]
+    numbering_style: NumberingStyle,
+
+    /// Report progress as JSON lines on stdout instead of progress bars and messages
+    #[arg(long, conflicts_with_all = ["json", "list_chapters_json"])]
+    json_progress: bool,
+
+    /// Show the source video's streams before splitting (always on with --verbose),
+    /// or estimated clip sizes with --dry-run
+    #[arg(long)]
+    info: bool,
+
+    /// Print the planned clips and format variants without downloading anything
+    #[arg(long)]
+    dry_run: bool,
+
+    /// Print the dry run plan as JSON
+    #[arg(long, requires = "dry_run")]
+    json: bool,
+
+    /// Show the output of ffmpeg and yt-dlp and extra details about what is being run
+    #[arg(short, long)]
+    verbose: bool,
+
+    /// Also write all messages and ffmpeg/yt-dlp output to FILE
+    #[arg(long, value_name = "FILE")]
+    log_file: Option<PathBuf>,
+
+    /// Only process these chapters, e.g. "5-12", "1,3,5" or "3-"
+    #[arg(long, value_name = "RANGE")]
+    chapters: Option<String>,
+
+    /// Skip chapter N (1-based); can be repeated
+    #[arg(long = "skip-chapter", value_name = "N", value_parser = clap::value_parser!(u64).range(1..))]
+    skip_chapters: Vec<u64>,
+
+    /// Skip chapters whose title matches this regex
+    #[arg(long, value_name = "REGEX")]
+    skip_chapter_pattern: Option<String>,
+
+    /// Number clips consecutively, as if skipped chapters weren't there
+    #[arg(long)]
+    renumber_skipped: bool,
+
+    /// Read chapters from this JSON or YAML (.yml/.yaml) file instead of the
+    /// video's chapter markers (a list of {"title", "start_time", "end_time"}
+    /// entries, times in seconds)
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["playlist", "input_file"])]
+    chapters_file: Option<PathBuf>,
+
+    /// Cut this time range (e.g. "1:00-2:30" or "12.5-20") instead of using chapters; repeatable
+    #[arg(
+        long,
+        value_name = "START-END",
+        conflicts_with_all = ["chapters_file", "parse_description", "playlist", "input_file"]
+    )]
+    clip: Vec<String>,
+
+    /// Title of the preceding --clip, used for its file name instead of the time range
+    #[arg(long, value_name = "TITLE", requires = "clip")]
+    clip_title: Vec<String>,
+
+    /// Take chapters from timestamps in the video description (e.g. "5:32 Main Topic")
+    #[arg(long, conflicts_with = "chapters_file")]
+    parse_description: bool,
+
+    /// Fail instead of warning when chapters are empty, overlap or are out
+    /// of order
+    #[arg(long)]
+    strict_chapters: bool,
+
+    /// Report the parts of the video no chapter covers: before the first
+    /// chapter, between chapters and after the last one
+    #[arg(long)]
+    detect_gaps: bool,
+
+    /// Also split the gaps between chapters into clips named like
+    /// gap_01_between_ch1_ch2 (implies --detect-gaps)
+    #[arg(long)]
+    extract_gaps: bool,
+
+    /// Ignore gaps shorter than this
+    #[arg(long, value_name = "SECONDS", default_value_t = 5.0)]
+    min_gap_duration: f64,
+
+    /// When a video has no chapters, split it at scene changes instead
+    #[arg(long)]
+    auto_chapters: bool,
+
+    /// Shortest chapter --auto-chapters creates
+    #[arg(long, value_name = "SECONDS", default_value_t = 30.0)]
+    auto_chapter_min_duration: f64,
+
+    /// Use this title for the video instead of the one from yt-dlp
+    #[arg(long, conflicts_with_all = ["playlist", "input_file"])]
+    title: Option<String>,
+
+    /// Pick the chapters to process from a checklist before downloading
+    #[arg(long)]
+    interactive: bool,
+
+    /// Only process chapters whose title matches this regex
+    #[arg(long, value_name = "REGEX")]
+    chapter_filter: Option<String>,
+
+    /// Also combine consecutive chapters whose titles match this regex into a
+    /// single clip
+    #[arg(long, value_name = "REGEX")]
+    merge_pattern: Option<String>,
+
+    /// Suffix added to the name of merged clips
+    #[arg(long, value_name = "SUFFIX", default_value = "_merged")]
+    merge_suffix: String,
+
+    /// Don't split the video; write it as one file with the chapters embedded
+    /// as chapter marks
+    #[arg(long, conflicts_with_all = ["formats", "thumbnails", "hls", "nfo", "merge_pattern"])]
+    concat_only: bool,
+
+    /// Skip chapters shorter than this
+    #[arg(long, value_name = "SECONDS")]
+    min_duration: Option<f64>,
+
+    /// Skip chapters longer than this
+    #[arg(long, value_name = "SECONDS")]
+    max_duration: Option<f64>,
+
+    /// List the chapters of the video with their durations and exit
+    #[arg(long)]
+    list_chapters: bool,
+
+    /// List the chapters of the video as a JSON array and exit
+    #[arg(long)]
+    list_chapters_json: bool,
+
+    /// Write playlist.m3u8 listing the clips in chapter order (and one per format variant)
+    #[arg(long)]
+    write_playlist: bool,
+
+    /// Re-encode clips instead of stream copying (fixes keyframe misalignment at chapter boundaries)
+    #[arg(long)]
+    reencode: bool,
+
+    /// Cut clips on the exact frame instead of the nearest keyframe (re-encodes, implied by --reencode)
+    #[arg(long)]
+    accurate_seek: bool,
+
+    /// Constant rate factor used when re-encoding (lower is better quality) [default: 23, 28 for HEVC, 30 for AV1, 33 for VP9]
+    #[arg(long)]
+    crf: Option<u32>,
+
+    /// Re-encode clips with this video codec (AV1 output defaults to MKV,
+    /// VP9 to WebM)
+    #[arg(long, value_enum, conflicts_with = "video_codec")]
+    codec: Option<VideoCodec>,
+
+    /// Encode VP9 clips in two passes, which spends the bits where the video
+    /// needs them most
+    #[arg(long)]
+    two_pass: bool,
+
+    /// Video codec used when re-encoding
+    #[arg(long, value_name = "CODEC", default_value = encode::DEFAULT_VIDEO_CODEC)]
+    video_codec: String,
+
+    /// Audio codec used when re-encoding
+    #[arg(long, value_name = "CODEC", default_value = encode::DEFAULT_AUDIO_CODEC)]
+    audio_codec: String,
+
+    /// Audio bitrate used when re-encoding and for lossy audio-only exports
+    #[arg(long, value_name = "BITRATE", default_value = audio::DEFAULT_BITRATE)]
+    audio_bitrate: String,
+
+    /// x264/x265 encoding speed preset used when re-encoding
+    #[arg(long, value_enum)]
+    preset: Option<Preset>,
+
+    /// Tone map HDR video to SDR (BT.709) so clips don't look washed out (implies --reencode)
+    #[arg(long)]
+    tone_map: bool,
+
+    /// Tone mapping curve used by --tone-map
+    #[arg(long, value_enum, value_name = "ALGORITHM", default_value_t = ToneMapAlgorithm::Hable)]
+    tone_map_algorithm: ToneMapAlgorithm,
+
+    /// Convert clips to this frame rate, e.g. 30 or 29.97 (implies --reencode
+    /// unless the source already has it)
+    #[arg(long, value_name = "FPS", value_parser = encode::parse_fps)]
+    fps: Option<f64>,
+
+    /// Scale clips to this size, e.g. 1920x1080 (implies --reencode)
+    #[arg(long, value_name = "WxH", value_parser = encode::parse_scale)]
+    scale: Option<(u32, u32)>,
+
+    /// How --scale handles a different aspect ratio: fit (pad with black
+    /// bars), fill (crop) or stretch
+    #[arg(long, value_enum, value_name = "METHOD", default_value_t = ScaleMethod::Fit)]
+    scale_method: ScaleMethod,
+
+    /// Deinterlace clips of interlaced broadcast captures to remove combing
+    /// (implies --reencode)
+    #[arg(long)]
+    deinterlace: bool,
+
+    /// Filter used by --deinterlace and --detect-interlace
+    #[arg(long, value_enum, value_name = "MODE", default_value_t = DeinterlaceMode::Yadif)]
+    deinterlace_mode: DeinterlaceMode,
+
+    /// Analyze the first frames of each video and deinterlace it if it turns
+    /// out to be interlaced
+    #[arg(long, conflicts_with = "deinterlace")]
+    detect_interlace: bool,
+
+    /// Reduce compression noise before encoding (implies --reencode)
+    #[arg(long)]
+    denoise: bool,
+
+    /// Noise reduction filter used by --denoise
+    #[arg(long, value_enum, value_name = "FILTER", default_value_t = DenoiseFilter::Hqdn3d)]
+    denoise_filter: DenoiseFilter,
+
+    /// Multiply every parameter of the --denoise filter by this, e.g. 0.5
+    /// for lighter or 2 for heavier noise reduction
+    #[arg(long, value_name = "FLOAT", default_value_t = 1.0, value_parser = encode::parse_denoise_strength)]
+    denoise_strength: f64,
+
+    /// Resample all audio to this rate, e.g. 48000. Audio is re-encoded even
+    /// when stream copying if the source has a different rate
+    #[arg(long, value_name = "HZ", value_parser = encode::parse_sample_rate)]
+    sample_rate: Option<u32>,
+
+    /// Resample with the SoX resampler (aresample=resampler=soxr) for the
+    /// best quality
+    #[arg(long, requires = "sample_rate")]
+    high_quality_resample: bool,
+
+    /// Convert clip and format variant audio to 1 (mono) or 2 (stereo, e.g.
+    /// for surround sources played on phones) channels, or keep the source's.
+    /// Audio is re-encoded even when stream copying if the source has a
+    /// different channel count
+    #[arg(long, value_enum, value_name = "N")]
+    audio_channels: Option<AudioChannels>,
+
+    /// Keep several audio tracks, such as the original and dubbed languages:
+    /// `all`, or track numbers like `0,2` as listed by --info. Clips are MKV
+    /// unless a container is chosen, as MP4 doesn't hold several audio tracks
+    /// reliably
+    #[arg(long, value_name = "TRACKS")]
+    audio_tracks: Option<AudioTracks>,
+
+    /// Overlay this image (e.g. a PNG logo) on every clip (implies --reencode)
+    #[arg(long, value_name = "FILE")]
+    watermark_image: Option<PathBuf>,
+
+    /// Draw this text on every clip (implies --reencode)
+    #[arg(long, value_name = "STRING")]
+    watermark_text: Option<String>,
+
+    /// Where to place the watermark: top-left, top-right, bottom-left,
+    /// bottom-right, or X:Y pixel coordinates
+    #[arg(long, value_name = "POSITION", default_value = "bottom-right")]
+    watermark_position: WatermarkPosition,
+
+    /// Opacity of the watermark, from 0.0 (invisible) to 1.0 (opaque)
+    #[arg(long, value_name = "FLOAT", default_value_t = 1.0, value_parser = watermark::parse_opacity)]
+    watermark_opacity: f64,
+
+    /// Font name or font file for --watermark-text
+    #[arg(long, value_name = "FONT")]
+    watermark_font: Option<String>,
+
+    /// Font size in pixels for --watermark-text
+    #[arg(long, value_name = "PIXELS", default_value_t = 24)]
+    watermark_font_size: u32,
+
+    /// Text color for --watermark-text, as an ffmpeg color name or hex value
+    #[arg(long, value_name = "COLOR", default_value = "white")]
+    watermark_color: String,
+
+    /// Play clips this many times as fast, e.g. 2.0 or 0.5 (implies --reencode)
+    #[arg(long, value_name = "MULTIPLIER", default_value_t = 1.0, value_parser = speed::parse_speed)]
+    speed: f64,
+
+    /// Per-chapter --speed as a JSON object of chapter titles to multipliers,
+    /// e.g. '{"Intro": 2.0}' (implies --reencode)
+    #[arg(long, value_name = "JSON", value_parser = speed::parse_speed_map)]
+    speed_map: Option<BTreeMap<String, f64>>,
+
+    /// Fade each clip in and out over this many seconds (requires --reencode)
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        num_args = 0..=1,
+        default_missing_value = "0.5"
+    )]
+    fade_duration: Option<f64>,
+
+    /// Pad the stories format with a blurred copy of the video instead of black bars
+    #[arg(long, visible_alias = "vertical-blur-background")]
+    blur_background: bool,
+
+    /// Part of the frame the vertical format keeps: center, left, right, or
+    /// X:Y pixel offsets of the crop
+    #[arg(long, value_name = "GRAVITY", default_value = "center")]
+    vertical_gravity: Gravity,
+
+    /// Frame rate of the GIF format variant
+    #[arg(long, value_name = "N", default_value_t = formats::DEFAULT_GIF_FPS)]
+    gif_fps: u32,
+
+    /// Width in pixels of the GIF format variant
+    #[arg(long, value_name = "PX", default_value_t = formats::DEFAULT_GIF_WIDTH)]
+    gif_width: u32,
+
+    /// Skip the GIF format variant for chapters longer than this many seconds
+    #[arg(long, value_name = "SECONDS")]
+    gif_max_duration: Option<f64>,
+
+    /// Extract a thumbnail image for each chapter into thumbnails/
+    #[arg(long)]
+    thumbnails: bool,
+
+    /// Image format of chapter thumbnails
+    #[arg(long, value_enum, default_value_t = ThumbnailFormat::Webp)]
+    thumbnail_format: ThumbnailFormat,
+
+    /// Where in each chapter to take the thumbnail, from 0.0 (start) to 1.0 (end)
+    #[arg(long, value_name = "FRACTION", default_value_t = 0.5, value_parser = thumbnails::parse_offset)]
+    thumbnail_offset: f64,
+
+    /// Write a Kodi/Jellyfin/Plex .nfo file next to every clip
+    #[arg(long)]
+    nfo: bool,
+
+    /// Save the video description to description.txt in the output directory
+    #[arg(long)]
+    description: bool,
+
+    /// Also write each chapter's part of the description (from its timestamp
+    /// line to the next) next to its clip as a .txt file
+    #[arg(long, requires = "description")]
+    description_per_chapter: bool,
+
+    /// Draw a waveform image of every clip's audio (from the audio-only exports with --formats)
+    #[arg(long)]
+    waveforms: bool,
+
+    /// Size of the --waveforms images
+    #[arg(long, value_name = "WxH", default_value = "1280x200", value_parser = waveforms::parse_size)]
+    waveform_size: (u32, u32),
+
+    /// Color of the --waveforms images (an ffmpeg color name or hex code)
+    #[arg(long, value_name = "COLOR", default_value = "cyan")]
+    waveform_color: String,
+
+    /// Tile the middle frame of every chapter into spritesheet.jpg, with a sprites.vtt preview track
+    #[arg(long)]
+    spritesheet: bool,
+
+    /// Width in pixels of each sprite sheet tile
+    #[arg(long, value_name = "PIXELS", default_value_t = 160)]
+    sprite_width: u32,
+
+    /// Number of columns in the sprite sheet [default: square grid]
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    sprite_cols: Option<u32>,
+
+    /// Download subtitles and write an SRT file next to each clip
+    #[arg(long)]
+    subtitles: bool,
+
+    /// Subtitle language to download
+    #[arg(long, value_name = "LANG", default_value = "en")]
+    sub_lang: String,
+
+    /// Embed subtitles in the clips instead of writing SRT files
+    /// (burned in when re-encoding, a soft track otherwise)
+    #[arg(long)]
+    embed_subs: bool,
+
+    /// Cut SponsorBlock segments out of the chapter clips
+    #[arg(long)]
+    sponsorblock: bool,
+
+    /// SponsorBlock categories to cut out
+    #[arg(
+        long,
+        value_name = "CATEGORIES",
+        value_delimiter = ',',
+        default_value = "sponsor,selfpromo"
+    )]
+    sponsorblock_categories: Vec<String>,
+
+    /// Normalize the loudness of each clip using two-pass EBU R128 (re-encodes audio)
+    #[arg(long)]
+    normalize_audio: bool,
+
+    /// Target integrated loudness in LUFS for --normalize-audio
+    #[arg(
+        long,
+        value_name = "LUFS",
+        default_value_t = -23.0,
+        allow_negative_numbers = true
+    )]
+    normalize_target: f64,
+
+    /// Cut silence off the start and end of each clip
+    #[arg(long)]
+    trim_silence: bool,
+
+    /// Audio quieter than this counts as silence for --trim-silence
+    #[arg(
+        long,
+        value_name = "DB",
+        default_value = "-40dB",
+        value_parser = silence::parse_threshold,
+        allow_hyphen_values = true
+    )]
+    silence_threshold: f64,
+
+    /// Shortest stretch of quiet that counts as silence for --trim-silence
+    #[arg(long, value_name = "SECONDS", default_value_t = 0.1)]
+    silence_duration: f64,
+
+    /// Join this clip onto the start of every chapter clip (re-encodes)
+    #[arg(long, value_name = "FILE")]
+    prepend_clip: Option<PathBuf>,
+
+    /// Join this clip onto the end of every chapter clip (re-encodes)
+    #[arg(long, value_name = "FILE")]
+    append_clip: Option<PathBuf>,
+
+    /// Start each chapter this many seconds earlier
+    #[arg(long, value_name = "SECONDS", default_value_t = 0.0)]
+    pre_pad: f64,
+
+    /// End each chapter this many seconds later
+    #[arg(long, value_name = "SECONDS", default_value_t = 0.0)]
+    post_pad: f64,
+
+    /// Write chapter and video details into the tags of every clip
+    #[arg(long)]
+    embed_metadata: bool,
+
+    /// Download at most this video height, e.g. 1080
+    #[arg(long, value_name = "N", conflicts_with = "format")]
+    max_height: Option<u32>,
+
+    /// Raw yt-dlp format selector, overriding --max-height
+    #[arg(long, value_name = "STRING")]
+    format: Option<String>,
+
+    /// Format of the audio-only variant
+    #[arg(long, value_enum, value_name = "FORMAT", default_value_t = AudioFormat::Mp3)]
+    audio_format: AudioFormat,
+
+    /// Embed the video thumbnail as cover art in the audio-only variant
+    #[arg(long, requires = "formats")]
+    embed_thumbnail: bool,
+
+    /// Write a CUE sheet (playlist.cue) of the audio-only variant
+    #[arg(long, requires = "formats")]
+    cue_sheet: bool,
+
+    /// Also join the audio-only clips into a single full_audio file, which
+    /// --cue-sheet then indexes into instead of the separate clips
+    #[arg(long, requires = "formats")]
+    concat_audio: bool,
+
+    /// Export each audio stream of every chapter (e.g. separate vocal and
+    /// instrumental tracks) as its own --audio-format file in
+    /// `stems/<chapter>/`, named after the stream's language when tagged
+    #[arg(long)]
+    stems: bool,
+
+    /// Sample rate of the audio-only variant (default: --sample-rate, or the
+    /// same as the source)
+    #[arg(long, value_name = "HZ")]
+    audio_samplerate: Option<u32>,
+
+    /// Container of the clips and video format variants. WebM clips are
+    /// always re-encoded to VP9 and Opus
+    #[arg(long, value_enum, value_name = "FORMAT", default_value_t = Container::Mp4)]
+    container: Container,
+
+    /// Write clips and format variants as WebM for web delivery: VP9 in
+    /// constrained quality mode and 128k Opus audio, unless --audio-bitrate
+    /// is given
+    #[arg(long, conflicts_with = "container")]
+    webm: bool,
+
+    /// Path of the ffmpeg executable (default: $YT_CLIPPER_FFMPEG, then ffmpeg in PATH)
+    #[arg(long, value_name = "PATH")]
+    ffmpeg_path: Option<PathBuf>,
+
+    /// Path of the ffprobe executable used by --info (default: $YT_CLIPPER_FFPROBE, then ffprobe in PATH)
+    #[arg(long, value_name = "PATH")]
+    ffprobe_path: Option<PathBuf>,
+
+    /// Path of the yt-dlp executable (default: $YT_CLIPPER_YTDLP, then yt-dlp in PATH)
+    #[arg(long, value_name = "PATH")]
+    ytdlp_path: Option<PathBuf>,
+
+    /// Extra arguments added to every ffmpeg command that writes a clip or
+    /// format variant, e.g. "-vf 'scale=1280:-2, eq=contrast=1.1'". Quote
+    /// arguments containing spaces as you would in a shell
+    #[arg(long, value_name = "ARGS", allow_hyphen_values = true, value_parser = executables::parse_extra_args)]
+    ffmpeg_extra_args: Option<String>,
+
+    /// Netscape-format cookie file used to access members-only or
+    /// age-restricted videos
+    #[arg(long, value_name = "FILE", conflicts_with = "cookies_from_browser")]
+    cookies: Option<PathBuf>,
+
+    /// Read cookies from this browser's profile
+    #[arg(long, value_enum, value_name = "BROWSER")]
+    cookies_from_browser: Option<Browser>,
+
+    /// Proxy used for all network access, e.g. "socks5://127.0.0.1:1080"
+    /// (default: $HTTPS_PROXY, then $ALL_PROXY)
+    #[arg(long, value_name = "URL")]
+    proxy: Option<String>,
+
+    /// Ask yt-dlp to bypass geographic restrictions
+    #[arg(long)]
+    geo_bypass: bool,
+
+    /// Give up on unresponsive connections after this many seconds
+    #[arg(long, value_name = "SECONDS")]
+    socket_timeout: Option<u64>,
+
+    /// Connect from this local IP address, to pick a network interface
+    #[arg(long, value_name = "IP")]
+    source_address: Option<IpAddr>,
+
+    /// Retry each fragment of a fragmented download this many times
+    #[arg(long, value_name = "N", default_value_t = executables::DEFAULT_FRAGMENT_RETRIES)]
+    fragment_retries: u32,
+
+    /// Extra arguments added to every yt-dlp command, e.g. "--cookies
+    /// 'My Cookies.txt'". Quote arguments containing spaces as you would in
+    /// a shell
+    #[arg(long, value_name = "ARGS", allow_hyphen_values = true, value_parser = executables::parse_extra_args)]
+    ytdlp_extra_args: Option<String>,
+
+    /// Use a hardware video encoder when re-encoding
+    #[arg(long, value_enum, value_name = "BACKEND")]
+    hwaccel: Option<HwAccel>,
+
+    /// Run this shell command as soon as each clip is written. {clip_path},
+    /// {chapter_title}, {chapter_index}, {video_title}, {start_time} and
+    /// {end_time} expand like the shell variables $YT_CLIPPER_CLIP_PATH etc.
+    /// the hook gets, so quote them as you would a variable
+    #[arg(long, value_name = "COMMAND")]
+    on_chapter_complete: Option<String>,
+
+    /// Run this shell command once each video is done. {output_dir} and
+    /// {clip_count} expand like the shell variables $YT_CLIPPER_OUTPUT_DIR
+    /// and $YT_CLIPPER_CLIP_COUNT
+    #[arg(long, value_name = "COMMAND")]
+    on_complete: Option<String>,
+
+    /// Keep going when a chapter fails to split, finishing the others and
+    /// listing the failures at the end (the exit code is still non-zero).
+    /// Every chapter's outcome is written to processing_report.json
+    #[arg(long)]
+    continue_on_error: bool,
+
+    /// Record processed videos and their clips in this SQLite database
+    #[arg(long, value_name = "FILE", global = true)]
+    db: Option<PathBuf>,
+
+    /// Record processed video IDs in FILE and skip videos already listed there
+    #[arg(long, value_name = "FILE")]
+    archive: Option<PathBuf>,
+
+    /// Write every video's chapters to FILE as CSV, for analysis in a
+    /// spreadsheet (also with --dry-run)
+    #[arg(long, value_name = "FILE", conflicts_with = "export_tsv")]
+    export_csv: Option<PathBuf>,
+
+    /// Like --export-csv, but tab-separated
+    #[arg(long, value_name = "FILE")]
+    export_tsv: Option<PathBuf>,
+
+    /// Download even if the output directory seems to lack the disk space
+    #[arg(long)]
+    skip_space_check: bool,
+
+    /// Only extract chapters without a clip from an earlier run, e.g. after
+    /// the creator added chapters to the video
+    #[arg(long)]
+    incremental: bool,
+
+    /// Keep running, checking the video for new chapters every
+    /// --watch-interval minutes and extracting only the new ones (implies
+    /// --incremental)
+    #[arg(long, conflicts_with_all = ["playlist", "input_file", "archive", "dry_run"])]
+    watch: bool,
+
+    /// Minutes between --watch checks
+    #[arg(long, value_name = "MINUTES", default_value_t = 60, value_parser = clap::value_parser!(u64).range(1..))]
+    watch_interval: u64,
+
+    /// Continue an interrupted run: resume the download and keep chapter
+    /// clips that were already written
+    #[arg(long)]
+    resume: bool,
+
+    /// Process videos even if they are already listed in the archive, and
+    /// redo everything even with --resume
+    #[arg(long)]
+    force: bool,
+
+    /// Read default settings from this file instead of the user config file
+    #[arg(long, value_name = "FILE")]
+    config: Option<PathBuf>,
+
+    /// Upload the output directory to this S3 bucket when done (AWS credentials from the usual chain)
+    #[arg(long, value_name = "BUCKET")]
+    upload_s3: Option<String>,
+
+    /// Key prefix for --upload-s3
+    #[arg(long, value_name = "PREFIX", requires = "upload_s3")]
+    s3_prefix: Option<String>,
+
+    /// Endpoint URL of an S3-compatible service, e.g. `https://s3.us-west-004.backblazeb2.com`
+    #[arg(long, value_name = "URL", requires = "upload_s3")]
+    s3_endpoint: Option<String>,
+
+    /// Only upload these output directories, e.g. "clips,audio_only"
+    #[arg(
+        long,
+        value_name = "FORMATS",
+        value_delimiter = ',',
+        requires = "upload_s3"
+    )]
+    upload_only_formats: Vec<String>,
+
+    /// Also convert every clip into an HLS stream under hls/, with an
+    /// index.m3u8 master playlist (re-encodes)
+    #[arg(long)]
+    hls: bool,
+
+    /// Target length of each HLS segment
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        default_value_t = 6,
+        value_parser = clap::value_parser!(u32).range(1..),
+        requires = "hls"
+    )]
+    hls_segment_duration: u32,
+
+    /// Absolute URL the hls/ directory will be served from, used in the playlists
+    #[arg(long, value_name = "URL", requires = "hls")]
+    hls_base_url: Option<String>,
+
+    /// Write SHA-256 checksums of all produced files to checksums.sha256
+    #[arg(long)]
+    checksums: bool,
+
+    /// Verify the files listed in a checksums.sha256 file (or the one in a
+    /// directory) and exit
+    #[arg(long, value_name = "PATH")]
+    verify_checksums: Option<PathBuf>,
+
+    /// Print the effective configuration as TOML and exit
+    #[arg(long)]
+    print_config: bool,
+
+    /// Apply the flags of the [profiles.NAME] table in the config file
+    #[arg(long, value_name = "NAME")]
+    profile: Option<String>,
+
+    /// List the profiles defined in the config file and exit
+    #[arg(long)]
+    print_profiles: bool,
+
+    /// Print the versions of yt-clipper, yt-dlp and ffmpeg and exit
+    #[arg(long)]
+    version: bool,
+
+    #[command(subcommand)]
+    #[serde(skip)]
+    command: Option<Commands>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Commands {
+    /// Run an SQL query against the --db database and print the results as a table
+    Query {
+        /// The SQL statement to run, e.g. "SELECT title, processed_at FROM videos"
+        sql: String,
+    },
+    /// Print a completion script for SHELL
+    #[command(after_help = COMPLETIONS_HELP)]
+    Completions {
+        #[arg(value_enum)]
+        shell: Shell,
+    },
+}
+
+const COMPLETIONS_HELP: &str = "\
+Shell completions:
+  bash:       yt-clipper completions bash > /etc/bash_completion.d/yt-clipper
+  zsh:        yt-clipper completions zsh > \"${fpath[1]}/_yt-clipper\"
+  fish:       yt-clipper completions fish > ~/.config/fish/completions/yt-clipper.fish
+  powershell: yt-clipper completions powershell >> $PROFILE";
+
+/// The parts of yt-dlp's `--dump-json` output yt-clipper uses.
+#[derive(Debug, Deserialize)]
+pub struct VideoInfo {
+    pub id: String,
+    /// yt-dlp's name for the site, e.g. `Youtube`.
+    pub extractor_key: Option<String>,
+    pub webpage_url: Option<String>,
+    pub title: String,
+    /// Length in seconds.
+    pub duration: Option<f64>,
+    pub uploader: Option<String>,
+    /// Upload date as `YYYYMMDD`.
+    pub upload_date: Option<String>,
+    pub description: Option<String>,
+    /// URL of the video's thumbnail image.
+    pub thumbnail: Option<String>,
+    /// Size in bytes of the selected format, when known exactly.
+    pub filesize: Option<f64>,
+    /// yt-dlp's estimate of the size in bytes.
+    pub filesize_approx: Option<f64>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    /// Date the video was last modified as `YYYYMMDD`, when the site reports it.
+    pub modified_date: Option<String>,
+    /// Who may watch the video, e.g. `public`, `members_only` or `private`.
+    pub availability: Option<String>,
+    pub chapters: Option<Vec<Chapter>>,
+}
+
+/// A chapter of a video, with times in seconds from the start of the video.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Chapter {
+    pub title: String,
+    pub start_time: f64,
+    pub end_time: f64,
+    /// 1-based position of the chapter in the video, kept stable across selection.
+    #[serde(skip)]
+    pub number: usize,
+}
+
+/// Settings for the library functions. [`ClipperConfig::new`] starts from
+/// the command line defaults; change the fields to taste.
+#[derive(Debug, Clone)]
+pub struct ClipperConfig {
+    /// yt-dlp, ffmpeg and ffprobe, and how they are run.
+    pub executables: Executables,
+    /// yt-dlp format selector used for downloads.
+    pub format: String,
+    pub container: Container,
+    /// Codec settings for format variants, and for clips with `reencode`.
+    pub encode: EncodeSettings,
+    /// Re-encode clips instead of stream copying them.
+    pub reencode: bool,
+    /// Clip file name template, e.g. `{num}_{title}`.
+    pub template: String,
+    pub filenames: FilenameOptions,
+    /// Number of chapters to split concurrently (0 = one per CPU core).
+    pub jobs: usize,
+    /// Cut every chapter even when some fail, and report the failures at the end.
+    pub continue_on_error: bool,
+    pub formats: FormatOptions,
+}
+
+impl ClipperConfig {
+    /// The command line defaults, with yt-dlp, ffmpeg and ffprobe looked up
+    /// the same way as the command line does. Fails when a proxy environment
+    /// variable holds an invalid URL.
+    pub fn new() -> Result<Self, Error> {
+        let proxy = proxy::resolve(None).map_err(Error::Config)?;
+        Ok(ClipperConfig {
+            executables: Executables::new(NetworkOptions {
+                proxy,
+                ..NetworkOptions::default()
+            }),
+            format: DEFAULT_FORMAT.to_string(),
+            container: Container::default(),
+            encode: EncodeSettings::default(),
+            reencode: false,
+            template: DEFAULT_TEMPLATE.to_string(),
+            filenames: FilenameOptions::default(),
+            jobs: 1,
+            continue_on_error: false,
+            formats: FormatOptions::default(),
+        })
+    }
+
+    /// The settings `args` describe.
+    pub fn from_args(args: &Args) -> Result<Self, Error> {
+        let proxy = proxy::resolve(args.proxy.as_deref()).map_err(Error::Config)?;
+        Ok(ClipperConfig {
+            executables: Executables::from_args(
+                args,
+                NetworkOptions::from_args(args, proxy.as_ref()),
+            ),
+            format: format_selector(args),
+            container: args.container,
+            encode: EncodeSettings::new(args),
+            reencode: EncodeSettings::from_args(args).is_some(),
+            template: clip_template(args),
+            filenames: FilenameOptions::from_args(args),
+            jobs: args.jobs,
+            continue_on_error: args.continue_on_error,
+            formats: FormatOptions::from_args(args, None),
+        })
+    }
+
+    /// Clip file names (without extension) for `chapters`.
+    fn clip_names(&self, chapters: &[Chapter], video_title: &str) -> Result<Vec<String>, Error> {
+        let template = Template::parse(&self.template).map_err(Error::Config)?;
+        let num_width = chapters.len().to_string().len().max(2);
+        Ok(clip_names(
+            &template,
+            chapters,
+            video_title,
+            num_width,
+            &self.filenames,
+        ))
+    }
+}
+
+/// Fetches the information of the video at `url`. Its chapters are numbered
+/// in order.
+pub fn get_video_info(config: &ClipperConfig, url: &str) -> Result<VideoInfo, Error> {
+    let mut video_info = dump_video_info(&config.executables, url).map_err(Error::VideoInfo)?;
+    for (i, chapter) in video_info.chapters.iter_mut().flatten().enumerate() {
+        chapter.number = i + 1;
+    }
+    Ok(video_info)
+}
+
+/// Downloads the video at `url` into `output_dir` and returns the path of
+/// the downloaded file.
+pub fn download_video(
+    config: &ClipperConfig,
+    url: &str,
+    output_dir: &Path,
+) -> Result<PathBuf, Error> {
+    fs::create_dir_all(output_dir)
+        .context("Failed to create output directory")
+        .map_err(Error::Download)?;
+    let options = DownloadOptions {
+        stem: FULL_VIDEO_STEM,
+        format: &config.format,
+        container: config.container,
+        section: None,
+        sub_lang: None,
+        resume: false,
+        limit_rate: None,
+        audio_multistreams: false,
+    };
+    download_full_video(&config.executables, url, output_dir, &options).map_err(Error::Download)
+}
+
+/// Cuts one clip per chapter out of `video_path` into `output_dir`, named by
+/// the config's template, and returns the clip paths in chapter order.
+/// `video_title` fills the template's `{video_title}`. Up to `jobs` chapters
+/// are cut at once.
+pub async fn split_video_into_chapters(
+    config: &ClipperConfig,
+    video_path: &Path,
+    video_title: &str,
+    chapters: &[Chapter],
+    output_dir: &Path,
+) -> Result<Vec<PathBuf>, Error> {
+    let clip_names = config.clip_names(chapters, video_title)?;
+    fs::create_dir_all(output_dir)
+        .context("Failed to create clips directory")
+        .map_err(Error::Split)?;
+
+    let options = SplitOptions {
+        encode: config.reencode.then_some(&config.encode),
+        embed_subtitles: None,
+        skip_segments: &[],
+        metadata: None,
+        container: config.container,
+        executables: &config.executables,
+        resume: false,
+        sleep: None,
+        jobs: config.jobs,
+        fade: None,
+        fade_audio: false,
+        speed: &SpeedSettings {
+            default: 1.0,
+            overrides: BTreeMap::new(),
+        },
+        continue_on_error: config.continue_on_error,
+        resample_audio: None,
+        audio_tracks: None,
+        two_pass: false,
+        record: None,
+        chapter_hook: None,
+    };
+    let results = split_chapters(video_path, chapters, &clip_names, output_dir, &options)
+        .await
+        .map_err(Error::Split)?;
+    if results.iter().any(ChapterResult::is_failure) {
+        let failures = results.into_iter().filter(|r| r.is_failure()).collect();
+        return Err(Error::ChapterFailures(failures));
+    }
+
+    Ok(clip_names
+        .iter()
+        .map(|name| output_dir.join(format!("{}.{}", name, config.container.extension())))
+        .collect())
+}
+
+/// Creates every format variant (vertical, square, GIF, audio-only, ...) of
+/// each chapter of `video_path` in `formats_dir`, named like the clips.
+pub fn generate_format_variants(
+    config: &ClipperConfig,
+    video_path: &Path,
+    video_title: &str,
+    chapters: &[Chapter],
+    formats_dir: &Path,
+) -> Result<(), Error> {
+    let clip_names = config.clip_names(chapters, video_title)?;
+    fs::create_dir_all(formats_dir)
+        .context("Failed to create formats directory")
+        .map_err(Error::Formats)?;
+    formats::generate_format_variants(
+        &config.executables,
+        video_path,
+        chapters,
+        &clip_names,
+        formats_dir,
+        &config.encode,
+        &config.formats,
+    )
+    .map_err(Error::Formats)
+}
+
+/// The process exit code for an error returned by [`run`]: 3 for videos
+/// that are private, deleted or need an account, 1 otherwise.
+pub fn exit_code(error: &anyhow::Error) -> u8 {
+    if error.downcast_ref::<Unavailable>().is_some() {
+        availability::EXIT_UNAVAILABLE
+    } else {
+        1
+    }
+}
+
+/// Runs the command line tool. `matches` are the parsed command line `args`
+/// came from, used to tell which options were given explicitly and so take
+/// precedence over the config file.
+pub fn run(args: Args, matches: &ArgMatches) -> Result<()> {
+    let result = run_cli(args, matches);
+    if let Err(e) = &result {
+        log::write(format!("Error: {:?}\n", e).as_bytes());
+        progress::emit(Event::Error {
+            message: format!("{:#}", e),
+        });
+    }
+    result
+}
+
+fn run_cli(args: Args, matches: &ArgMatches) -> Result<()> {
+    if let Some(Commands::Completions { shell }) = args.command {
+        clap_complete::generate(
+            shell,
+            &mut Args::command(),
+            "yt-clipper",
+            &mut std::io::stdout(),
+        );
+        return Ok(());
+    }
+
+    let config = Config::load(args.config.as_deref())?;
+
+    if args.print_profiles {
+        if config.profiles.is_empty() {
+            println!("No profiles defined");
+        } else {
+            print!("{}", config.profiles_toml()?);
+        }
+        return Ok(());
+    }
+
+    let config = config.with_profile(args.profile.as_deref())?;
+    let mut args = config.apply(args, matches)?;
+
+    if args.webm {
+        args.container = Container::Webm;
+        if !config.is_set("audio_bitrate", matches) {
+            args.audio_bitrate = encode::WEBM_AUDIO_BITRATE.to_string();
+        }
+    }
+    // Not every player handles AV1 in MP4, so prefer MKV unless a container
+    // was chosen explicitly. VP9 belongs in WebM.
+    if args.container == Container::Mp4 && !config.is_set("container", matches) {
+        match args.codec {
+            Some(VideoCodec::Av1) => args.container = Container::Mkv,
+            Some(VideoCodec::Vp9) => args.container = Container::Webm,
+            _ => {}
+        }
+    }
+    if args.two_pass && EncodeSettings::new(&args).video_codec != VideoCodec::Vp9.encoder() {
+        anyhow::bail!("--two-pass only applies to VP9: use it with --webm or --codec vp9");
+    }
+    if args.watch {
+        args.incremental = true;
+    }
+    if args.audio_tracks.is_some() && args.container == Container::Mp4 {
+        if config.is_set("container", matches) {
+            estatus!("Warning: MP4 doesn't hold several audio tracks reliably, consider --container mkv for --audio-tracks");
+        } else {
+            args.container = Container::Mkv;
+        }
+    }
+
+    if args.print_config {
+        print!("{}", config.to_effective_toml(&args)?);
+        return Ok(());
+    }
+
+    if args.version {
+        let executables = Executables::from_args(&args, NetworkOptions::from_args(&args, None));
+        println!("yt-clipper {}", env!("CARGO_PKG_VERSION"));
+        for (name, command) in [
+            ("yt-dlp", executables.ytdlp()),
+            ("ffmpeg", executables.ffmpeg()),
+        ] {
+            let version = versions::detect(command)
+                .map(|version| version.unwrap_or_else(|| "(unknown version)".to_string()))
+                .unwrap_or_else(|_| "not found".to_string());
+            println!("{} {}", name, version);
+        }
+        return Ok(());
+    }
+
+    if let Some(path) = &args.verify_checksums {
+        let failures = checksums::verify_checksums(path)?;
+        if !failures.is_empty() {
+            anyhow::bail!("{} files failed verification", failures.len());
+        }
+        status!("All files verified");
+        return Ok(());
+    }
+
+    if let Some(Commands::Query { sql }) = &args.command {
+        let path = args
+            .db
+            .as_deref()
+            .context("The query command needs a database: pass --db FILE")?;
+        return Database::open(path)?.print_query(sql);
+    }
+
+    if args.json_progress {
+        progress::enable_json();
+    }
+    QUIET.store(
+        args.json || args.list_chapters_json || args.json_progress,
+        Ordering::Relaxed,
+    );
+
+    if let Some(log_file) = &args.log_file {
+        log::init(log_file)?;
+    }
+
+    #[cfg(windows)]
+    filename::warn_if_long_paths_disabled();
+
+    let template = Template::parse(&clip_template(&args))?;
+    let dir_template = DirTemplate::parse(&args.dir_template)?;
+    let selection = args
+        .chapters
+        .as_deref()
+        .map(ChapterSelection::parse)
+        .transpose()?;
+    let chapter_filter = args
+        .chapter_filter
+        .as_deref()
+        .map(Regex::new)
+        .transpose()
+        .context("Invalid --chapter-filter regex")?;
+    let skips = ChapterSkips {
+        numbers: args.skip_chapters.iter().map(|&n| n as usize).collect(),
+        pattern: args
+            .skip_chapter_pattern
+            .as_deref()
+            .map(Regex::new)
+            .transpose()
+            .context("Invalid --skip-chapter-pattern regex")?,
+    };
+    let merge_pattern = args
+        .merge_pattern
+        .as_deref()
+        .map(Regex::new)
+        .transpose()
+        .context("Invalid --merge-pattern regex")?;
+
+    if args.deinterlace && !args.reencode {
+        estatus!("Warning: --deinterlace re-encodes the clips, as if --reencode were given");
+    }
+
+    if let Some(fade) = args.fade_duration {
+        if fade <= 0.0 {
+            anyhow::bail!("--fade-duration must be greater than 0");
+        }
+        if EncodeSettings::from_args(&args).is_none() {
+            anyhow::bail!(
+                "--fade-duration requires --reencode: fades can't be applied while stream copying (-c copy)"
+            );
+        }
+    }
+
+    for (flag, path) in [
+        ("--prepend-clip", &args.prepend_clip),
+        ("--append-clip", &args.append_clip),
+        ("--watermark-image", &args.watermark_image),
+    ] {
+        if let Some(path) = path.as_deref().filter(|path| !path.is_file()) {
+            anyhow::bail!("{} file {} does not exist", flag, path.display());
+        }
+    }
+
+    let manual_chapters = if !args.clip.is_empty() {
+        Some(chapters::clip_chapters(
+            &args.clip,
+            &clip_titles(&args, matches),
+        )?)
+    } else {
+        args.chapters_file
+            .as_deref()
+            .map(chapters::load_chapters_file)
+            .transpose()?
+    };
+
+    let urls = collect_urls(&args)?;
+    let archive = args
+        .archive
+        .as_deref()
+        .map(Archive::load)
+        .transpose()?
+        .map(Mutex::new);
+    let database = args
+        .db
+        .as_deref()
+        .map(Database::open)
+        .transpose()?
+        .map(Mutex::new);
+    let export = match (&args.export_csv, &args.export_tsv) {
+        (Some(path), _) => Some(ChapterExport::create(path, b',')?),
+        (_, Some(path)) => Some(ChapterExport::create(path, b'\t')?),
+        _ => None,
+    }
+    .map(Mutex::new);
+    let proxy = proxy::resolve(args.proxy.as_deref())?;
+    let executables =
+        Executables::from_args(&args, NetworkOptions::from_args(&args, proxy.as_ref()));
+    let retry = RetryPolicy {
+        retries: args.retries,
+        base_delay: Duration::from_millis(args.retry_delay_ms),
+    };
+    let session = Session {
+        args,
+        executables,
+        retry,
+        template,
+        dir_template,
+        selection,
+        chapter_filter,
+        skips,
+        merge_pattern,
+        manual_chapters,
+        archive,
+        database,
+        export,
+    };
+
+    status!("yt-clipper\n");
+
+    versions::check(session.executables.ytdlp(), versions::MIN_YTDLP_VERSION)?;
+    versions::check(session.executables.ffmpeg(), versions::MIN_FFMPEG_VERSION)?;
+
+    if let Some(hwaccel) = session.args.hwaccel {
+        if session.args.container == Container::Webm {
+            estatus!("Warning: --hwaccel is ignored for WebM output");
+        } else {
+            check_hwaccel(&session.executables, hwaccel)?;
+        }
+    }
+
+    if let Some(codec) = session.args.codec {
+        if session.args.container == Container::Webm && codec != VideoCodec::Vp9 {
+            estatus!("Warning: --codec is ignored for WebM output");
+        } else if session.args.hwaccel.is_some() && codec != VideoCodec::H264 {
+            anyhow::bail!(
+                "--hwaccel encoders only produce H.264 and can't be combined with --codec"
+            );
+        } else {
+            check_codec(&session.executables, codec)?;
+        }
+    }
+
+    if session.args.audio_format.is_lossless()
+        && session.args.audio_bitrate != audio::DEFAULT_BITRATE
+    {
+        estatus!(
+            "Warning: --audio-bitrate is ignored for {} audio-only exports",
+            session.args.audio_format.extension().to_uppercase()
+        );
+    }
+
+    if session.args.accurate_seek {
+        estatus!(
+            "Warning: --accurate-seek re-encodes every clip, which can take significantly longer"
+        );
+    }
+
+    let conflicts = session.executables.conflicting_ffmpeg_args();
+    if !conflicts.is_empty() {
+        estatus!(
+            "Warning: --ffmpeg-extra-args overrides options yt-clipper sets itself: {}",
+            conflicts.join(", ")
+        );
+    }
+
+    // Downloads run one at a time; the runtime lets the chapters of each
+    // video be cut concurrently. It has to be multi-threaded: the stages that
+    // still run blocking commands use `task::block_in_place`.
+    let runtime = tokio::runtime::Runtime::new().context("Failed to start async runtime")?;
+    if session.args.input_file.is_none() {
+        return runtime.block_on(process_url(&session, &urls[0]));
+    }
+
+    runtime.block_on(process_batch(&session, &urls))
+}
+
+/// Settings parsed once at startup and shared by every processed video.
+struct Session {
+    args: Args,
+    executables: Executables,
+    /// Applied to fetching video info and downloading.
+    retry: RetryPolicy,
+    template: Template,
+    dir_template: DirTemplate,
+    selection: Option<ChapterSelection>,
+    chapter_filter: Option<Regex>,
+    skips: ChapterSkips,
+    merge_pattern: Option<Regex>,
+    /// Chapters from `--chapters-file` or `--clip`, used instead of the video's own.
+    manual_chapters: Option<Vec<Chapter>>,
+    archive: Option<Mutex<Archive>>,
+    /// `--db` database, updated as each clip is finished.
+    database: Option<Mutex<Database>>,
+    /// `--export-csv`/`--export-tsv` output, appended to by every video.
+    export: Option<Mutex<ChapterExport>>,
+}
+
+/// The `--clip-title` of each `--clip`. A title belongs to the `--clip` given
+/// before it on the command line; titles from a config file are matched up
+/// in order.
+fn clip_titles(args: &Args, matches: &ArgMatches) -> Vec<Option<String>> {
+    let (Some(clip_indices), Some(title_indices)) =
+        (matches.indices_of("clip"), matches.indices_of("clip_title"))
+    else {
+        return args.clip_title.iter().cloned().map(Some).collect();
+    };
+
+    let clip_indices: Vec<usize> = clip_indices.collect();
+    let mut titles = vec![None; clip_indices.len()];
+    for (title_index, title) in title_indices.zip(&args.clip_title) {
+        if let Some(clip) = clip_indices.iter().rposition(|&i| i < title_index) {
+            titles[clip] = Some(title.clone());
+        }
+    }
+    titles
+}
+
+fn collect_urls(args: &Args) -> Result<Vec<String>> {
+    let mut urls: Vec<String> = args.url.iter().cloned().collect();
+
+    if let Some(input_file) = &args.input_file {
+        let contents = if input_file.as_os_str() == "-" {
+            std::io::read_to_string(std::io::stdin()).context("Failed to read URLs from stdin")?
+        } else {
+            fs::read_to_string(input_file)
+                .with_context(|| format!("Failed to read {}", input_file.display()))?
+        };
+
+        urls.extend(
+            contents
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                .map(String::from),
+        );
+    }
+
+    if urls.is_empty() {
+        anyhow::bail!("No URLs to process");
+    }
+
+    Ok(urls)
+}
+
+async fn process_batch(session: &Session, urls: &[String]) -> Result<()> {
+    let mut failed = Vec::new();
+
+    for (i, url) in urls.iter().enumerate() {
+        status!("[{}/{}] {}\n", i + 1, urls.len(), url);
+
+        if let Err(e) = process_url(session, url).await {
+            estatus!("\nFailed to process {}: {:#}\n", url, e);
+            failed.push((url.as_str(), e));
+        }
+    }
+
+    status!(
+        "\nProcessed {} URLs: {} succeeded, {} failed",
+        urls.len(),
+        urls.len() - failed.len(),
+        failed.len()
+    );
+    for (url, e) in &failed {
+        status!("  - {}: {}", url, e);
+    }
+
+    if let Some(failed_output) = &session.args.failed_output {
+        let contents: String = failed.iter().map(|(url, _)| format!("{}\n", url)).collect();
+        fs::write(failed_output, contents)
+            .with_context(|| format!("Failed to write {}", failed_output.display()))?;
+        if !failed.is_empty() {
+            status!("Failed URLs written to {}", failed_output.display());
+        }
+    }
+
+    if failed.len() == urls.len() {
+        anyhow::bail!("All {} URLs failed to process", urls.len());
+    }
+
+    Ok(())
+}
+
+async fn process_url(session: &Session, url: &str) -> Result<()> {
+    let cleaned_url = clean_url(url);
+
+    if session.args.playlist || is_playlist_url(&cleaned_url) {
+        return process_playlist(session, &cleaned_url).await;
+    }
+
+    if session.args.watch {
+        return watch_video(session, &cleaned_url).await;
+    }
+
+    status!("Fetching video information...");
+    let video_info = session
+        .retry
+        .run("Fetching video info", || {
+            dump_video_info(&session.executables, &cleaned_url)
+        })
+        .await?;
+
+    process_video(session, &cleaned_url, video_info).await
+}
+
+/// `--watch`: processes the video, then checks it again every
+/// `--watch-interval` minutes and extracts the chapters added since. Runs
+/// until interrupted; failed checks are reported and retried next time.
+async fn watch_video(session: &Session, url: &str) -> Result<()> {
+    let minutes = session.args.watch_interval;
+    loop {
+        if let Err(e) = check_for_new_chapters(session, url).await {
+            estatus!("Warning: {:#}", e);
+        }
+        status!("\nChecking again in {} minutes (Ctrl-C to stop)\n", minutes);
+        tokio::time::sleep(Duration::from_secs(minutes * 60)).await;
+    }
+}
+
+/// Processes the video if its chapters changed since the state saved in its
+/// output directory, then saves the new state.
+async fn check_for_new_chapters(session: &Session, url: &str) -> Result<()> {
+    status!("Fetching video information...");
+    let mut video_info = session
+        .retry
+        .run("Fetching video info", || {
+            dump_video_info(&session.executables, url)
+        })
+        .await?;
+    if let Some(title) = &session.args.title {
+        video_info.title = title.clone();
+    }
+
+    let paths = resolve_output_paths(&session.args, &session.dir_template, &video_info);
+    let state = WatchState::of(&video_info);
+    match watch::load_state(&paths.root)? {
+        Some(previous) if previous == state => {
+            status!("No new chapters in \"{}\"", video_info.title);
+            return Ok(());
+        }
+        Some(previous) if state.chapter_count > previous.chapter_count => {
+            let message = format!(
+                "{} new chapters",
+                state.chapter_count - previous.chapter_count
+            );
+            status!("{}", message);
+            watch::notify(&video_info.title, &message);
+        }
+        Some(_) => status!("The video was modified, checking for new chapters"),
+        None => {}
+    }
+
+    process_video(session, url, video_info).await?;
+
+    fs::create_dir_all(&paths.root).context("Failed to create output directory")?;
+    watch::save_state(&paths.root, &state)
+}
+
+/// Processes every video of a playlist in turn. Failed videos are reported but
+/// don't stop the rest of the playlist.
+async fn process_playlist(session: &Session, url: &str) -> Result<()> {
+    let args = &session.args;
+
+    status!("Fetching playlist information...");
+    let entries = get_playlist_entries(
+        &session.executables,
+        url,
+        args.playlist_start,
+        args.playlist_end,
+    )?;
+
+    if entries.is_empty() {
+        anyhow::bail!("No videos found in this playlist");
+    }
+
+    status!("Found {} videos in playlist\n", entries.len());
+
+    let total = entries.len();
+    let mut failed = Vec::new();
+
+    for (i, video_info) in entries.into_iter().enumerate() {
+        status!("[{}/{}] {}\n", i + 1, total, video_info.title);
+
+        let title = video_info.title.clone();
+        let video_url = video_info
+            .webpage_url
+            .clone()
+            .unwrap_or_else(|| video_info.id.clone());
+
+        if let Err(e) = process_video(session, &video_url, video_info).await {
+            estatus!("\nFailed to process \"{}\": {:#}\n", title, e);
+            failed.push(title);
+        }
+    }
+
+    status!(
+        "\nProcessed {} playlist videos: {} succeeded, {} failed",
+        total,
+        total - failed.len(),
+        failed.len()
+    );
+    for title in &failed {
+        status!("  - {}", title);
+    }
+
+    if failed.len() == total {
+        anyhow::bail!("All {} playlist videos failed to process", total);
+    }
+
+    Ok(())
+}
+
+async fn process_video(
+    session: &Session,
+    cleaned_url: &str,
+    mut video_info: VideoInfo,
+) -> Result<()> {
+    let args = &session.args;
+    let started = Instant::now();
+
+    if let Some(title) = &args.title {
+        video_info.title = title.clone();
+    }
+
+    status!("Video: {}", video_info.title);
+
+    availability::check(
+        &video_info,
+        args.cookies.is_some() || args.cookies_from_browser.is_some(),
+    )?;
+
+    if let Some(archive) = &session.archive {
+        if !args.force && archive.lock().unwrap().contains(&video_info.id) {
+            status!(
+                "Skipping: video {} is already in the archive",
+                video_info.id
+            );
+            return Ok(());
+        }
+    }
+
+    let paths = resolve_output_paths(args, &session.dir_template, &video_info);
+    let wants_subtitles = args.subtitles || args.embed_subs;
+    let sub_lang = wants_subtitles.then_some(args.sub_lang.as_str());
+
+    // With --auto-chapters, a video without chapters has to be downloaded
+    // before its chapters are known.
+    let mut downloaded = None;
+    let mut chapters = match &session.manual_chapters {
+        Some(chapters) => chapters.clone(),
+        None if args.parse_description => {
+            let duration = video_info
+                .duration
+                .context("Video duration is unknown, so description chapters can't be used")?;
+            chapters::parse_description(
+                video_info.description.as_deref().unwrap_or_default(),
+                duration,
+            )
+        }
+        None => match video_info.chapters.take() {
+            Some(chapters) if !chapters.is_empty() => chapters,
+            _ if args.auto_chapters => {
+                if args.dry_run || args.list_chapters || args.list_chapters_json {
+                    anyhow::bail!(
+                        "This video has no chapters, and --auto-chapters would have to download it to find scenes"
+                    );
+                }
+                let duration = video_info
+                    .duration
+                    .context("Video duration is unknown, so scene chapters can't be made")?;
+
+                fs::create_dir_all(&paths.clips).context("Failed to create clips directory")?;
+                status!("No chapters found, detecting scene changes instead\n");
+                status!("Output directory: {}\n", paths.root.display());
+                let video_path =
+                    fetch_video(session, cleaned_url, &video_info, &paths, None, sub_lang).await?;
+
+                let pb = progress::spinner();
+                pb.set_message("Detecting scene changes...");
+                pb.enable_steady_tick(Duration::from_millis(100));
+                let chapters = task::block_in_place(|| {
+                    scenes::auto_chapters(
+                        &session.executables,
+                        &video_path,
+                        duration,
+                        args.auto_chapter_min_duration,
+                    )
+                });
+                pb.finish_and_clear();
+
+                downloaded = Some(video_path);
+                chapters?
+            }
+            chapters => chapters.context("No chapters found in this video")?,
+        },
+    };
+
+    if chapters.is_empty() && args.parse_description {
+        anyhow::bail!("No chapter timestamps found in the video description");
+    }
+    if chapters.is_empty() {
+        anyhow::bail!("No chapters found in this video");
+    }
+
+    for (i, chapter) in chapters.iter_mut().enumerate() {
+        chapter.number = i + 1;
+    }
+
+    let warnings = chapters::validate_chapters(&chapters);
+    if args.strict_chapters && !warnings.is_empty() {
+        let warnings: Vec<String> = warnings.iter().map(|w| format!("  - {}", w)).collect();
+        anyhow::bail!(
+            "Malformed chapters (--strict-chapters):\n{}",
+            warnings.join("\n")
+        );
+    }
+    for warning in &warnings {
+        estatus!("Warning: {}", warning);
+    }
+
+    status!("Found {} chapters\n", chapters.len());
+
+    let gaps = if args.detect_gaps || args.extract_gaps {
+        chapters::find_gaps(&chapters, video_info.duration, args.min_gap_duration)
+    } else {
+        Vec::new()
+    };
+    if !gaps.is_empty() {
+        status!("{} parts of the video aren't in any chapter:", gaps.len());
+        for gap in &gaps {
+            status!(
+                "  {} - {} ({:.1}s) {}",
+                plan::format_timestamp(gap.start),
+                plan::format_timestamp(gap.end),
+                gap.duration(),
+                gap.describe()
+            );
+        }
+        status!("");
+    } else if args.detect_gaps || args.extract_gaps {
+        status!(
+            "No gaps of {}s or longer between chapters\n",
+            args.min_gap_duration
+        );
+    }
+
+    if let Some(export) = &session.export {
+        export
+            .lock()
+            .unwrap()
+            .write_video(&video_info.id, &video_info.title, &chapters)?;
+    }
+
+    let limits = DurationLimits {
+        min: args.min_duration,
+        max: args.max_duration,
+    };
+
+    if args.list_chapters || args.list_chapters_json {
+        return list_chapters(session, &chapters, &limits);
+    }
+
+    let total_chapters = chapters.len();
+    let (skipped, chapters): (Vec<Chapter>, Vec<Chapter>) = chapters
+        .into_iter()
+        .partition(|chapter| session.skips.matches(chapter));
+    for chapter in &skipped {
+        status!(
+            "Skipping chapter {} \"{}\" (--skip-chapter)",
+            chapter.number,
+            chapter.title
+        );
+    }
+    if chapters.is_empty() {
+        anyhow::bail!("--skip-chapter/--skip-chapter-pattern skip every chapter");
+    }
+
+    let all_chapters = args.interactive.then(|| chapters.clone());
+    let mut chapters = chapters::select_chapters(
+        chapters,
+        session.selection.as_ref(),
+        session.chapter_filter.as_ref(),
+    );
+
+    if chapters.is_empty() {
+        anyhow::bail!("No chapters match the given selection");
+    }
+
+    chapters.retain(|chapter| match limits.violation(chapter) {
+        Some(reason) => {
+            status!("Skipping chapter \"{}\" ({})", chapter.title, reason);
+            false
+        }
+        None => true,
+    });
+
+    if chapters.is_empty() {
+        anyhow::bail!("All chapters were filtered out by --min-duration/--max-duration");
+    }
+
+    if let Some(all_chapters) = &all_chapters {
+        chapters = interactive::pick_chapters(all_chapters, chapters)?;
+    }
+
+    if args.pre_pad > 0.0 || args.post_pad > 0.0 {
+        chapters =
+            chapters::pad_chapters(&chapters, args.pre_pad, args.post_pad, video_info.duration);
+    }
+
+    // Subtitles always cover the whole video and gaps can lie anywhere in
+    // it, so only download a section of the video when neither is needed.
+    let section = if chapters.len() < total_chapters {
+        status!(
+            "Selected {} of {} chapters\n",
+            chapters.len(),
+            total_chapters
+        );
+        (!wants_subtitles && !args.extract_gaps && downloaded.is_none())
+            .then(|| chapters::time_span(&chapters))
+            .flatten()
+    } else {
+        None
+    };
+
+    let mut skip_segments = Vec::new();
+    if args.sponsorblock {
+        match task::block_in_place(|| {
+            sponsorblock::fetch_segments(
+                &video_info.id,
+                &args.sponsorblock_categories,
+                session.executables.network.proxy.as_ref(),
+            )
+        }) {
+            Ok(segments) => skip_segments = segments,
+            Err(e) => estatus!("Warning: skipping SponsorBlock: {:#}", e),
+        }
+        status!("SponsorBlock: {} segments to skip\n", skip_segments.len());
+
+        chapters.retain(|chapter| {
+            let covered =
+                sponsorblock::keep_ranges(chapter.start_time, chapter.end_time, &skip_segments)
+                    .is_empty();
+            if covered {
+                status!(
+                    "Skipping chapter \"{}\" (entirely covered by SponsorBlock segments)",
+                    chapter.title
+                );
+            }
+            !covered
+        });
+
+        if chapters.is_empty() {
+            anyhow::bail!("All chapters are covered by SponsorBlock segments");
+        }
+    }
+
+    if args.renumber_skipped {
+        let skipped: Vec<usize> = skipped.iter().map(|chapter| chapter.number).collect();
+        chapters::renumber(&mut chapters, &skipped);
+    }
+
+    let num_width = args
+        .pad_width
+        .unwrap_or_else(|| total_chapters.to_string().len().max(2));
+    let mut clip_names = clip_names(
+        &session.template,
+        &chapters,
+        &video_info.title,
+        num_width,
+        &FilenameOptions::from_args(&session.args),
+    );
+
+    if args.incremental {
+        let comparison =
+            incremental::compare(&paths.clips, &clip_names, args.container.extension())?;
+
+        if !comparison.moved.is_empty() {
+            estatus!("Warning: these chapters exist under a different number, the video's chapters were probably reordered. They are skipped; rename or delete the old clips to resolve this:");
+            for (i, previous) in &comparison.moved {
+                estatus!("  - {} (existing: {})", clip_names[*i], previous);
+            }
+        }
+        if !comparison.existing.is_empty() {
+            status!(
+                "Incremental: {} of {} chapters already extracted",
+                comparison.existing.len(),
+                chapters.len()
+            );
+        }
+
+        let (kept_chapters, kept_names): (Vec<Chapter>, Vec<String>) = chapters
+            .into_iter()
+            .zip(clip_names)
+            .enumerate()
+            .filter(|(i, _)| !comparison.skips(*i))
+            .map(|(_, pair)| pair)
+            .unzip();
+        chapters = kept_chapters;
+        clip_names = kept_names;
+
+        if chapters.is_empty() {
+            status!("No new chapters to extract");
+            return Ok(());
+        }
+    }
+
+    if args.dry_run {
+        let plan = Plan {
+            video_id: video_info.id.clone(),
+            video_title: video_info.title.clone(),
+            output_dir: paths.root.clone(),
+            download_section: section,
+            total_duration: chapters.iter().map(|c| c.end_time - c.start_time).sum(),
+            clips: chapters
+                .iter()
+                .zip(&clip_names)
+                .map(|(chapter, name)| PlannedClip {
+                    number: chapter.number,
+                    title: chapter.title.clone(),
+                    start_time: chapter.start_time,
+                    end_time: chapter.end_time,
+                    duration: chapter.end_time - chapter.start_time,
+                    estimated_size: (args.info || args.verbose)
+                        .then(|| estimated_clip_size(&video_info, chapter))
+                        .flatten(),
+                    output: paths
+                        .clips
+                        .join(format!("{}.{}", name, args.container.extension())),
+                })
+                .collect(),
+            format_variants: if args.formats {
+                formats::planned_format_variants(
+                    &paths.formats,
+                    &FormatOptions::from_args(args, None),
+                )
+            } else {
+                Vec::new()
+            },
+            thumbnails: args.thumbnails.then(|| PlannedVariant {
+                name: "thumbnails".to_string(),
+                directory: paths.thumbnails.clone(),
+                extension: args.thumbnail_format.extension().to_string(),
+            }),
+            keep_full: args.keep_full,
+        };
+
+        if args.json {
+            println!("{}", serde_json::to_string_pretty(&plan)?);
+        } else {
+            plan.print();
+        }
+        return Ok(());
+    }
+
+    let video_path = match downloaded {
+        Some(video_path) => video_path,
+        None => {
+            fs::create_dir_all(&paths.clips).context("Failed to create clips directory")?;
+            status!("Output directory: {}\n", paths.root.display());
+            fetch_video(session, cleaned_url, &video_info, &paths, section, sub_lang).await?
+        }
+    };
+
+    let media_info = if args.info
+        || args.verbose
+        || args.fps.is_some()
+        || args.sample_rate.is_some()
+        || args.audio_channels.is_some()
+        || args.audio_tracks.is_some()
+        || args.stems
+    {
+        match task::block_in_place(|| probe::probe(&session.executables, &video_path)) {
+            Ok(info) => Some(info),
+            Err(e) => {
+                estatus!("Warning: could not read video details: {:#}", e);
+                None
+            }
+        }
+    } else {
+        None
+    };
+    if args.info || args.verbose {
+        if let Some(info) = &media_info {
+            info.print_summary();
+        }
+    }
+    let source_fps = media_info.as_ref().and_then(MediaInfo::fps);
+    let source_sample_rate = media_info
+        .as_ref()
+        .and_then(MediaInfo::sample_rate)
+        .and_then(|rate| rate.parse::<u32>().ok());
+    let source_channels = media_info.as_ref().and_then(MediaInfo::channels);
+    let channels = args.audio_channels.and_then(AudioChannels::count);
+    let resample_audio = args
+        .sample_rate
+        .is_some_and(|rate| source_sample_rate != Some(rate));
+    let remix_audio = channels.is_some_and(|channels| source_channels != Some(channels));
+    let audio_tracks = match &args.audio_tracks {
+        Some(selection) => {
+            let media_info = media_info
+                .as_ref()
+                .context("--audio-tracks needs ffprobe to read the video's audio tracks")?;
+            let tracks = selection.select(&media_info.audio_languages())?;
+            if tracks.len() > 1 {
+                status!("Keeping {} audio tracks", tracks.len());
+            }
+            Some(tracks)
+        }
+        None => None,
+    };
+
+    let subtitle_path = sub_lang.and_then(|lang| {
+        let path = subtitles::find_subtitle_file(&video_path, lang);
+        if path.is_none() {
+            estatus!("Warning: no '{}' subtitles available for this video", lang);
+        }
+        path
+    });
+
+    // The download only covers the selected section, so shift chapter times
+    // to be relative to the start of the downloaded file.
+    if let Some((offset, _)) = section {
+        for chapter in &mut chapters {
+            chapter.start_time -= offset;
+            chapter.end_time -= offset;
+        }
+        for segment in &mut skip_segments {
+            segment.0 -= offset;
+            segment.1 -= offset;
+        }
+    }
+
+    if args.concat_only {
+        let output = paths.root.join(format!(
+            "{}.{}",
+            FilenameOptions::from_args(args).clean(&video_info.title),
+            args.container.extension()
+        ));
+        status!("\nEmbedding {} chapter marks...", chapters.len());
+        task::block_in_place(|| {
+            embed_chapter_marks(
+                &session.executables,
+                &video_path,
+                &video_info.title,
+                &chapters,
+                &output,
+            )
+        })?;
+        // Nothing is split, so the clips directory stays empty.
+        let _ = fs::remove_dir(&paths.clips);
+
+        if !args.keep_full {
+            fs::remove_file(&video_path).context("Failed to remove full video file")?;
+            if let Some(subtitle_path) = &subtitle_path {
+                fs::remove_file(subtitle_path).context("Failed to remove subtitle file")?;
+            }
+        }
+
+        status!("\nDone! Video saved to: {}", output.display());
+        return Ok(());
+    }
+
+    status!("\nSplitting video into chapters...\n");
+
+    let metadata = args.embed_metadata.then(|| VideoMetadata {
+        title: video_info.title.clone(),
+        uploader: video_info.uploader.clone(),
+        upload_date: video_info.upload_date.clone(),
+        url: canonical_url(&video_info, cleaned_url),
+    });
+
+    if let (Some(target), Some(source)) = (args.fps, source_fps) {
+        if !encode::needs_fps_conversion(source, target) {
+            status!(
+                "Source is already {} fps, skipping frame rate conversion",
+                target
+            );
+        }
+    }
+    let interlaced = args.detect_interlace
+        && match task::block_in_place(|| {
+            interlace::detect_interlace(&session.executables, &video_path)
+        }) {
+            Ok(interlaced) => interlaced,
+            Err(e) => {
+                estatus!("Warning: could not detect interlacing: {:#}", e);
+                false
+            }
+        };
+    if interlaced {
+        if EncodeSettings::for_source(args, source_fps, false).is_none() {
+            estatus!("Warning: the video is interlaced, re-encoding clips to deinterlace them");
+        } else {
+            status!("The video is interlaced, deinterlacing clips");
+        }
+    }
+    let encode = EncodeSettings::for_source(args, source_fps, interlaced);
+    let audio_settings = EncodeSettings::new(args);
+    if resample_audio && encode.is_none() {
+        estatus!(
+            "Warning: the source audio is {}, re-encoding audio to --sample-rate {} Hz",
+            source_sample_rate.map_or("at an unknown sample rate".to_string(), |rate| format!(
+                "{} Hz",
+                rate
+            )),
+            audio_settings.sample_rate.unwrap_or_default()
+        );
+    }
+    if remix_audio && encode.is_none() {
+        estatus!(
+            "Warning: the source audio has {} channels, re-encoding audio to --audio-channels {}",
+            source_channels.map_or("an unknown number of".to_string(), |channels| channels
+                .to_string()),
+            channels.unwrap_or_default()
+        );
+    }
+    let split_options = SplitOptions {
+        encode: encode.as_ref(),
+        embed_subtitles: subtitle_path.as_deref().filter(|_| args.embed_subs),
+        skip_segments: &skip_segments,
+        metadata: metadata.as_ref(),
+        container: args.container,
+        executables: &session.executables,
+        resume: args.resume && !args.force,
+        sleep: args.sleep_chapters.map(Duration::from_millis),
+        jobs: args.jobs,
+        fade: args.fade_duration,
+        fade_audio: args.normalize_audio,
+        speed: &SpeedSettings::from_args(args),
+        continue_on_error: args.continue_on_error,
+        resample_audio: (resample_audio || remix_audio).then_some(&audio_settings),
+        audio_tracks: audio_tracks.as_deref(),
+        two_pass: args.two_pass,
+        record: session.database.as_ref().map(|database| VideoRecord {
+            database,
+            video_id: &video_info.id,
+        }),
+        chapter_hook: args
+            .on_chapter_complete
+            .as_deref()
+            .map(|command| ChapterHook {
+                command,
+                video_title: &video_info.title,
+            }),
+    };
+    if let Some(database) = &session.database {
+        database.lock().unwrap().record_video(
+            &video_info.id,
+            cleaned_url,
+            &video_info.title,
+            &paths.root,
+            !args.incremental,
+        )?;
+    }
+
+    let results = split_chapters(
+        &video_path,
+        &chapters,
+        &clip_names,
+        &paths.clips,
+        &split_options,
+    )
+    .await?;
+
+    if args.extract_gaps && !gaps.is_empty() {
+        status!("\nExtracting {} gaps...\n", gaps.len());
+        let gap_chapters: Vec<Chapter> = gaps
+            .iter()
+            .enumerate()
+            .map(|(i, gap)| gap.to_chapter(i + 1))
+            .collect();
+        let gap_names: Vec<String> = gaps.iter().map(Gap::clip_name).collect();
+        // Gaps aren't chapters, so they aren't recorded in --db or tagged
+        // with a track number that would collide with the real chapters'.
+        let gap_options = SplitOptions {
+            metadata: None,
+            record: None,
+            chapter_hook: None,
+            ..split_options
+        };
+        let gap_results = split_chapters(
+            &video_path,
+            &gap_chapters,
+            &gap_names,
+            &paths.clips,
+            &gap_options,
+        )
+        .await?;
+        for failure in gap_results.iter().filter(|result| result.is_failure()) {
+            estatus!(
+                "Warning: failed to extract the gap {}: {}",
+                gaps[failure.number() - 1].describe(),
+                failure.error().unwrap_or_default()
+            );
+        }
+    }
+
+    // With --continue-on-error, carry on with the chapters that did split.
+    let split_chapters = chapters.len();
+    let failed_numbers: Vec<usize> = results
+        .iter()
+        .filter(|result| result.is_failure())
+        .map(ChapterResult::number)
+        .collect();
+    if !failed_numbers.is_empty() {
+        let failed = |chapter: &Chapter| failed_numbers.contains(&chapter.number);
+        let (kept_chapters, kept_names): (Vec<Chapter>, Vec<String>) = chapters
+            .into_iter()
+            .zip(clip_names)
+            .filter(|(chapter, _)| !failed(chapter))
+            .unzip();
+        chapters = kept_chapters;
+        clip_names = kept_names;
+        if chapters.is_empty() {
+            write_processing_report(&paths.root, &results, started);
+            anyhow::bail!(failure_summary(&results, split_chapters));
+        }
+    }
+
+    if let Some(subtitle_path) = subtitle_path.as_deref().filter(|_| !args.embed_subs) {
+        status!("\nSlicing subtitles...");
+        let cues = subtitles::read_cues(subtitle_path)?;
+        for (chapter, name) in chapters.iter().zip(&clip_names) {
+            let output = paths.clips.join(format!("{}.srt", name));
+            subtitles::slice_subtitles(&cues, chapter, &output)?;
+        }
+    }
+
+    if args.description {
+        match video_info
+            .description
+            .as_deref()
+            .filter(|d| !d.trim().is_empty())
+        {
+            Some(description) => {
+                write_descriptions(args, description, &chapters, &clip_names, &paths)?
+            }
+            None => estatus!("Warning: this video has no description to save"),
+        }
+    }
+
+    let clips: Vec<PathBuf> = clip_names
+        .iter()
+        .map(|name| {
+            paths
+                .clips
+                .join(format!("{}.{}", name, args.container.extension()))
+        })
+        .collect();
+
+    if args.trim_silence {
+        status!("\nTrimming silence...\n");
+        task::block_in_place(|| {
+            silence::trim_clips(
+                &session.executables,
+                &clips,
+                SilenceOptions {
+                    threshold_db: args.silence_threshold,
+                    min_duration: args.silence_duration,
+                },
+                encode.as_ref(),
+            )
+        })?;
+    }
+
+    if args.normalize_audio {
+        status!("\nNormalizing audio...\n");
+        let audio = EncodeSettings::new(args);
+        task::block_in_place(|| {
+            loudnorm::normalize_clips(&session.executables, &clips, args.normalize_target, &audio)
+        })?;
+    }
+
+    if args.prepend_clip.is_some() || args.append_clip.is_some() {
+        status!("\nAdding intro/outro...\n");
+        task::block_in_place(|| {
+            bumpers::concat_clips(
+                &session.executables,
+                &clips,
+                &Bumpers {
+                    prepend: args.prepend_clip.as_deref(),
+                    append: args.append_clip.as_deref(),
+                },
+                &EncodeSettings::new(args),
+            )
+        })?;
+    }
+
+    // Post-processing may have changed the clips since they were first
+    // recorded, and --resume skips recording clips from earlier runs.
+    if let Some(record) = &split_options.record {
+        for (clip, chapter) in clips.iter().zip(&chapters) {
+            record.clip(chapter, clip);
+        }
+    }
+
+    if let Some(pattern) = &session.merge_pattern {
+        for group in chapters::merge_groups(&chapters, pattern) {
+            let clips: Vec<PathBuf> = clip_names[group.clone()]
+                .iter()
+                .map(|name| {
+                    paths
+                        .clips
+                        .join(format!("{}.{}", name, args.container.extension()))
+                })
+                .collect();
+            let output = paths.clips.join(format!(
+                "{}{}.{}",
+                clip_names[group.start],
+                args.merge_suffix,
+                args.container.extension()
+            ));
+
+            status!("Merging {} chapters into {}", clips.len(), output.display());
+            task::block_in_place(|| merge_chapters(&session.executables, &clips, &output))?;
+        }
+    }
+
+    if args.hls {
+        status!("\nCreating HLS streams...\n");
+        let clips: Vec<(PathBuf, &Chapter)> = clips.iter().cloned().zip(&chapters).collect();
+        task::block_in_place(|| {
+            hls::generate_hls(
+                &session.executables,
+                &clips,
+                &paths.hls,
+                &EncodeSettings::new(args),
+                &HlsOptions {
+                    segment_duration: args.hls_segment_duration,
+                    base_url: args.hls_base_url.as_deref(),
+                },
+            )
+        })?;
+    }
+
+    if args.thumbnails {
+        status!("\nExtracting thumbnails...\n");
+        task::block_in_place(|| {
+            thumbnails::generate_thumbnails(
+                &session.executables,
+                &video_path,
+                &chapters,
+                &clip_names,
+                &paths.thumbnails,
+                args.thumbnail_format,
+                args.thumbnail_offset,
+            )
+        })?;
+    }
+
+    if args.nfo {
+        status!("\nWriting NFO files...\n");
+        let clips: Vec<NfoClip> = clips
+            .iter()
+            .zip(&chapters)
+            .zip(&clip_names)
+            .map(|((path, chapter), name)| NfoClip {
+                path: path.clone(),
+                chapter,
+                thumbnail: args.thumbnails.then(|| {
+                    paths
+                        .thumbnails
+                        .join(format!("{}.{}", name, args.thumbnail_format.extension()))
+                }),
+            })
+            .collect();
+        task::block_in_place(|| {
+            nfo::write_nfo_files(
+                &session.executables,
+                &clips,
+                &NfoVideo {
+                    title: &video_info.title,
+                    uploader: video_info.uploader.as_deref(),
+                    upload_date: video_info.upload_date.as_deref(),
+                    description: video_info.description.as_deref(),
+                },
+            )
+        })?;
+    }
+
+    if args.spritesheet {
+        status!("\nGenerating sprite sheet...");
+        let aspect_ratio = match (video_info.width, video_info.height) {
+            (Some(width), Some(height)) if height > 0 => f64::from(width) / f64::from(height),
+            _ => 16.0 / 9.0,
+        };
+        task::block_in_place(|| {
+            thumbnails::generate_spritesheet(
+                &session.executables,
+                &video_path,
+                &chapters,
+                &paths.root,
+                SpriteLayout::new(
+                    chapters.len(),
+                    args.sprite_width,
+                    aspect_ratio,
+                    args.sprite_cols,
+                ),
+                section.map_or(0.0, |(start, _)| start),
+            )
+        })?;
+    }
+
+    if args.formats {
+        status!("\nGenerating format variants...\n");
+        fs::create_dir_all(&paths.formats).context("Failed to create formats directory")?;
+        task::block_in_place(|| {
+            formats::generate_format_variants(
+                &session.executables,
+                &video_path,
+                &chapters,
+                &clip_names,
+                &paths.formats,
+                &EncodeSettings {
+                    deinterlace: (args.deinterlace || interlaced).then_some(args.deinterlace_mode),
+                    ..EncodeSettings::new(args)
+                },
+                &FormatOptions {
+                    resample_audio: resample_audio || remix_audio,
+                    ..FormatOptions::from_args(args, metadata.clone())
+                },
+            )
+        })?;
+    }
+
+    if args.embed_thumbnail {
+        match video_info.thumbnail.as_deref() {
+            Some(_) if !args.audio_format.supports_cover_art() => estatus!(
+                "Warning: {} files can't hold cover art, skipping --embed-thumbnail",
+                args.audio_format.extension()
+            ),
+            Some(url) => {
+                status!("\nEmbedding cover art...\n");
+                let options = FormatOptions::from_args(args, None);
+                let clips: Vec<PathBuf> = clip_names
+                    .iter()
+                    .map(|name| {
+                        FormatVariant::AudioOnly.output_path(&paths.formats, name, &options)
+                    })
+                    .collect();
+                task::block_in_place(|| {
+                    cover::embed_cover_art(
+                        &session.executables,
+                        url,
+                        session.executables.network.proxy.as_ref(),
+                        &clips,
+                        args.audio_format,
+                    )
+                })?;
+            }
+            None => estatus!("Warning: this video has no thumbnail to embed"),
+        }
+    }
+
+    if args.concat_audio || args.cue_sheet {
+        let options = FormatOptions::from_args(args, None);
+        let audio_dir = paths.formats.join(FormatVariant::AudioOnly.dir_name());
+        let clips: Vec<PathBuf> = clip_names
+            .iter()
+            .map(|name| FormatVariant::AudioOnly.output_path(&paths.formats, name, &options))
+            .collect();
+
+        let concatenated = if args.concat_audio {
+            let existing: Vec<PathBuf> =
+                clips.iter().filter(|clip| clip.exists()).cloned().collect();
+            let output = audio_dir.join(format!(
+                "{}.{}",
+                cue::CONCAT_STEM,
+                FormatVariant::AudioOnly.extension(&options)
+            ));
+            status!("\nJoining {} audio clips...", existing.len());
+            task::block_in_place(|| {
+                concat_files(&session.executables, &existing, &output, None, &[])
+            })
+            .with_context(|| format!("Failed to create {}", output.display()))?;
+            Some(output)
+        } else {
+            None
+        };
+
+        if args.cue_sheet {
+            let output = audio_dir.join(cue::CUE_FILE);
+            let clips: Vec<(PathBuf, &Chapter)> = clips.into_iter().zip(&chapters).collect();
+            task::block_in_place(|| {
+                cue::write_cue_sheet(
+                    &session.executables,
+                    &clips,
+                    concatenated.as_deref(),
+                    &CueAlbum {
+                        title: &video_info.title,
+                        performer: video_info.uploader.as_deref(),
+                        format: args.audio_format,
+                    },
+                    &output,
+                )
+            })?;
+            status!("\nCUE sheet written to {}", output.display());
+        }
+    }
+
+    if args.stems {
+        let languages = media_info
+            .as_ref()
+            .context("--stems needs ffprobe to read the video's audio streams")?
+            .audio_languages();
+        if languages.len() < 2 {
+            estatus!(
+                "Warning: the video has {} audio streams, --stems works best with separate tracks",
+                languages.len()
+            );
+        }
+        status!(
+            "\nExporting {} audio stems per chapter...\n",
+            languages.len()
+        );
+        let chapters: Vec<(&str, &Chapter)> = clip_names
+            .iter()
+            .map(String::as_str)
+            .zip(&chapters)
+            .collect();
+        task::block_in_place(|| {
+            stems::export_stems(
+                &session.executables,
+                &video_path,
+                &chapters,
+                &languages,
+                &paths.stems,
+                &AudioSettings::from_args(args),
+                metadata.as_ref(),
+            )
+        })?;
+    }
+
+    if args.waveforms {
+        status!("\nDrawing waveforms...\n");
+        let options = FormatOptions::from_args(args, None);
+        let clips: Vec<PathBuf> = clip_names
+            .iter()
+            .map(|name| {
+                if args.formats {
+                    FormatVariant::AudioOnly.output_path(&paths.formats, name, &options)
+                } else {
+                    paths
+                        .clips
+                        .join(format!("{}.{}", name, args.container.extension()))
+                }
+            })
+            .collect();
+        task::block_in_place(|| {
+            waveforms::generate_waveforms(
+                &session.executables,
+                &clips,
+                &paths.waveforms,
+                args.waveform_size,
+                &args.waveform_color,
+            )
+        })?;
+    }
+
+    if args.write_playlist {
+        let clips: Vec<(PathBuf, &Chapter)> = clip_names
+            .iter()
+            .map(|name| {
+                paths
+                    .clips
+                    .join(format!("{}.{}", name, args.container.extension()))
+            })
+            .zip(&chapters)
+            .collect();
+        m3u::write_m3u8_playlist(&clips, &paths.root.join("playlist.m3u8"))?;
+
+        if args.formats {
+            let options = FormatOptions::from_args(args, None);
+            for variant in FormatVariant::ALL {
+                let clips: Vec<(PathBuf, &Chapter)> = clip_names
+                    .iter()
+                    .map(|name| variant.output_path(&paths.formats, name, &options))
+                    .zip(&chapters)
+                    .collect();
+                let playlist = paths
+                    .formats
+                    .join(format!("playlist_{}.m3u8", variant.dir_name()));
+                m3u::write_m3u8_playlist(&clips, &playlist)?;
+            }
+        }
+    }
+
+    if !args.keep_full {
+        fs::remove_file(&video_path).context("Failed to remove full video file")?;
+        if let Some(subtitle_path) = &subtitle_path {
+            fs::remove_file(subtitle_path).context("Failed to remove subtitle file")?;
+        }
+        status!("\nRemoved full video file");
+    }
+
+    if args.checksums {
+        let checksum_path = task::block_in_place(|| checksums::write_checksums(&paths.root))?;
+        status!("\nChecksums written to {}", checksum_path.display());
+    }
+
+    if let Some(bucket) = &args.upload_s3 {
+        status!("\nUploading to s3://{}...\n", bucket);
+        upload::upload_dir(
+            &paths.root,
+            &S3Options {
+                bucket: bucket.clone(),
+                prefix: args.s3_prefix.clone(),
+                endpoint: args.s3_endpoint.clone(),
+                only: args.upload_only_formats.clone(),
+            },
+        )
+        .await?;
+    }
+
+    if progress::is_json() {
+        let mut files = Vec::new();
+        checksums::collect_files(&paths.root, &mut files)?;
+        files.sort();
+        progress::emit(Event::Complete {
+            output_dir: paths.root.clone(),
+            files,
+        });
+    }
+
+    status!("\nDone! All clips saved to: {}", paths.root.display());
+    status!("  - Original clips: {}", paths.clips.display());
+    if args.formats {
+        status!("  - Format variants: {}", paths.formats.display());
+    }
+    if args.thumbnails {
+        status!("  - Thumbnails: {}", paths.thumbnails.display());
+    }
+    if args.waveforms {
+        status!("  - Waveforms: {}", paths.waveforms.display());
+    }
+    if args.hls {
+        let master = paths.hls.join(hls::MASTER_PLAYLIST_FILE);
+        status!("  - HLS streams: {}", master.display());
+    }
+    if args.stems {
+        status!("  - Audio stems: {}", paths.stems.display());
+    }
+    if args.spritesheet {
+        let spritesheet = paths.root.join(thumbnails::SPRITESHEET_FILE);
+        status!("  - Sprite sheet: {}", spritesheet.display());
+    }
+
+    if let Some(command) = &args.on_complete {
+        hooks::run(
+            command,
+            &[
+                ("output_dir", paths.root.display().to_string()),
+                ("clip_count", clips.len().to_string()),
+            ],
+        )
+        .await;
+    }
+
+    if args.continue_on_error {
+        write_processing_report(&paths.root, &results, started);
+    }
+    if !failed_numbers.is_empty() {
+        anyhow::bail!(failure_summary(&results, split_chapters));
+    }
+
+    if let Some(archive) = &session.archive {
+        let extractor = video_info.extractor_key.as_deref().unwrap_or("youtube");
+        archive.lock().unwrap().record(extractor, &video_info.id)?;
+    }
+
+    Ok(())
+}
+
+/// Writes `description.txt`, and with `--description-per-chapter` each
+/// chapter's section of it next to the chapter's clip.
+fn write_descriptions(
+    args: &Args,
+    description: &str,
+    chapters: &[Chapter],
+    clip_names: &[String],
+    paths: &OutputPaths,
+) -> Result<()> {
+    let path = paths.root.join("description.txt");
+    fs::write(&path, description).with_context(|| format!("Failed to write {}", path.display()))?;
+
+    if args.description_per_chapter {
+        let sections = chapters::description_sections(description, chapters);
+        let mut missing = 0;
+        for (section, name) in sections.iter().zip(clip_names) {
+            let Some(section) = section else {
+                missing += 1;
+                continue;
+            };
+            let path = paths.clips.join(format!("{}.txt", name));
+            fs::write(&path, section)
+                .with_context(|| format!("Failed to write {}", path.display()))?;
+        }
+        if missing > 0 {
+            estatus!(
+                "Warning: {} chapters have no timestamp line in the description",
+                missing
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Prints every chapter with its duration, marking the ones the current
+/// selection and duration limits would skip.
+fn list_chapters(session: &Session, chapters: &[Chapter], limits: &DurationLimits) -> Result<()> {
+    let selected = chapters::select_chapters(
+        chapters.to_vec(),
+        session.selection.as_ref(),
+        session.chapter_filter.as_ref(),
+    );
+
+    let listed: Vec<ListedChapter> = chapters
+        .iter()
+        .map(|chapter| ListedChapter {
+            number: chapter.number,
+            title: chapter.title.clone(),
+            start_time: chapter.start_time,
+            end_time: chapter.end_time,
+            duration: chapter.end_time - chapter.start_time,
+            selected: selected.iter().any(|c| c.number == chapter.number)
+                && !session.skips.matches(chapter),
+            skip_reason: limits.violation(chapter),
+        })
+        .collect();
+
+    if session.args.list_chapters_json {
+        println!("{}", serde_json::to_string_pretty(&listed)?);
+    } else {
+        plan::print_chapter_table(&listed);
+    }
+
+    Ok(())
+}
+
+/// Where everything produced for one video is written.
+struct OutputPaths {
+    /// Holds the downloaded video, playlists, checksums and the directories below.
+    root: PathBuf,
+    clips: PathBuf,
+    formats: PathBuf,
+    thumbnails: PathBuf,
+    waveforms: PathBuf,
+    hls: PathBuf,
+    stems: PathBuf,
+}
+
+/// Places the output in a directory named by `--dir-template` (after the
+/// video by default) under `--output-dir` (or the current directory). With `--flat`, clips go
+/// straight into `--output-dir` instead.
+fn resolve_output_paths(
+    args: &Args,
+    dir_template: &DirTemplate,
+    video_info: &VideoInfo,
+) -> OutputPaths {
+    let base = args
+        .output_dir
+        .clone()
+        .unwrap_or_else(|| PathBuf::from("."));
+
+    let (root, clips) = if args.flat {
+        let base = filename::long_path_safe(base, args.max_filename_length);
+        (base.clone(), base)
+    } else {
+        let mut dir = dir_template.render(&DirContext {
+            title: &video_info.title,
+            uploader: video_info.uploader.as_deref(),
+            upload_date: video_info.upload_date.as_deref(),
+            id: &video_info.id,
+            filenames: &FilenameOptions::from_args(args),
+        });
+        if args.include_id {
+            dir.push_str(&id_suffix(&video_info.id));
+        }
+        let date = video_info
+            .upload_date
+            .as_deref()
+            .and_then(|date| metadata::format_date_as(date, args.date_format));
+        // The date goes on the last directory when the template nests them.
+        if let Some(date) = date.filter(|_| args.date_prefix) {
+            let name_start = dir
+                .rfind(['/', std::path::MAIN_SEPARATOR])
+                .map_or(0, |i| i + 1);
+            dir.insert_str(name_start, &format!("{}_", date));
+        }
+        let root = filename::long_path_safe(base.join(dir), args.max_filename_length);
+        let clips = root.join("clips");
+        (root, clips)
+    };
+
+    OutputPaths {
+        formats: root.join("formats"),
+        thumbnails: root.join("thumbnails"),
+        waveforms: root.join("waveforms"),
+        hls: root.join("hls"),
+        stems: root.join("stems"),
+        root,
+        clips,
+    }
+}
+
+/// The ` [VIDEO_ID]` suffix yt-dlp appends to file names.
+fn id_suffix(id: &str) -> String {
+    format!(" [{}]", id)
+}
+
+/// The clip name template, dropping the number for `--no-number` and for
+/// `--numbering-style none` with the default template, and joining number
+/// and title with `--separator`.
+fn clip_template(args: &Args) -> String {
+    let default_template = args.template == DEFAULT_TEMPLATE;
+    let unnumbered = args.numbering_style == NumberingStyle::None && default_template;
+    match &args.separator {
+        _ if args.no_number || unnumbered => "{title}".to_string(),
+        Some(separator) if default_template => format!(
+            "{{num}}{}{{title}}",
+            separator.replace('{', "{{").replace('}', "}}")
+        ),
+        _ => args.template.clone(),
+    }
+}
+
+/// File names (without extension) for the clips of `chapters`, shared by
+/// the clips and every format variant. Names that collide, such as two
+/// chapters with the same title under `--no-number`, get a `_2`, `_3`, ...
+/// suffix before anything is written, with one warning listing them all.
+fn clip_names(
+    template: &Template,
+    chapters: &[Chapter],
+    video_title: &str,
+    num_width: usize,
+    filenames: &FilenameOptions,
+) -> Vec<String> {
+    let mut names: Vec<String> = Vec::with_capacity(chapters.len());
+    let mut renamed = Vec::new();
+
+    for chapter in chapters {
+        let name = template.render(&TemplateContext {
+            num: chapter.number,
+            num_width,
+            title: &chapter.title,
+            start: chapter.start_time,
+            end: chapter.end_time,
+            video_title,
+            filenames,
+        });
+
+        // Compared case-insensitively, since "Q&A" and "q&a" are the same
+        // file on macOS and Windows.
+        let taken = |candidate: &str| names.iter().any(|n| n.eq_ignore_ascii_case(candidate));
+        let mut unique = name.clone();
+        let mut suffix = 2;
+        while taken(&unique) {
+            unique = format!("{}_{}", name, suffix);
+            suffix += 1;
+        }
+        if unique != name {
+            renamed.push((chapter.number, name, unique.clone()));
+        }
+        names.push(unique);
+    }
+
+    if !renamed.is_empty() {
+        estatus!("Warning: these chapters would overwrite an earlier chapter's clip, so they are saved under a different name:");
+        for (number, name, unique) in &renamed {
+            estatus!("  - chapter {}: \"{}\" -> \"{}\"", number, name, unique);
+        }
+    }
+
+    names
+}
+
+fn clean_url(url: &str) -> String {
+    url.replace("\\?", "?")
+        .replace("\\=", "=")
+        .replace("\\&", "&")
+}
+
+fn check_codec(executables: &Executables, codec: VideoCodec) -> Result<()> {
+    let output = executables
+        .ffmpeg()
+        .args(["-hide_banner", "-codecs"])
+        .output()
+        .context("Failed to execute ffmpeg")?;
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    if !stdout
+        .split_whitespace()
+        .any(|word| word == codec.encoder())
+    {
+        anyhow::bail!(
+            "Encoder '{}' is not available in this ffmpeg build; install an ffmpeg compiled with it or choose another --codec",
+            codec.encoder()
+        );
+    }
+
+    Ok(())
+}
+
+fn check_hwaccel(executables: &Executables, hwaccel: HwAccel) -> Result<()> {
+    let output = executables
+        .ffmpeg()
+        .args(["-hide_banner", "-hwaccels"])
+        .output()
+        .context("Failed to execute ffmpeg")?;
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let available: Vec<&str> = stdout
+        .lines()
+        .skip_while(|line| !line.starts_with("Hardware acceleration methods"))
+        .skip(1)
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect();
+
+    if !available.contains(&hwaccel.hwaccel_name()) {
+        anyhow::bail!(
+            "Hardware acceleration backend '{}' is not supported by this ffmpeg build.\n\
+             Available methods: {}",
+            hwaccel.hwaccel_name(),
+            if available.is_empty() {
+                "none".to_string()
+            } else {
+                available.join(", ")
+            }
+        );
+    }
+
+    Ok(())
+}
+
+/// Whether `url` points at a playlist rather than a single video. Watch URLs
+/// that merely play a video from a playlist count as a single video.
+fn is_playlist_url(url: &str) -> bool {
+    url.contains("/playlist?") || (url.contains("list=") && !url.contains("v="))
+}
+
+fn dump_video_info(executables: &Executables, url: &str) -> Result<VideoInfo> {
+    let output = executables
+        .ytdlp()
+        .args(["--dump-json", "--no-download", "--no-playlist", url])
+        .output()
+        .context("Failed to execute yt-dlp")?;
+
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        if let Some(unavailable) = availability::from_ytdlp_error(&error) {
+            return Err(anyhow::anyhow!("yt-dlp failed: {}", error).context(unavailable));
+        }
+        anyhow::bail!("yt-dlp failed: {}", error);
+    }
+
+    let json_str = String::from_utf8(output.stdout).context("Failed to parse yt-dlp output")?;
+
+    let video_info: VideoInfo =
+        serde_json::from_str(&json_str).context("Failed to parse video information")?;
+
+    Ok(video_info)
+}
+
+/// Fetches the information of every video in a playlist. yt-dlp prints one
+/// JSON object per line and skips videos it can't fetch.
+fn get_playlist_entries(
+    executables: &Executables,
+    url: &str,
+    start: Option<usize>,
+    end: Option<usize>,
+) -> Result<Vec<VideoInfo>> {
+    let mut command = executables.ytdlp();
+    command.args([
+        "--dump-json",
+        "--no-download",
+        "--yes-playlist",
+        "--ignore-errors",
+    ]);
+
+    if let Some(start) = start {
+        command.args(["--playlist-start", &start.to_string()]);
+    }
+    if let Some(end) = end {
+        command.args(["--playlist-end", &end.to_string()]);
+    }
+
+    let output = command
+        .arg(url)
+        .output()
+        .context("Failed to execute yt-dlp")?;
+
+    let stdout = String::from_utf8(output.stdout).context("Failed to parse yt-dlp output")?;
+    let entries = stdout
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| serde_json::from_str(line).context("Failed to parse video information"))
+        .collect::<Result<Vec<VideoInfo>>>()?;
+
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        if entries.is_empty() {
+            anyhow::bail!("yt-dlp failed: {}", error);
+        }
+        estatus!(
+            "Warning: some playlist videos could not be fetched:\n{}",
+            error.trim_end()
+        );
+    }
+
+    Ok(entries)
+}
+
+/// The yt-dlp format selector: `--format` verbatim, otherwise the best
+/// streams capped at `--max-height`.
+fn format_selector(args: &Args) -> String {
+    match (&args.format, args.max_height) {
+        (Some(format), _) => format.clone(),
+        // Every audio-only format, kept as separate tracks with
+        // --audio-multistreams.
+        (None, height) if args.audio_tracks.is_some() || args.stems => {
+            let video = height.map_or("bestvideo".to_string(), |height| {
+                format!("bestvideo[height<={}]", height)
+            });
+            format!("{}+mergeall[vcodec=none]", video)
+        }
+        (None, Some(height)) => {
+            format!("bestvideo[height<={0}]+bestaudio/best[height<={0}]", height)
+        }
+        (None, None) => DEFAULT_FORMAT.to_string(),
+    }
+}
+
+/// The approximate size of the download in bytes, scaled down when only a
+/// section of the video is downloaded.
+fn estimated_download_size(video_info: &VideoInfo, section: Option<(f64, f64)>) -> Option<u64> {
+    let size = video_info.filesize.or(video_info.filesize_approx)?;
+
+    let fraction = match (section, video_info.duration) {
+        (Some((start, end)), Some(duration)) if duration > 0.0 => (end - start) / duration,
+        _ => 1.0,
+    };
+
+    Some((size * fraction.min(1.0)) as u64)
+}
+
+/// Estimates the size of a stream-copied clip of `chapter` from the source's
+/// average bitrate.
+fn estimated_clip_size(video_info: &VideoInfo, chapter: &Chapter) -> Option<u64> {
+    let size = video_info.filesize.or(video_info.filesize_approx)?;
+    let duration = video_info.duration.filter(|&duration| duration > 0.0)?;
+    Some((size / duration * (chapter.end_time - chapter.start_time)) as u64)
+}
+
+/// Fails when the filesystem holding `output_dir` has less than 110% of
+/// `estimated_size` bytes free.
+fn check_disk_space(output_dir: &Path, estimated_size: u64) -> Result<()> {
+    let available = fs2::available_space(output_dir)
+        .with_context(|| format!("Failed to check free space in {}", output_dir.display()))?;
+
+    if available < estimated_size + estimated_size / 10 {
+        anyhow::bail!(
+            "Not enough disk space in {}: the download is about {} but only {} is available.\n\
+             Free up some space or use --skip-space-check to download anyway.",
+            output_dir.display(),
+            format_size(estimated_size),
+            format_size(available)
+        );
+    }
+
+    Ok(())
+}
+
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+/// Checks for disk space, then downloads the video (or just `section` of it)
+/// into `paths.root`, retrying on failure.
+async fn fetch_video(
+    session: &Session,
+    url: &str,
+    video_info: &VideoInfo,
+    paths: &OutputPaths,
+    section: Option<(f64, f64)>,
+    sub_lang: Option<&str>,
+) -> Result<PathBuf> {
+    let args = &session.args;
+
+    if !args.skip_space_check {
+        if let Some(size) = estimated_download_size(video_info, section) {
+            check_disk_space(&paths.root, size)?;
+        }
+    }
+
+    let format = format_selector(args);
+    if args.verbose {
+        status!("yt-dlp format: {}", format);
+    }
+    let stem = if args.include_id {
+        format!("{}{}", FULL_VIDEO_STEM, id_suffix(&video_info.id))
+    } else {
+        FULL_VIDEO_STEM.to_string()
+    };
+    let download_options = DownloadOptions {
+        stem: &stem,
+        format: &format,
+        container: args.container,
+        section,
+        sub_lang,
+        resume: args.resume && !args.force,
+        limit_rate: args.bandwidth_limit.as_deref(),
+        audio_multistreams: args.audio_tracks.is_some() || args.stems,
+    };
+    session
+        .retry
+        .run("Download", || {
+            download_full_video(&session.executables, url, &paths.root, &download_options)
+        })
+        .await
+}
+
+/// The video's canonical watch URL: rebuilt from the ID for YouTube, and the
+/// page yt-dlp reports (or the URL it was given) for other sites.
+fn canonical_url(video_info: &VideoInfo, url: &str) -> String {
+    match video_info.extractor_key.as_deref() {
+        None | Some("Youtube") => format!("https://www.youtube.com/watch?v={}", video_info.id),
+        Some(_) => video_info
+            .webpage_url
+            .clone()
+            .unwrap_or_else(|| url.to_string()),
+    }
+}
+
+/// How the full video is downloaded.
+struct DownloadOptions<'a> {
+    /// File name of the download, without extension.
+    stem: &'a str,
+    /// yt-dlp format selector.
+    format: &'a str,
+    container: Container,
+    /// Only download this `(start, end)` range of the video.
+    section: Option<(f64, f64)>,
+    /// Also download subtitles in this language.
+    sub_lang: Option<&'a str>,
+    /// Continue a partial download left by an earlier run.
+    resume: bool,
+    /// Maximum download rate, in yt-dlp's `--limit-rate` format.
+    limit_rate: Option<&'a str>,
+    /// Keep every audio format the selector picks instead of only the first.
+    audio_multistreams: bool,
+}
+
+fn download_full_video(
+    executables: &Executables,
+    url: &str,
+    output_dir: &Path,
+    options: &DownloadOptions,
+) -> Result<PathBuf> {
+    status!("Downloading video...");
+
+    let output_template = output_dir.join(format!("{}.%(ext)s", options.stem));
+    let output_template_str = output_template.to_str().context("Invalid output path")?;
+
+    let pb = progress::spinner();
+    pb.set_message("Downloading...");
+    pb.enable_steady_tick(Duration::from_millis(100));
+
+    let mut command = executables.ytdlp();
+    command.args([
+        "-f",
+        options.format,
+        "--merge-output-format",
+        options.container.extension(),
+        "-o",
+        output_template_str,
+    ]);
+
+    if let Some((start, end)) = options.section {
+        command.args([
+            "--download-sections",
+            &format!("*{:.3}-{:.3}", start, end),
+            "--force-keyframes-at-cuts",
+        ]);
+    }
+
+    if let Some(lang) = options.sub_lang {
+        command.args(subtitles::ytdlp_args(lang));
+    }
+
+    if options.resume && has_partial_download(output_dir, options.stem) {
+        command.arg("--continue");
+    }
+
+    command.args(executables.network.download_args());
+
+    if let Some(rate) = options.limit_rate {
+        command.args(["--limit-rate", rate]);
+    }
+
+    if options.audio_multistreams {
+        command.arg("--audio-multistreams");
+    }
+
+    let result = executables.run(command.arg(url));
+
+    pb.finish_and_clear();
+    result.context("Failed to download video")?;
+
+    let video_path = output_dir.join(format!(
+        "{}.{}",
+        options.stem,
+        options.container.extension()
+    ));
+
+    if !video_path.exists() {
+        anyhow::bail!("Downloaded video file not found");
+    }
+
+    status!("Download complete");
+
+    Ok(video_path)
+}
+
+/// Whether an earlier run left a (possibly partial) download named `stem` in
+/// `output_dir`, such as `full_video.mp4.part` or `full_video.f137.mp4`.
+fn has_partial_download(output_dir: &Path, stem: &str) -> bool {
+    let prefix = format!("{}.", stem);
+    fs::read_dir(output_dir).is_ok_and(|entries| {
+        entries.filter_map(|entry| entry.ok()).any(|entry| {
+            entry
+                .file_name()
+                .to_str()
+                .is_some_and(|name| name.starts_with(&prefix))
+        })
+    })
+}
+
+/// How each chapter clip is produced.
+struct SplitOptions<'a> {
+    /// Re-encode settings, or `None` to stream copy.
+    encode: Option<&'a EncodeSettings>,
+    /// Subtitle file to embed in every clip.
+    embed_subtitles: Option<&'a Path>,
+    /// Time ranges to cut out of the clips.
+    skip_segments: &'a [(f64, f64)],
+    /// Tags to write into every clip.
+    metadata: Option<&'a VideoMetadata>,
+    container: Container,
+    executables: &'a Executables,
+    /// Keep clips that already exist instead of cutting them again.
+    resume: bool,
+    /// Pause after each chapter.
+    sleep: Option<Duration>,
+    jobs: usize,
+    /// Length in seconds of the fade at both ends of every clip.
+    fade: Option<f64>,
+    /// Fade the audio along with the video.
+    fade_audio: bool,
+    speed: &'a SpeedSettings,
+    /// Report failed chapters instead of failing the whole split.
+    continue_on_error: bool,
+    /// Re-encode the audio with these settings when stream copying, to
+    /// change its sample rate or channel count.
+    resample_audio: Option<&'a EncodeSettings>,
+    /// Audio tracks to keep instead of ffmpeg's default of one.
+    audio_tracks: Option<&'a [AudioTrack]>,
+    /// Run a first analysis pass before encoding each clip.
+    two_pass: bool,
+    /// Where to record each clip as soon as it is written.
+    record: Option<VideoRecord<'a>>,
+    /// Run for each clip as soon as it is written.
+    chapter_hook: Option<ChapterHook<'a>>,
+}
+
+async fn split_chapters(
+    video_path: &Path,
+    chapters: &[Chapter],
+    clip_names: &[String],
+    output_dir: &Path,
+    options: &SplitOptions<'_>,
+) -> Result<Vec<ChapterResult>> {
+    let output_paths: Vec<PathBuf> = clip_names
+        .iter()
+        .map(|name| output_dir.join(format!("{}.{}", name, options.container.extension())))
+        .collect();
+
+    // With --resume, clips written by an earlier run are kept as they are.
+    let pending: Vec<(&Chapter, &PathBuf)> = chapters
+        .iter()
+        .zip(&output_paths)
+        .filter(|(_, path)| {
+            !options.resume || fs::metadata(path).map_or(true, |metadata| metadata.len() == 0)
+        })
+        .collect();
+
+    let completed = chapters.len() - pending.len();
+    if completed > 0 {
+        status!(
+            "Resuming: {} of {} chapters already done",
+            completed,
+            chapters.len()
+        );
+    }
+
+    let pb = progress::bar(chapters.len());
+    pb.set_position(completed as u64);
+
+    let jobs = match options.jobs {
+        0 => std::thread::available_parallelism().map_or(1, |n| n.get()),
+        jobs => jobs,
+    };
+
+    let mut results: Vec<ChapterResult> = stream::iter(pending)
+        .map(|(chapter, output_path)| {
+            let pb = &pb;
+            async move {
+                pb.set_message(format!("Processing: {}", chapter.title));
+                progress::emit(Event::ChapterStart {
+                    index: chapter.number,
+                    title: &chapter.title,
+                    total: chapters.len(),
+                });
+                let started = Instant::now();
+                let attempted_at = SystemTime::now();
+
+                let result = split_chapter(video_path, chapter, output_path, options).await;
+                pb.inc(1);
+                match &result {
+                    Ok(()) => {
+                        if let Some(record) = &options.record {
+                            record.clip(chapter, output_path);
+                        }
+                        if let Some(hook) = &options.chapter_hook {
+                            hook.run(chapter, output_path).await;
+                        }
+                        progress::emit(Event::ChapterDone {
+                            index: chapter.number,
+                            elapsed_ms: started.elapsed().as_millis(),
+                        })
+                    }
+                    Err(e) => progress::emit(Event::Error {
+                        message: format!("{}: {:#}", chapter.title, e),
+                    }),
+                }
+
+                if let Some(sleep) = options.sleep {
+                    tokio::time::sleep(sleep).await;
+                }
+
+                match result {
+                    Ok(()) => ChapterResult::Success {
+                        number: chapter.number,
+                        chapter_title: chapter.title.clone(),
+                        path: output_path.clone(),
+                        duration: (chapter.end_time - chapter.start_time)
+                            / options.speed.for_chapter(chapter),
+                    },
+                    Err(e) => ChapterResult::Failure {
+                        number: chapter.number,
+                        chapter_title: chapter.title.clone(),
+                        error: format!("{:#}", e),
+                        command: e
+                            .chain()
+                            .find_map(|cause| cause.downcast_ref::<CommandError>())
+                            .map(|e| e.command_line.clone()),
+                        attempted_at,
+                    },
+                }
+            }
+        })
+        .buffer_unordered(jobs)
+        .collect()
+        .await;
+    results.sort_by_key(ChapterResult::number);
+
+    let failures: Vec<&ChapterResult> = results.iter().filter(|r| r.is_failure()).collect();
+    if failures.is_empty() {
+        pb.finish_with_message("All chapters processed");
+        return Ok(results);
+    }
+
+    pb.finish_and_clear();
+    if !options.continue_on_error {
+        let failures: Vec<String> = failures
+            .iter()
+            .map(|failure| {
+                format!(
+                    "{}: {}",
+                    failure.chapter_title(),
+                    failure.error().unwrap_or_default()
+                )
+            })
+            .collect();
+        anyhow::bail!(
+            "Failed to split {} of {} chapters:\n  {}",
+            failures.len(),
+            chapters.len(),
+            failures.join("\n  ")
+        );
+    }
+
+    for failure in &failures {
+        estatus!(
+            "Warning: chapter {} ({}) failed, continuing: {}",
+            failure.number(),
+            failure.chapter_title(),
+            failure.error().unwrap_or_default()
+        );
+    }
+
+    Ok(results)
+}
+
+/// Writes the `--continue-on-error` report of `results`, only warning if
+/// that fails so the clips that did split are still reported.
+fn write_processing_report(output_dir: &Path, results: &[ChapterResult], started: Instant) {
+    match report::write_report(output_dir, results, started.elapsed()) {
+        Ok(path) => status!("\nProcessing report written to {}", path.display()),
+        Err(e) => estatus!("Warning: {:#}", e),
+    }
+}
+
+/// The `--continue-on-error` summary line, e.g.
+/// "2/3 chapters succeeded. Failed: Chapter 3 (error)."
+fn failure_summary(results: &[ChapterResult], total: usize) -> String {
+    let failed: Vec<String> = results
+        .iter()
+        .filter_map(|result| {
+            let error = result.error()?;
+            Some(format!("Chapter {} ({})", result.number(), error))
+        })
+        .collect();
+    format!(
+        "{}/{} chapters succeeded. Failed: {}.",
+        total - failed.len(),
+        total,
+        failed.join(", ")
+    )
+}
+
+async fn split_chapter(
+    video_path: &Path,
+    chapter: &Chapter,
+    output_path: &Path,
+    options: &SplitOptions<'_>,
+) -> Result<()> {
+    create_parent_dir(output_path)?;
+
+    let ranges =
+        sponsorblock::keep_ranges(chapter.start_time, chapter.end_time, options.skip_segments);
+    let metadata_args = options
+        .metadata
+        .map(|metadata| metadata.clip_args(chapter))
+        .unwrap_or_default();
+
+    let speed = options.speed.for_chapter(chapter);
+
+    match ranges.as_slice() {
+        [] => anyhow::bail!("Chapter is entirely covered by skipped segments"),
+        [(start, end)] => {
+            cut_segment(
+                video_path,
+                Segment {
+                    start: *start,
+                    end: *end,
+                    edges: ClipEdges::BOTH,
+                    speed,
+                },
+                output_path,
+                options,
+                &metadata_args,
+            )
+            .await
+        }
+        pieces => {
+            // Cut around the skipped segments, then join the pieces back together.
+            let temp_dir = tempfile::tempdir().context("Failed to create temporary directory")?;
+            let extension = options.container.extension();
+
+            let mut piece_paths = Vec::new();
+            for (i, (start, end)) in pieces.iter().enumerate() {
+                let piece_path = temp_dir.path().join(format!("piece_{}.{}", i, extension));
+                let segment = Segment {
+                    start: *start,
+                    end: *end,
+                    edges: ClipEdges {
+                        start: i == 0,
+                        end: i == pieces.len() - 1,
+                    },
+                    speed,
+                };
+                cut_segment(video_path, segment, &piece_path, options, &[]).await?;
+                piece_paths.push(piece_path);
+            }
+
+            concat_files_async(
+                options.executables,
+                &piece_paths,
+                output_path,
+                &metadata_args,
+            )
+            .await
+        }
+    }
+}
+
+/// Which ends of a cut segment are also the ends of its clip, and so get
+/// faded with `--fade-duration`.
+#[derive(Clone, Copy)]
+struct ClipEdges {
+    start: bool,
+    end: bool,
+}
+
+impl ClipEdges {
+    const BOTH: ClipEdges = ClipEdges {
+        start: true,
+        end: true,
+    };
+}
+
+/// A time range of the source video that becomes (part of) a clip.
+#[derive(Clone, Copy)]
+struct Segment {
+    start: f64,
+    end: f64,
+    edges: ClipEdges,
+    /// Playback speed multiplier from `--speed` or `--speed-map`.
+    speed: f64,
+}
+
+/// How far before a re-encoded clip's start ffmpeg seeks to on the input.
+/// Only this preroll is decoded and dropped to reach the exact frame, instead
+/// of everything from the start of the video.
+const SEEK_PREROLL: f64 = 5.0;
+
+/// `fade` (video) or `afade` (audio) filters for a segment from `start` to
+/// `end`, in the timestamps the filters see: seconds from the input seek
+/// point, since timestamps restart at zero there.
+fn fade_filters(filter: &str, start: f64, end: f64, fade: f64, edges: ClipEdges) -> Option<String> {
+    let fade = fade.min((end - start) / 2.0);
+    let mut filters = Vec::new();
+    if edges.start {
+        filters.push(format!("{}=t=in:st={:.3}:d={:.3}", filter, start, fade));
+    }
+    if edges.end {
+        filters.push(format!(
+            "{}=t=out:st={:.3}:d={:.3}",
+            filter,
+            end - fade,
+            fade
+        ));
+    }
+    (!filters.is_empty()).then(|| filters.join(","))
+}
+
+async fn cut_segment(
+    video_path: &Path,
+    segment: Segment,
+    output_path: &Path,
+    options: &SplitOptions<'_>,
+    output_args: &[String],
+) -> Result<()> {
+    let Segment {
+        start,
+        end,
+        edges,
+        speed,
+    } = segment;
+    let encode = options.encode;
+
+    // Seeking before an input jumps straight to the nearest keyframe, which is
+    // all a stream copy can start on. When re-encoding, seek coarsely to a
+    // little before the start, then drop the decoded preroll with an output
+    // seek so the clip starts on the exact frame.
+    let input_seek = match encode {
+        Some(_) => (start - SEEK_PREROLL).max(0.0),
+        None => start,
+    };
+    let preroll = start - input_seek;
+    let input_seek_args = ["-ss".to_string(), format!("{:.3}", input_seek)];
+
+    let mut command = options.executables.ffmpeg();
+    command
+        .args(encode.map(EncodeSettings::input_args).unwrap_or_default())
+        .args(&input_seek_args)
+        .args(["-i", video_path.to_str().unwrap()]);
+
+    let (local_start, local_end) = (preroll, preroll + end - start);
+    let video_fade = options
+        .fade
+        .and_then(|fade| fade_filters("fade", local_start, local_end, fade, edges));
+    let audio_fade = options
+        .fade
+        .filter(|_| options.fade_audio)
+        .and_then(|fade| fade_filters("afade", local_start, local_end, fade, edges));
+
+    let speed_filters =
+        (speed != 1.0).then(|| (speed::video_filter(speed), speed::audio_filter(speed)));
+
+    let codec_args = match (options.embed_subtitles, encode) {
+        (subtitles, Some(encode))
+            if subtitles.is_some() || video_fade.is_some() || speed_filters.is_some() =>
+        {
+            let (video_speed, audio_speed) = speed_filters.unzip();
+            // Subtitles are timed from the start of the video, so they are
+            // burned in while the frames carry source timestamps.
+            let filter: Vec<String> = subtitles
+                .map(|subtitles| {
+                    format!(
+                        "setpts=PTS+{0:.3}/TB,{1},setpts=PTS-{0:.3}/TB",
+                        input_seek,
+                        subtitles::burn_filter(subtitles)
+                    )
+                })
+                .into_iter()
+                .chain(video_fade)
+                .chain(video_speed)
+                .collect();
+            let mut codec_args = encode.video_args(Some(&filter.join(",")));
+            let audio_filter: Vec<String> = audio_fade.into_iter().chain(audio_speed).collect();
+            let audio_filter = audio_filter.join(",");
+            codec_args
+                .extend(encode.audio_args(Some(audio_filter.as_str()).filter(|f| !f.is_empty())));
+            codec_args
+        }
+        (Some(subtitles), None) => {
+            command
+                .args(&input_seek_args)
+                .args(["-i", subtitles.to_str().unwrap()]);
+            subtitles::soft_track_args(options.container.subtitle_codec(), options.audio_tracks)
+        }
+        (_, None) => match options.resample_audio {
+            Some(audio) => encode::copy_video_args(audio),
+            None => encode::clip_codec_args(None),
+        },
+        (_, encode) => encode::clip_codec_args(encode),
+    };
+
+    // Output seeking applies after the filters, so with --speed it has to
+    // use the sped up timestamps.
+    if encode.is_some() {
+        command.args(["-ss", &format!("{:.3}", preroll / speed)]);
+    }
+    // Soft subtitles map their own streams.
+    if let Some(tracks) = options
+        .audio_tracks
+        .filter(|_| encode.is_some() || options.embed_subtitles.is_none())
+    {
+        command.args(tracks::map_args(tracks));
+    }
+    command
+        .args(["-t", &format!("{:.3}", (end - start) / speed)])
+        .args(codec_args)
+        .args(output_args)
+        .args(&options.executables.ffmpeg_extra_args);
+
+    // The first pass only writes its statistics to the log, which the
+    // second pass reads back. Each clip gets its own log so parallel jobs
+    // don't share one.
+    let _pass_log = if options.two_pass && encode.is_some() {
+        let dir = tempfile::tempdir().context("Failed to create temporary directory")?;
+        let log = dir.path().join("ffmpeg2pass");
+        let mut first_pass = options.executables.ffmpeg();
+        first_pass
+            .args(command.get_args())
+            .args(["-pass", "1", "-passlogfile", log.to_str().unwrap()])
+            .args(["-f", "null", "-y", "-"]);
+        options.executables.run_async(first_pass).await?;
+        command.args(["-pass", "2", "-passlogfile", log.to_str().unwrap()]);
+        Some(dir)
+    } else {
+        None
+    };
+
+    command.args(["-y", output_path.to_str().unwrap()]);
+    options.executables.run_async(command).await
+}
+
+/// Combines already split chapter clips into one clip.
+fn merge_chapters(executables: &Executables, clips: &[PathBuf], output: &Path) -> Result<()> {
+    concat_files(executables, clips, output, None, &[])
+        .with_context(|| format!("Failed to merge clips into {}", output.display()))
+}
+
+/// Copies `video_path` to `output` with `chapters` as its chapter marks,
+/// replacing any the video already has.
+fn embed_chapter_marks(
+    executables: &Executables,
+    video_path: &Path,
+    title: &str,
+    chapters: &[Chapter],
+    output: &Path,
+) -> Result<()> {
+    let metadata_file = tempfile::Builder::new()
+        .suffix(".meta")
+        .tempfile()
+        .context("Failed to create chapters metadata file")?;
+    fs::write(metadata_file.path(), metadata::ffmetadata(title, chapters))
+        .context("Failed to write chapters metadata file")?;
+
+    executables
+        .run(executables.ffmpeg().args([
+            "-i",
+            video_path.to_str().unwrap(),
+            "-i",
+            metadata_file.path().to_str().unwrap(),
+            "-map",
+            "0",
+            "-map_metadata",
+            "1",
+            "-map_chapters",
+            "1",
+            "-c",
+            "copy",
+            "-y",
+            output.to_str().unwrap(),
+        ]))
+        .with_context(|| format!("Failed to write {}", output.display()))
+}
+
+/// Joins `inputs` into `output` with ffmpeg's concat demuxer. Without `encode`
+/// the streams are copied, so all inputs must share the same codecs.
+/// `output_args` are added just before the output path.
+pub(crate) fn concat_files(
+    executables: &Executables,
+    inputs: &[PathBuf],
+    output: &Path,
+    encode: Option<&EncodeSettings>,
+    output_args: &[String],
+) -> Result<()> {
+    let list_file = concat_list(inputs)?;
+    executables
+        .run(&mut concat_command(
+            executables,
+            list_file.path(),
+            output,
+            encode,
+            output_args,
+        ))
+        .with_context(|| format!("Failed to concatenate {} files", inputs.len()))
+}
+
+/// Like `concat_files`, without blocking the thread.
+async fn concat_files_async(
+    executables: &Executables,
+    inputs: &[PathBuf],
+    output: &Path,
+    output_args: &[String],
+) -> Result<()> {
+    let list_file = concat_list(inputs)?;
+    let command = concat_command(executables, list_file.path(), output, None, output_args);
+    executables
+        .run_async(command)
+        .await
+        .with_context(|| format!("Failed to concatenate {} files", inputs.len()))
+}
+
+/// Writes the concat demuxer's list of `inputs` to a temporary file.
+fn concat_list(inputs: &[PathBuf]) -> Result<NamedTempFile> {
+    let list_file = tempfile::Builder::new()
+        .suffix(".txt")
+        .tempfile()
+        .context("Failed to create concat list")?;
+
+    let list: String = inputs
+        .iter()
+        .map(|path| {
+            let path = fs::canonicalize(path).unwrap_or_else(|_| path.clone());
+            format!("file '{}'\n", path.to_string_lossy().replace('\'', "'\\''"))
+        })
+        .collect();
+    fs::write(list_file.path(), list).context("Failed to write concat list")?;
+
+    Ok(list_file)
+}
+
+fn concat_command(
+    executables: &Executables,
+    list_file: &Path,
+    output: &Path,
+    encode: Option<&EncodeSettings>,
+    output_args: &[String],
+) -> Command {
+    let mut command = executables.ffmpeg();
+    command
+        .args([
+            "-f",
+            "concat",
+            "-safe",
+            "0",
+            "-i",
+            list_file.to_str().unwrap(),
+        ])
+        .args(match encode {
+            Some(encode) => encode.ffmpeg_args(),
+            None => vec!["-c".to_string(), "copy".to_string()],
+        })
+        .args(output_args)
+        .args(&executables.ffmpeg_extra_args)
+        .args(["-y", output.to_str().unwrap()]);
+    command
+}
+
+pub(crate) fn create_parent_dir(path: &Path) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create directory {}", parent.display()))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Compares the settings that come from defaults, leaving out the program
+    /// paths and proxy, which come from the environment.
+    #[test]
+    fn clipper_config_new_matches_command_line_defaults() {
+        let args = Args::try_parse_from(["yt-clipper", "--print-config"]).unwrap();
+        let (new, parsed) = (
+            ClipperConfig::new().unwrap(),
+            ClipperConfig::from_args(&args).unwrap(),
+        );
+
+        assert_eq!(new.format, parsed.format);
+        assert_eq!(new.container, parsed.container);
+        assert_eq!(new.reencode, parsed.reencode);
+        assert_eq!(new.template, parsed.template);
+        assert_eq!(new.jobs, parsed.jobs);
+        assert_eq!(new.continue_on_error, parsed.continue_on_error);
+
+        let (executables, parsed_executables) = (&new.executables, &parsed.executables);
+        assert_eq!(
+            executables.network.fragment_retries,
+            parsed_executables.network.fragment_retries
+        );
+        assert_eq!(
+            executables.ffmpeg_extra_args,
+            parsed_executables.ffmpeg_extra_args
+        );
+        assert_eq!(
+            executables.ytdlp_extra_args,
+            parsed_executables.ytdlp_extra_args
+        );
+        assert_eq!(executables.verbose, parsed_executables.verbose);
+
+        let (encode, parsed_encode) = (&new.encode, &parsed.encode);
+        assert_eq!(encode.video_codec, parsed_encode.video_codec);
+        assert_eq!(encode.audio_codec, parsed_encode.audio_codec);
+        assert_eq!(encode.crf, parsed_encode.crf);
+        assert_eq!(encode.preset, parsed_encode.preset);
+        assert_eq!(encode.audio_bitrate, parsed_encode.audio_bitrate);
+        assert_eq!(encode.hwaccel, parsed_encode.hwaccel);
+        assert_eq!(encode.tone_map, parsed_encode.tone_map);
+        assert!(parsed_encode.watermark.is_none() && parsed_encode.scale.is_none());
+
+        let (filenames, parsed_filenames) = (&new.filenames, &parsed.filenames);
+        assert_eq!(filenames.mode, parsed_filenames.mode);
+        assert_eq!(filenames.numbering, parsed_filenames.numbering);
+        assert_eq!(filenames.max_length, parsed_filenames.max_length);
+        assert_eq!(filenames.word_separator, parsed_filenames.word_separator);
+
+        let (formats, parsed_formats) = (&new.formats, &parsed.formats);
+        assert_eq!(formats.vertical_gravity, parsed_formats.vertical_gravity);
+        assert_eq!(formats.gif_fps, parsed_formats.gif_fps);
+        assert_eq!(formats.gif_width, parsed_formats.gif_width);
+        assert_eq!(formats.container, parsed_formats.container);
+        assert_eq!(formats.audio.format, parsed_formats.audio.format);
+        assert_eq!(formats.audio.bitrate, parsed_formats.audio.bitrate);
+    }
+}
@@ -0,0 +1,56 @@
+use anyhow::{Context, Result};
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+
+static LOG_FILE: OnceLock<Mutex<File>> = OnceLock::new();
+
+/// Starts copying all messages and subprocess output into `path`.
+pub fn init(path: &Path) -> Result<()> {
+    let file = File::create(path)
+        .with_context(|| format!("Failed to create log file {}", path.display()))?;
+    let _ = LOG_FILE.set(Mutex::new(file));
+    Ok(())
+}
+
+pub fn is_enabled() -> bool {
+    LOG_FILE.get().is_some()
+}
+
+/// Appends `buf` to the log file, if there is one.
+pub fn write(buf: &[u8]) {
+    if let Some(file) = LOG_FILE.get() {
+        let _ = file.lock().unwrap().write_all(buf);
+    }
+}
+
+/// A writer that passes everything through to `inner` and copies it into
+/// the log file.
+pub struct Tee<W> {
+    inner: W,
+}
+
+impl<W: Write> Write for Tee<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        write(&buf[..written]);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+pub fn stdout() -> Tee<io::Stdout> {
+    Tee {
+        inner: io::stdout(),
+    }
+}
+
+pub fn stderr() -> Tee<io::Stderr> {
+    Tee {
+        inner: io::stderr(),
+    }
+}
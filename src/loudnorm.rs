@@ -0,0 +1,129 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::encode::EncodeSettings;
+use crate::executables::Executables;
+use crate::progress;
+
+/// Loudness range and true peak targets used alongside the integrated loudness target.
+const TARGET_LRA: f64 = 7.0;
+const TARGET_TP: f64 = -2.0;
+
+/// Values measured by the first `loudnorm` pass. ffmpeg prints them as strings.
+#[derive(Debug, Deserialize)]
+struct Measurement {
+    input_i: String,
+    input_lra: String,
+    input_tp: String,
+    input_thresh: String,
+    target_offset: String,
+}
+
+/// Normalizes the audio of every clip in place using two-pass EBU R128
+/// loudness normalization. The video stream is copied, audio is re-encoded.
+pub fn normalize_clips(
+    executables: &Executables,
+    clips: &[PathBuf],
+    target: f64,
+    audio: &EncodeSettings,
+) -> Result<()> {
+    let pb = progress::bar(clips.len());
+
+    for clip in clips {
+        let name = clip.file_name().unwrap_or_default().to_string_lossy();
+        pb.set_message(format!("Normalizing: {}", name));
+
+        if let Err(e) = normalize_clip(executables, clip, target, audio) {
+            pb.finish_and_clear();
+            return Err(e).with_context(|| format!("Failed to normalize audio of {}", name));
+        }
+
+        pb.inc(1);
+    }
+
+    pb.finish_with_message("Audio normalized");
+
+    Ok(())
+}
+
+fn normalize_clip(
+    executables: &Executables,
+    clip: &Path,
+    target: f64,
+    audio: &EncodeSettings,
+) -> Result<()> {
+    let measurement = measure(executables, clip, target)?;
+
+    let filter = format!(
+        "loudnorm=I={}:LRA={}:TP={}:measured_I={}:measured_LRA={}:measured_TP={}:measured_thresh={}:offset={}:linear=true",
+        target,
+        TARGET_LRA,
+        TARGET_TP,
+        measurement.input_i,
+        measurement.input_lra,
+        measurement.input_tp,
+        measurement.input_thresh,
+        measurement.target_offset
+    );
+
+    let mut tmp_name = clip.file_stem().unwrap_or_default().to_os_string();
+    tmp_name.push(".normalized.");
+    tmp_name.push(clip.extension().unwrap_or_default());
+    let tmp_path = clip.with_file_name(tmp_name);
+
+    let result = executables.run(
+        executables
+            .ffmpeg()
+            .args(["-i", clip.to_str().unwrap()])
+            .args(audio.audio_args(Some(&filter)))
+            .args(["-c:v", "copy", "-y", tmp_path.to_str().unwrap()]),
+    );
+
+    if let Err(e) = result {
+        let _ = fs::remove_file(&tmp_path);
+        return Err(e);
+    }
+
+    fs::rename(&tmp_path, clip).context("Failed to replace clip with normalized version")?;
+
+    Ok(())
+}
+
+/// Runs the measuring pass, which prints its results as JSON at the end of stderr.
+fn measure(executables: &Executables, clip: &Path, target: f64) -> Result<Measurement> {
+    let output = executables
+        .ffmpeg()
+        .args([
+            "-hide_banner",
+            "-i",
+            clip.to_str().unwrap(),
+            "-af",
+            &format!(
+                "loudnorm=I={}:LRA={}:TP={}:print_format=json",
+                target, TARGET_LRA, TARGET_TP
+            ),
+            "-f",
+            "null",
+            "-",
+        ])
+        .output()
+        .context("Failed to execute ffmpeg")?;
+
+    if !output.status.success() {
+        anyhow::bail!("Loudness measurement failed");
+    }
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let json = stderr
+        .rfind('{')
+        .and_then(|start| {
+            stderr[start..]
+                .find('}')
+                .map(|end| &stderr[start..=start + end])
+        })
+        .context("Loudness measurement produced no results")?;
+
+    serde_json::from_str(json).context("Failed to parse loudness measurement")
+}
@@ -0,0 +1,72 @@
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::Chapter;
+
+/// Writes an extended M3U playlist of `clips` to `output`, in the given
+/// order. Clips are listed relative to the playlist so the directory can be
+/// moved as a whole; clips that don't exist (such as skipped GIFs) are left
+/// out.
+pub fn write_m3u8_playlist(clips: &[(PathBuf, &Chapter)], output: &Path) -> Result<()> {
+    let base = output.parent().unwrap_or(Path::new(""));
+
+    let mut contents = String::from("#EXTM3U\n");
+    for (path, chapter) in clips {
+        if !path.exists() {
+            continue;
+        }
+        let entry = path.strip_prefix(base).unwrap_or(path);
+        contents.push_str(&format!(
+            "#EXTINF:{},{}\n{}\n",
+            (chapter.end_time - chapter.start_time).round() as u64,
+            chapter.title,
+            entry.to_string_lossy()
+        ));
+    }
+
+    fs::write(output, contents).with_context(|| format!("Failed to write {}", output.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chapter(title: &str, start_time: f64, end_time: f64) -> Chapter {
+        Chapter {
+            title: title.to_string(),
+            start_time,
+            end_time,
+            number: 0,
+        }
+    }
+
+    #[test]
+    fn lists_existing_clips_relative_to_the_playlist() {
+        let dir = tempfile::tempdir().unwrap();
+        let clips_dir = dir.path().join("clips");
+        fs::create_dir(&clips_dir).unwrap();
+        fs::write(clips_dir.join("01_Intro.mp4"), "").unwrap();
+        fs::write(clips_dir.join("03_Outro.mp4"), "").unwrap();
+
+        let (intro, missing, outro) = (
+            chapter("Intro", 0.0, 59.6),
+            chapter("Main", 59.6, 120.0),
+            chapter("Outro", 120.0, 150.2),
+        );
+        let clips = [
+            (clips_dir.join("01_Intro.mp4"), &intro),
+            (clips_dir.join("02_Main.gif"), &missing),
+            (clips_dir.join("03_Outro.mp4"), &outro),
+        ];
+        let output = dir.path().join("playlist.m3u8");
+        write_m3u8_playlist(&clips, &output).unwrap();
+
+        assert_eq!(
+            fs::read_to_string(&output).unwrap(),
+            "#EXTM3U\n\
+             #EXTINF:60,Intro\nclips/01_Intro.mp4\n\
+             #EXTINF:30,Outro\nclips/03_Outro.mp4\n"
+        );
+    }
+}
@@ -0,0 +1,177 @@
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::Chapter;
+
+/// Video-level details written into the tags of every clip with `--embed-metadata`.
+#[derive(Debug, Clone)]
+pub struct VideoMetadata {
+    pub title: String,
+    pub uploader: Option<String>,
+    pub upload_date: Option<String>,
+    /// Link back to the video, written to the `comment` tag.
+    pub url: String,
+}
+
+impl VideoMetadata {
+    /// `-metadata` arguments for a chapter clip.
+    pub fn clip_args(&self, chapter: &Chapter) -> Vec<String> {
+        let mut tags = vec![
+            ("title", chapter.title.clone()),
+            ("track", chapter.number.to_string()),
+            ("album", self.title.clone()),
+        ];
+        if let Some(uploader) = &self.uploader {
+            tags.push(("artist", uploader.clone()));
+        }
+        if let Some(date) = self.upload_date.as_deref().and_then(format_date) {
+            tags.push(("date", date));
+        }
+        tags.push(("comment", self.url.clone()));
+
+        to_args(tags)
+    }
+
+    /// `-metadata` arguments for an audio-only export, which music players
+    /// also group by album artist.
+    pub fn audio_args(&self, chapter: &Chapter) -> Vec<String> {
+        let mut args = self.clip_args(chapter);
+
+        let mut tags = Vec::new();
+        if let Some(uploader) = &self.uploader {
+            tags.push(("album_artist", uploader.clone()));
+        }
+        args.extend(to_args(tags));
+
+        args
+    }
+}
+
+/// How `--date-prefix` writes the upload date.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DateFormat {
+    /// 20240315
+    Ymd,
+    /// 2024-03-15
+    Iso,
+    /// Seconds since 1970 at midnight UTC, e.g. 1710460800
+    Unix,
+}
+
+/// Formats yt-dlp's `YYYYMMDD` upload date as `YYYY-MM-DD`.
+pub fn format_date(date: &str) -> Option<String> {
+    format_date_as(date, DateFormat::Iso)
+}
+
+/// Formats yt-dlp's `YYYYMMDD` upload date in `format`, or `None` if `date`
+/// isn't a valid date.
+pub fn format_date_as(date: &str, format: DateFormat) -> Option<String> {
+    if date.len() != 8 || !date.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let year: i64 = date[..4].parse().ok()?;
+    let month: i64 = date[4..6].parse().ok()?;
+    let day: i64 = date[6..].parse().ok()?;
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+
+    Some(match format {
+        DateFormat::Ymd => date.to_string(),
+        DateFormat::Iso => format!("{}-{}-{}", &date[..4], &date[4..6], &date[6..]),
+        DateFormat::Unix => (days_since_epoch(year, month, day) * 86_400).to_string(),
+    })
+}
+
+/// Days from 1970-01-01 to the given date in the proleptic Gregorian
+/// calendar (Howard Hinnant's `days_from_civil`).
+fn days_since_epoch(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// An FFMETADATA file with `title` and a chapter mark for every chapter, for
+/// use as a second ffmpeg input with `-map_metadata 1 -map_chapters 1`.
+pub fn ffmetadata(title: &str, chapters: &[Chapter]) -> String {
+    let mut metadata = format!(";FFMETADATA1\ntitle={}\n", escape_ffmetadata(title));
+    for chapter in chapters {
+        metadata.push_str(&format!(
+            "\n[CHAPTER]\nTIMEBASE=1/1000\nSTART={}\nEND={}\ntitle={}\n",
+            (chapter.start_time * 1000.0).round() as u64,
+            (chapter.end_time * 1000.0).round() as u64,
+            escape_ffmetadata(&chapter.title)
+        ));
+    }
+    metadata
+}
+
+/// Escapes the characters FFMETADATA gives a special meaning.
+fn escape_ffmetadata(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '=' | ';' | '#' | '\\' | '\n') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+fn to_args(tags: Vec<(&str, String)>) -> Vec<String> {
+    tags.into_iter()
+        .flat_map(|(key, value)| ["-metadata".to_string(), format!("{}={}", key, value)])
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_upload_dates() {
+        assert_eq!(format_date("20240315").as_deref(), Some("2024-03-15"));
+        assert_eq!(
+            format_date_as("20240315", DateFormat::Ymd).as_deref(),
+            Some("20240315")
+        );
+        assert_eq!(
+            format_date_as("20240315", DateFormat::Unix).as_deref(),
+            Some("1710460800")
+        );
+        assert_eq!(
+            format_date_as("19700101", DateFormat::Unix).as_deref(),
+            Some("0")
+        );
+        assert_eq!(
+            format_date_as("20000301", DateFormat::Unix).as_deref(),
+            Some("951868800")
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_upload_dates() {
+        for date in ["", "2024-03-15", "20241315", "20240300", "2024031"] {
+            assert_eq!(format_date(date), None, "{} was accepted", date);
+        }
+    }
+
+    #[test]
+    fn writes_ffmetadata_chapter_marks() {
+        let chapters = [Chapter {
+            title: "Q&A; part=1".to_string(),
+            start_time: 0.0,
+            end_time: 61.2345,
+            number: 1,
+        }];
+        assert_eq!(
+            ffmetadata("Talk #1", &chapters),
+            ";FFMETADATA1\ntitle=Talk \\#1\n\
+             \n[CHAPTER]\nTIMEBASE=1/1000\nSTART=0\nEND=61235\ntitle=Q&A\\; part\\=1\n"
+        );
+    }
+}
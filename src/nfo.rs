@@ -0,0 +1,159 @@
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::executables::Executables;
+use crate::metadata::format_date;
+use crate::probe::{self, MediaInfo};
+use crate::{progress, Chapter};
+
+/// Video-level details written into every clip's NFO file.
+pub struct NfoVideo<'a> {
+    pub title: &'a str,
+    pub uploader: Option<&'a str>,
+    /// yt-dlp's `YYYYMMDD` upload date.
+    pub upload_date: Option<&'a str>,
+    pub description: Option<&'a str>,
+}
+
+/// A clip to describe, with its `--thumbnails` image if there is one.
+pub struct NfoClip<'a> {
+    pub path: PathBuf,
+    pub chapter: &'a Chapter,
+    pub thumbnail: Option<PathBuf>,
+}
+
+/// Writes a Kodi-style `<movie>` NFO file next to every clip, which Kodi,
+/// Jellyfin and Plex read for titles, dates and artwork. Stream details come
+/// from probing the finished clip and are left out if that fails.
+pub fn write_nfo_files(
+    executables: &Executables,
+    clips: &[NfoClip],
+    video: &NfoVideo,
+) -> Result<()> {
+    let pb = progress::bar(clips.len());
+
+    for clip in clips {
+        pb.set_message(format!("NFO: {}", clip.chapter.title));
+
+        let info = probe::probe(executables, &clip.path).ok();
+        let output = clip.path.with_extension("nfo");
+        let contents = movie_nfo(clip, video, info.as_ref(), &output);
+
+        if let Err(e) = fs::write(&output, contents) {
+            pb.finish_and_clear();
+            return Err(e).with_context(|| format!("Failed to write {}", output.display()));
+        }
+
+        pb.inc(1);
+    }
+
+    pb.finish_with_message("All NFO files written");
+
+    Ok(())
+}
+
+fn movie_nfo(clip: &NfoClip, video: &NfoVideo, info: Option<&MediaInfo>, output: &Path) -> String {
+    let chapter = clip.chapter;
+    let duration = info
+        .and_then(MediaInfo::duration)
+        .unwrap_or(chapter.end_time - chapter.start_time);
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n");
+    xml.push_str("<movie>\n");
+    push_element(&mut xml, 1, "title", &chapter.title);
+    if let Some(plot) = video.description {
+        push_element(&mut xml, 1, "plot", plot);
+    }
+    // Kodi reads the runtime in whole minutes.
+    let minutes = (duration / 60.0).ceil().max(1.0) as u64;
+    push_element(&mut xml, 1, "runtime", &minutes.to_string());
+    if let Some(thumbnail) = &clip.thumbnail {
+        let from = output.parent().unwrap_or(Path::new(""));
+        let path = relative_path(thumbnail, from);
+        push_element(&mut xml, 1, "thumb", &path.to_string_lossy());
+    }
+    push_element(&mut xml, 1, "studio", video.title);
+    if let Some(uploader) = video.uploader {
+        push_element(&mut xml, 1, "director", uploader);
+    }
+    if let Some(date) = video.upload_date.and_then(format_date) {
+        push_element(&mut xml, 1, "premiered", &date);
+    }
+    if let Some(info) = info {
+        push_stream_details(&mut xml, info, duration);
+    }
+    xml.push_str("</movie>\n");
+
+    xml
+}
+
+fn push_stream_details(xml: &mut String, info: &MediaInfo, duration: f64) {
+    xml.push_str("  <fileinfo>\n    <streamdetails>\n");
+
+    if let Some(codec) = info.video_codec() {
+        xml.push_str("      <video>\n");
+        push_element(xml, 4, "codec", codec);
+        if let Some((width, height)) = info.resolution() {
+            push_element(xml, 4, "width", &width.to_string());
+            push_element(xml, 4, "height", &height.to_string());
+            if height > 0 {
+                let aspect = f64::from(width) / f64::from(height);
+                push_element(xml, 4, "aspect", &format!("{:.2}", aspect));
+            }
+        }
+        push_element(
+            xml,
+            4,
+            "durationinseconds",
+            &(duration.round() as u64).to_string(),
+        );
+        xml.push_str("      </video>\n");
+    }
+
+    if let Some(codec) = info.audio_codec() {
+        xml.push_str("      <audio>\n");
+        push_element(xml, 4, "codec", codec);
+        if let Some(channels) = info.channels() {
+            push_element(xml, 4, "channels", &channels.to_string());
+        }
+        xml.push_str("      </audio>\n");
+    }
+
+    xml.push_str("    </streamdetails>\n  </fileinfo>\n");
+}
+
+fn push_element(xml: &mut String, depth: usize, name: &str, value: &str) {
+    xml.push_str(&format!(
+        "{}<{}>{}</{}>\n",
+        "  ".repeat(depth),
+        name,
+        escape(value),
+        name
+    ));
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// `path` relative to the directory `from`, assuming both are relative to
+/// the same base (or both absolute).
+fn relative_path(path: &Path, from: &Path) -> PathBuf {
+    let path: Vec<_> = path.components().collect();
+    let from: Vec<_> = from.components().collect();
+    let common = path.iter().zip(&from).take_while(|(a, b)| a == b).count();
+
+    let mut relative = PathBuf::new();
+    for _ in common..from.len() {
+        relative.push("..");
+    }
+    for component in &path[common..] {
+        relative.push(component);
+    }
+    relative
+}
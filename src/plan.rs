@@ -0,0 +1,148 @@
+use serde::Serialize;
+use std::path::PathBuf;
+
+use crate::format_size;
+
+/// Everything a run would do for one video, produced instead of running
+/// yt-dlp and ffmpeg when `--dry-run` is set.
+#[derive(Debug, Serialize)]
+pub struct Plan {
+    pub video_id: String,
+    pub video_title: String,
+    pub output_dir: PathBuf,
+    pub download_section: Option<(f64, f64)>,
+    pub total_duration: f64,
+    pub clips: Vec<PlannedClip>,
+    pub format_variants: Vec<PlannedVariant>,
+    pub thumbnails: Option<PlannedVariant>,
+    pub keep_full: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PlannedClip {
+    pub number: usize,
+    pub title: String,
+    pub start_time: f64,
+    pub end_time: f64,
+    pub duration: f64,
+    /// Estimated from the source bitrate, shown with `--info`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub estimated_size: Option<u64>,
+    pub output: PathBuf,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PlannedVariant {
+    pub name: String,
+    pub directory: PathBuf,
+    pub extension: String,
+}
+
+impl Plan {
+    pub fn print(&self) {
+        println!("Dry run for: {} [{}]\n", self.video_title, self.video_id);
+        println!("Output directory: {}", self.output_dir.display());
+
+        if let Some((start, end)) = self.download_section {
+            println!("Download section: {:.3}s - {:.3}s", start, end);
+        }
+
+        println!("\nClips ({}):", self.clips.len());
+        for clip in &self.clips {
+            let size = clip
+                .estimated_size
+                .map(|size| format!(", ~{}", format_size(size)))
+                .unwrap_or_default();
+            println!(
+                "  {:>3}. {} ({:.1}s - {:.1}s, {:.1}s{})\n       -> {}",
+                clip.number,
+                clip.title,
+                clip.start_time,
+                clip.end_time,
+                clip.duration,
+                size,
+                clip.output.display()
+            );
+        }
+
+        println!("\nTotal duration: {:.1}s", self.total_duration);
+
+        if self.format_variants.is_empty() {
+            println!("Format variants: none");
+        } else {
+            println!("Format variants:");
+            for variant in &self.format_variants {
+                println!(
+                    "  - {} (*.{}) -> {}",
+                    variant.name,
+                    variant.extension,
+                    variant.directory.display()
+                );
+            }
+        }
+
+        if let Some(thumbnails) = &self.thumbnails {
+            println!(
+                "Thumbnails (*.{}) -> {}",
+                thumbnails.extension,
+                thumbnails.directory.display()
+            );
+        }
+
+        if !self.keep_full {
+            println!("\nThe full video would be removed after processing");
+        }
+    }
+}
+
+/// A chapter as shown by `--list-chapters`, along with whether the current
+/// selection and duration limits would process it.
+#[derive(Debug, Serialize)]
+pub struct ListedChapter {
+    pub number: usize,
+    pub title: String,
+    pub start_time: f64,
+    pub end_time: f64,
+    pub duration: f64,
+    pub selected: bool,
+    /// Why the chapter would be skipped despite being selected.
+    pub skip_reason: Option<String>,
+}
+
+/// Prints `chapters` as a table with human-readable times.
+pub fn print_chapter_table(chapters: &[ListedChapter]) {
+    println!(
+        "{:>4}  {:<12}  {:<12}  {:<12}  Title",
+        "#", "Start", "End", "Duration"
+    );
+
+    for chapter in chapters {
+        let note = match (&chapter.skip_reason, chapter.selected) {
+            (_, false) => " [not selected]".to_string(),
+            (Some(reason), true) => format!(" [skipped: {}]", reason),
+            (None, true) => String::new(),
+        };
+
+        println!(
+            "{:>4}  {}  {}  {}  {}{}",
+            chapter.number,
+            format_timestamp(chapter.start_time),
+            format_timestamp(chapter.end_time),
+            format_timestamp(chapter.duration),
+            chapter.title,
+            note
+        );
+    }
+}
+
+/// Formats `seconds` as `HH:MM:SS.mmm`.
+pub fn format_timestamp(seconds: f64) -> String {
+    let millis = (seconds.max(0.0) * 1000.0).round() as u64;
+    format!(
+        "{:02}:{:02}:{:02}.{:03}",
+        millis / 3_600_000,
+        millis / 60_000 % 60,
+        millis / 1000 % 60,
+        millis % 1000
+    )
+}
@@ -0,0 +1,196 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::path::Path;
+
+use crate::executables::Executables;
+use crate::format_size;
+
+/// The parts of `ffprobe -show_streams -show_format` output shown by `--info`.
+#[derive(Debug, Deserialize)]
+pub struct MediaInfo {
+    #[serde(default)]
+    streams: Vec<Stream>,
+    format: Option<Format>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Stream {
+    codec_type: Option<String>,
+    codec_name: Option<String>,
+    width: Option<u32>,
+    height: Option<u32>,
+    /// A fraction such as `30000/1001`.
+    avg_frame_rate: Option<String>,
+    sample_rate: Option<String>,
+    channels: Option<u32>,
+    #[serde(default)]
+    tags: StreamTags,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct StreamTags {
+    language: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Format {
+    duration: Option<String>,
+    size: Option<String>,
+}
+
+/// Runs ffprobe on `path`.
+pub fn probe(executables: &Executables, path: &Path) -> Result<MediaInfo> {
+    let output = executables
+        .ffprobe()
+        .args([
+            "-v",
+            "quiet",
+            "-print_format",
+            "json",
+            "-show_streams",
+            "-show_format",
+            path.to_str().unwrap(),
+        ])
+        .output()
+        .context("Failed to execute ffprobe")?;
+
+    if !output.status.success() {
+        anyhow::bail!("ffprobe exited with {}", output.status);
+    }
+
+    serde_json::from_slice(&output.stdout).context("Failed to parse ffprobe output")
+}
+
+impl MediaInfo {
+    pub fn print_summary(&self) {
+        status!("Source video:");
+
+        if let Some(video) = self.stream("video") {
+            let resolution = match (video.width, video.height) {
+                (Some(width), Some(height)) => format!("{}x{}", width, height),
+                _ => "unknown".to_string(),
+            };
+            let fps = video
+                .avg_frame_rate
+                .as_deref()
+                .and_then(parse_frame_rate)
+                .map(|fps| format!("{:.2} fps", fps))
+                .unwrap_or_else(|| "unknown fps".to_string());
+            status!(
+                "  Video: {}, {}, {}",
+                video.codec_name.as_deref().unwrap_or("unknown"),
+                resolution,
+                fps
+            );
+        }
+
+        let audio_streams: Vec<&Stream> = self.streams("audio").collect();
+        for (i, audio) in audio_streams.iter().enumerate() {
+            let label = if audio_streams.len() > 1 {
+                format!("Audio track {}", i)
+            } else {
+                "Audio".to_string()
+            };
+            let language = audio
+                .tags
+                .language
+                .as_deref()
+                .map(|language| format!(" ({})", language))
+                .unwrap_or_default();
+            status!(
+                "  {}: {}, {} Hz, {} channels{}",
+                label,
+                audio.codec_name.as_deref().unwrap_or("unknown"),
+                audio.sample_rate.as_deref().unwrap_or("?"),
+                audio
+                    .channels
+                    .map_or_else(|| "?".to_string(), |channels| channels.to_string()),
+                language
+            );
+        }
+
+        if let Some(format) = &self.format {
+            if let Some(duration) = format
+                .duration
+                .as_deref()
+                .and_then(|d| d.parse::<f64>().ok())
+            {
+                status!("  Duration: {:.1}s", duration);
+            }
+            if let Some(size) = format.size.as_deref().and_then(|s| s.parse::<u64>().ok()) {
+                status!("  Size: {}", format_size(size));
+            }
+        }
+
+        status!("");
+    }
+
+    /// Duration of the file in seconds.
+    pub fn duration(&self) -> Option<f64> {
+        self.format.as_ref()?.duration.as_deref()?.parse().ok()
+    }
+
+    /// Codec of the first video stream, e.g. `h264`.
+    pub fn video_codec(&self) -> Option<&str> {
+        self.stream("video")?.codec_name.as_deref()
+    }
+
+    /// Codec of the first audio stream, e.g. `aac`.
+    pub fn audio_codec(&self) -> Option<&str> {
+        self.stream("audio")?.codec_name.as_deref()
+    }
+
+    /// Channel count of the first audio stream.
+    pub fn channels(&self) -> Option<u32> {
+        self.stream("audio")?.channels
+    }
+
+    /// Width and height of the first video stream.
+    pub fn resolution(&self) -> Option<(u32, u32)> {
+        let video = self.stream("video")?;
+        Some((video.width?, video.height?))
+    }
+
+    /// Frame rate of the first video stream as ffprobe reports it, e.g. `30000/1001`.
+    pub fn frame_rate(&self) -> Option<&str> {
+        self.stream("video")?
+            .avg_frame_rate
+            .as_deref()
+            .filter(|rate| parse_frame_rate(rate).is_some_and(|fps| fps > 0.0))
+    }
+
+    /// Frame rate of the first video stream in frames per second.
+    pub fn fps(&self) -> Option<f64> {
+        self.frame_rate().and_then(parse_frame_rate)
+    }
+
+    /// Sample rate of the first audio stream in Hz.
+    pub fn sample_rate(&self) -> Option<&str> {
+        self.stream("audio")?.sample_rate.as_deref()
+    }
+
+    /// Language of every audio stream, in order.
+    pub fn audio_languages(&self) -> Vec<Option<String>> {
+        self.streams("audio")
+            .map(|stream| stream.tags.language.clone())
+            .collect()
+    }
+
+    fn stream(&self, codec_type: &str) -> Option<&Stream> {
+        self.streams
+            .iter()
+            .find(|stream| stream.codec_type.as_deref() == Some(codec_type))
+    }
+
+    fn streams<'a>(&'a self, codec_type: &'a str) -> impl Iterator<Item = &'a Stream> {
+        self.streams
+            .iter()
+            .filter(move |stream| stream.codec_type.as_deref() == Some(codec_type))
+    }
+}
+
+fn parse_frame_rate(rate: &str) -> Option<f64> {
+    let (num, den) = rate.split_once('/')?;
+    let (num, den): (f64, f64) = (num.parse().ok()?, den.parse().ok()?);
+    (den > 0.0).then(|| num / den)
+}
@@ -0,0 +1,83 @@
+use indicatif::{ProgressBar, ProgressStyle};
+use serde::Serialize;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::log;
+
+/// Set by `--json-progress`: progress is reported as JSON lines on stdout
+/// instead of progress bars.
+static JSON_PROGRESS: AtomicBool = AtomicBool::new(false);
+
+pub fn enable_json() {
+    JSON_PROGRESS.store(true, Ordering::Relaxed);
+}
+
+pub fn is_json() -> bool {
+    JSON_PROGRESS.load(Ordering::Relaxed)
+}
+
+/// A `--json-progress` event, written as one JSON object per line.
+#[derive(Debug, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event<'a> {
+    ChapterStart {
+        index: usize,
+        title: &'a str,
+        total: usize,
+    },
+    ChapterDone {
+        index: usize,
+        elapsed_ms: u128,
+    },
+    Error {
+        message: String,
+    },
+    Complete {
+        output_dir: PathBuf,
+        files: Vec<PathBuf>,
+    },
+}
+
+/// Writes `event` to stdout when `--json-progress` is active.
+pub fn emit(event: Event) {
+    if !is_json() {
+        return;
+    }
+    let mut line = serde_json::to_string(&event).expect("events always serialize");
+    line.push('\n');
+
+    let _ = log::stdout().write_all(line.as_bytes());
+}
+
+/// A progress bar in the standard style, hidden with `--json-progress`.
+pub fn bar(len: usize) -> ProgressBar {
+    if is_json() {
+        return ProgressBar::hidden();
+    }
+
+    let pb = ProgressBar::new(len as u64);
+    pb.set_style(
+        ProgressStyle::default_bar()
+            .template("[{elapsed_precise}] {bar:40.cyan/blue} {pos}/{len} {msg}")
+            .unwrap()
+            .progress_chars("=>-"),
+    );
+    pb
+}
+
+/// A spinner for work of unknown length, hidden with `--json-progress`.
+pub fn spinner() -> ProgressBar {
+    if is_json() {
+        return ProgressBar::hidden();
+    }
+
+    let pb = ProgressBar::new_spinner();
+    pb.set_style(
+        ProgressStyle::default_spinner()
+            .template("{spinner:.green} {msg}")
+            .unwrap(),
+    );
+    pb
+}
@@ -0,0 +1,34 @@
+use anyhow::{Context, Result};
+use std::env;
+use url::Url;
+
+/// Environment variables checked, in order, when no `--proxy` is given.
+const PROXY_ENV_VARS: &[&str] = &["HTTPS_PROXY", "https_proxy", "ALL_PROXY", "all_proxy"];
+
+/// The proxy given with `--proxy`, falling back to the proxy environment
+/// variables. Fails when the proxy isn't a valid URL.
+pub fn resolve(flag: Option<&str>) -> Result<Option<Url>> {
+    if let Some(proxy) = flag {
+        return parse(proxy)
+            .with_context(|| format!("Invalid --proxy URL \"{}\"", proxy))
+            .map(Some);
+    }
+
+    for var in PROXY_ENV_VARS {
+        if let Some(proxy) = env::var(var).ok().filter(|value| !value.is_empty()) {
+            return parse(&proxy)
+                .with_context(|| format!("Invalid proxy URL \"{}\" in ${}", proxy, var))
+                .map(Some);
+        }
+    }
+
+    Ok(None)
+}
+
+fn parse(proxy: &str) -> Result<Url> {
+    let url = Url::parse(proxy)?;
+    if url.host_str().is_none() {
+        anyhow::bail!("proxy URL has no host");
+    }
+    Ok(url)
+}
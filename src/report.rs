@@ -0,0 +1,97 @@
+use anyhow::{Context, Result};
+use serde::{Serialize, Serializer};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+pub const REPORT_FILE: &str = "processing_report.json";
+
+/// How splitting one chapter went.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum ChapterResult {
+    Success {
+        number: usize,
+        chapter_title: String,
+        path: PathBuf,
+        /// Length of the clip in seconds.
+        duration: f64,
+    },
+    Failure {
+        number: usize,
+        chapter_title: String,
+        error: String,
+        /// The command line of the ffmpeg run that failed, if one did.
+        command: Option<String>,
+        #[serde(serialize_with = "unix_seconds")]
+        attempted_at: SystemTime,
+    },
+}
+
+impl ChapterResult {
+    pub fn number(&self) -> usize {
+        match self {
+            ChapterResult::Success { number, .. } | ChapterResult::Failure { number, .. } => {
+                *number
+            }
+        }
+    }
+
+    pub fn chapter_title(&self) -> &str {
+        match self {
+            ChapterResult::Success { chapter_title, .. }
+            | ChapterResult::Failure { chapter_title, .. } => chapter_title,
+        }
+    }
+
+    /// The error of a failed chapter.
+    pub fn error(&self) -> Option<&str> {
+        match self {
+            ChapterResult::Success { .. } => None,
+            ChapterResult::Failure { error, .. } => Some(error),
+        }
+    }
+
+    pub fn is_failure(&self) -> bool {
+        matches!(self, ChapterResult::Failure { .. })
+    }
+}
+
+/// The `--continue-on-error` report written to `processing_report.json`.
+#[derive(Serialize)]
+struct ProcessingReport<'a> {
+    elapsed_seconds: f64,
+    succeeded: usize,
+    failed: usize,
+    chapters: &'a [ChapterResult],
+}
+
+/// Writes the outcome of every chapter split in this run to
+/// `processing_report.json` in `output_dir`.
+pub fn write_report(
+    output_dir: &Path,
+    results: &[ChapterResult],
+    elapsed: Duration,
+) -> Result<PathBuf> {
+    let failed = results.iter().filter(|result| result.is_failure()).count();
+    let report = ProcessingReport {
+        elapsed_seconds: elapsed.as_secs_f64(),
+        succeeded: results.len() - failed,
+        failed,
+        chapters: results,
+    };
+
+    let path = output_dir.join(REPORT_FILE);
+    let json = serde_json::to_string_pretty(&report)?;
+    fs::write(&path, json).with_context(|| format!("Failed to write {}", path.display()))?;
+
+    Ok(path)
+}
+
+fn unix_seconds<S: Serializer>(time: &SystemTime, serializer: S) -> Result<S::Ok, S::Error> {
+    let seconds = time
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    serializer.serialize_u64(seconds)
+}
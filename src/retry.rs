@@ -0,0 +1,50 @@
+use anyhow::Result;
+use std::time::Duration;
+use tokio::task;
+
+use crate::availability::Unavailable;
+
+/// How often and how patiently network operations are retried.
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    pub retries: u32,
+    /// Wait before the first retry; doubled for every retry after it.
+    pub base_delay: Duration,
+}
+
+impl RetryPolicy {
+    /// Runs the blocking `operation`, retrying it with exponential backoff
+    /// when it fails. `what` names the operation in messages, e.g. "Download".
+    /// Attempts run with [`task::block_in_place`] and the backoff is an async
+    /// sleep, so other tasks on the (multi-threaded) runtime keep going.
+    pub async fn run<T>(&self, what: &str, mut operation: impl FnMut() -> Result<T>) -> Result<T> {
+        let mut attempt = 0;
+        loop {
+            match task::block_in_place(&mut operation) {
+                Ok(value) => return Ok(value),
+                // Retrying won't make a private or deleted video available.
+                Err(e) if attempt < self.retries && e.downcast_ref::<Unavailable>().is_none() => {
+                    let delay = self.base_delay * 2u32.saturating_pow(attempt);
+                    estatus!(
+                        "{} failed (attempt {} of {}), retrying in {:.1}s: {:#}",
+                        what,
+                        attempt + 1,
+                        self.retries + 1,
+                        delay.as_secs_f64(),
+                        e
+                    );
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                Err(e) if attempt > 0 => {
+                    return Err(e.context(format!(
+                        "{} failed after {} attempts",
+                        what,
+                        attempt + 1
+                    )))
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
@@ -0,0 +1,79 @@
+use anyhow::{Context, Result};
+use std::path::Path;
+
+use crate::executables::Executables;
+use crate::Chapter;
+
+/// How different a frame must be from the previous one (0 to 1) to count as
+/// a scene change.
+const SCENE_THRESHOLD: f64 = 0.4;
+
+/// Splits a video without chapters at its scene changes, for
+/// `--auto-chapters`. Cuts closer than `min_duration` seconds to the previous
+/// one are ignored, so no chapter is shorter than that (unless the whole
+/// video is).
+pub fn auto_chapters(
+    executables: &Executables,
+    video: &Path,
+    duration: f64,
+    min_duration: f64,
+) -> Result<Vec<Chapter>> {
+    let cuts = detect_scene_changes(executables, video)?;
+
+    let mut starts = vec![0.0];
+    for cut in cuts {
+        if cut - starts.last().unwrap() >= min_duration {
+            starts.push(cut);
+        }
+    }
+    // Fold a too short final scene into the one before it.
+    if starts.len() > 1 && duration - starts.last().unwrap() < min_duration {
+        starts.pop();
+    }
+
+    let width = starts.len().to_string().len().max(2);
+    let ends = starts.iter().skip(1).copied().chain([duration]);
+    Ok(starts
+        .iter()
+        .zip(ends)
+        .enumerate()
+        .map(|(i, (&start_time, end_time))| Chapter {
+            title: format!("Scene {:0width$}", i + 1, width = width),
+            start_time,
+            end_time,
+            number: i + 1,
+        })
+        .collect())
+}
+
+/// Timestamps of the scene changes in `video`, which the `showinfo` filter
+/// prints to stderr as `pts_time:12.345` for every selected frame.
+fn detect_scene_changes(executables: &Executables, video: &Path) -> Result<Vec<f64>> {
+    let output = executables
+        .ffmpeg()
+        .args(["-hide_banner", "-i", video.to_str().unwrap(), "-an"])
+        .args([
+            "-vf",
+            &format!("select='gt(scene,{})',showinfo", SCENE_THRESHOLD),
+        ])
+        .args(["-vsync", "vfr", "-f", "null", "-"])
+        .output()
+        .context("Failed to execute ffmpeg")?;
+
+    if !output.status.success() {
+        anyhow::bail!("Scene detection failed");
+    }
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let mut cuts: Vec<f64> = stderr
+        .lines()
+        .filter(|line| line.contains("Parsed_showinfo"))
+        .filter_map(|line| {
+            let rest = &line[line.find("pts_time:")? + "pts_time:".len()..];
+            rest.split_whitespace().next()?.parse().ok()
+        })
+        .collect();
+    cuts.sort_by(f64::total_cmp);
+
+    Ok(cuts)
+}
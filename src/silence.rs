@@ -0,0 +1,230 @@
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::encode::{self, EncodeSettings};
+use crate::executables::Executables;
+use crate::progress;
+
+/// Silence shorter than this at a clip's very start or end still counts as
+/// touching the edge, since `silencedetect` timestamps are not frame exact.
+const EDGE_TOLERANCE: f64 = 0.05;
+
+/// `silencedetect` settings for `--trim-silence`.
+#[derive(Debug, Clone, Copy)]
+pub struct SilenceOptions {
+    /// Audio quieter than this many dB counts as silence.
+    pub threshold_db: f64,
+    /// Shortest stretch of quiet, in seconds, that counts as silence.
+    pub min_duration: f64,
+}
+
+/// Parses a `--silence-threshold` value such as `-40dB` or `-40`.
+pub fn parse_threshold(value: &str) -> Result<f64, String> {
+    let number = value
+        .strip_suffix("dB")
+        .or_else(|| value.strip_suffix("db"))
+        .unwrap_or(value);
+    match number.trim().parse::<f64>() {
+        Ok(db) if db < 0.0 => Ok(db),
+        Ok(_) => Err("threshold must be below 0dB".to_string()),
+        Err(_) => Err(format!(
+            "invalid threshold \"{}\", expected e.g. -40dB",
+            value
+        )),
+    }
+}
+
+/// Cuts leading and trailing silence off every clip in place. Each clip is
+/// scanned with `silencedetect` first, then re-cut if silence touches either end.
+pub fn trim_clips(
+    executables: &Executables,
+    clips: &[PathBuf],
+    options: SilenceOptions,
+    encode: Option<&EncodeSettings>,
+) -> Result<()> {
+    let pb = progress::bar(clips.len());
+
+    for clip in clips {
+        let name = clip.file_name().unwrap_or_default().to_string_lossy();
+        pb.set_message(format!("Trimming: {}", name));
+
+        if let Err(e) = trim_clip(executables, clip, options, encode) {
+            pb.finish_and_clear();
+            return Err(e).with_context(|| format!("Failed to trim silence of {}", name));
+        }
+
+        pb.inc(1);
+    }
+
+    pb.finish_with_message("Silence trimmed");
+
+    Ok(())
+}
+
+fn trim_clip(
+    executables: &Executables,
+    clip: &Path,
+    options: SilenceOptions,
+    encode: Option<&EncodeSettings>,
+) -> Result<()> {
+    let detection = detect(executables, clip, options)?;
+    let Some((start, end)) = detection.keep_range() else {
+        return Ok(());
+    };
+    if end - start <= 0.0 {
+        anyhow::bail!("Clip is entirely silent");
+    }
+
+    let mut tmp_name = clip.file_stem().unwrap_or_default().to_os_string();
+    tmp_name.push(".trimmed.");
+    tmp_name.push(clip.extension().unwrap_or_default());
+    let tmp_path = clip.with_file_name(tmp_name);
+
+    let result = executables.run(
+        executables
+            .ffmpeg()
+            .args(["-ss", &format!("{:.3}", start)])
+            .args(["-i", clip.to_str().unwrap()])
+            .args(["-t", &format!("{:.3}", end - start)])
+            .args(encode::clip_codec_args(encode))
+            .args(["-y", tmp_path.to_str().unwrap()]),
+    );
+
+    if let Err(e) = result {
+        let _ = fs::remove_file(&tmp_path);
+        return Err(e);
+    }
+
+    fs::rename(&tmp_path, clip).context("Failed to replace clip with trimmed version")?;
+
+    Ok(())
+}
+
+/// What the detection pass found in one clip.
+#[derive(Debug, Default)]
+struct Detection {
+    duration: Option<f64>,
+    /// `(start, end)` of each silent stretch. `end` is missing when the
+    /// silence lasts until the end of the clip.
+    silences: Vec<(f64, Option<f64>)>,
+}
+
+impl Detection {
+    /// The part of the clip to keep, or `None` when neither end is silent.
+    fn keep_range(&self) -> Option<(f64, f64)> {
+        let duration = self.duration?;
+
+        let start = self
+            .silences
+            .first()
+            .filter(|(start, _)| *start <= EDGE_TOLERANCE)
+            .map_or(0.0, |(_, end)| end.unwrap_or(duration));
+        let end = self
+            .silences
+            .last()
+            .filter(|(_, end)| end.is_none_or(|end| end >= duration - EDGE_TOLERANCE))
+            .map_or(duration, |(start, _)| *start);
+
+        (start > 0.0 || end < duration).then_some((start, end.max(start)))
+    }
+}
+
+/// Runs the detection pass, which reports each silent stretch on stderr as
+/// `silence_start: 1.23` and `silence_end: 4.56 | silence_duration: 3.33`.
+fn detect(executables: &Executables, clip: &Path, options: SilenceOptions) -> Result<Detection> {
+    let output = executables
+        .ffmpeg()
+        .args([
+            "-hide_banner",
+            "-i",
+            clip.to_str().unwrap(),
+            "-af",
+            &format!(
+                "silencedetect=n={}dB:d={}",
+                options.threshold_db, options.min_duration
+            ),
+            "-f",
+            "null",
+            "-",
+        ])
+        .output()
+        .context("Failed to execute ffmpeg")?;
+
+    if !output.status.success() {
+        anyhow::bail!("Silence detection failed");
+    }
+
+    Ok(parse_detection(&String::from_utf8_lossy(&output.stderr)))
+}
+
+fn parse_detection(stderr: &str) -> Detection {
+    let mut detection = Detection::default();
+
+    for line in stderr.lines() {
+        if let Some(value) = value_after(line, "Duration: ") {
+            detection.duration = detection.duration.or_else(|| parse_timestamp(value));
+        } else if let Some(value) = value_after(line, "silence_start: ") {
+            if let Ok(start) = value.parse() {
+                detection.silences.push((f64::max(start, 0.0), None));
+            }
+        } else if let Some(value) = value_after(line, "silence_end: ") {
+            if let (Some(last), Ok(end)) = (detection.silences.last_mut(), value.parse()) {
+                last.1 = Some(end);
+            }
+        }
+    }
+
+    detection
+}
+
+/// The whitespace or comma delimited value following `key` in `line`.
+fn value_after<'a>(line: &'a str, key: &str) -> Option<&'a str> {
+    let rest = &line[line.find(key)? + key.len()..];
+    rest.split([' ', ',']).next()
+}
+
+/// Parses ffmpeg's `HH:MM:SS.ss` duration format.
+fn parse_timestamp(value: &str) -> Option<f64> {
+    let mut seconds = 0.0;
+    for part in value.split(':') {
+        seconds = seconds * 60.0 + part.parse::<f64>().ok()?;
+    }
+    Some(seconds)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_thresholds() {
+        assert_eq!(parse_threshold("-40dB"), Ok(-40.0));
+        assert_eq!(parse_threshold("-32.5db"), Ok(-32.5));
+        assert_eq!(parse_threshold("-50"), Ok(-50.0));
+        assert!(parse_threshold("0dB").is_err());
+        assert!(parse_threshold("loud").is_err());
+    }
+
+    #[test]
+    fn keeps_the_part_between_leading_and_trailing_silence() {
+        let stderr = "  Duration: 00:01:00.50, start: 0.000000, bitrate: 128 kb/s\n\
+             [silencedetect @ 0x1] silence_start: -0.01\n\
+             [silencedetect @ 0x1] silence_end: 2.5 | silence_duration: 2.51\n\
+             [silencedetect @ 0x1] silence_start: 30\n\
+             [silencedetect @ 0x1] silence_end: 31 | silence_duration: 1\n\
+             [silencedetect @ 0x1] silence_start: 57.25\n";
+        let detection = parse_detection(stderr);
+        assert_eq!(detection.duration, Some(60.5));
+        assert_eq!(detection.silences.len(), 3);
+        assert_eq!(detection.keep_range(), Some((2.5, 57.25)));
+    }
+
+    #[test]
+    fn keeps_clips_without_silence_at_either_end() {
+        let stderr = "  Duration: 00:00:10.00, start: 0.000000\n\
+             [silencedetect @ 0x1] silence_start: 4\n\
+             [silencedetect @ 0x1] silence_end: 5 | silence_duration: 1\n";
+        assert_eq!(parse_detection(stderr).keep_range(), None);
+    }
+}
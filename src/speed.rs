@@ -0,0 +1,102 @@
+use std::collections::BTreeMap;
+
+use crate::{Args, Chapter};
+
+/// Range of speeds a single `atempo` filter accepts.
+const ATEMPO_MIN: f64 = 0.5;
+const ATEMPO_MAX: f64 = 2.0;
+
+/// Playback speed of the chapter clips, from `--speed` and `--speed-map`.
+#[derive(Debug, Clone, Default)]
+pub struct SpeedSettings {
+    pub default: f64,
+    /// Speeds for chapters with these exact titles.
+    pub overrides: BTreeMap<String, f64>,
+}
+
+impl SpeedSettings {
+    pub fn from_args(args: &Args) -> Self {
+        SpeedSettings {
+            default: args.speed,
+            overrides: args.speed_map.clone().unwrap_or_default(),
+        }
+    }
+
+    /// Whether any clip plays at other than normal speed.
+    pub fn is_active(&self) -> bool {
+        self.default != 1.0 || self.overrides.values().any(|&speed| speed != 1.0)
+    }
+
+    pub fn for_chapter(&self, chapter: &Chapter) -> f64 {
+        self.overrides
+            .get(&chapter.title)
+            .copied()
+            .unwrap_or(self.default)
+    }
+}
+
+/// Parses a `--speed` multiplier.
+pub fn parse_speed(value: &str) -> Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(speed) if speed > 0.0 && speed.is_finite() => Ok(speed),
+        _ => Err(format!(
+            "invalid speed \"{}\", expected a positive number such as 2.0 or 0.5",
+            value
+        )),
+    }
+}
+
+/// Parses a `--speed-map` JSON object of chapter titles to multipliers.
+pub fn parse_speed_map(value: &str) -> Result<BTreeMap<String, f64>, String> {
+    let map: BTreeMap<String, f64> =
+        serde_json::from_str(value).map_err(|e| format!("invalid JSON object: {}", e))?;
+    for (title, speed) in &map {
+        if *speed <= 0.0 || !speed.is_finite() {
+            return Err(format!("invalid speed {} for \"{}\"", speed, title));
+        }
+    }
+    Ok(map)
+}
+
+/// Video filter playing frames `speed` times as fast.
+pub fn video_filter(speed: f64) -> String {
+    format!("setpts=PTS/{}", speed)
+}
+
+/// Audio filter playing audio `speed` times as fast without changing its
+/// pitch. `atempo` only accepts 0.5 to 2.0, so larger changes are chained.
+pub fn audio_filter(mut speed: f64) -> String {
+    let mut filters = Vec::new();
+    while speed > ATEMPO_MAX {
+        filters.push(format!("atempo={}", ATEMPO_MAX));
+        speed /= ATEMPO_MAX;
+    }
+    while speed < ATEMPO_MIN {
+        filters.push(format!("atempo={}", ATEMPO_MIN));
+        speed /= ATEMPO_MIN;
+    }
+    filters.push(format!("atempo={}", speed));
+    filters.join(",")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_speed_maps() {
+        let map = parse_speed_map(r#"{"Intro": 2, "Slow part": 0.5}"#).unwrap();
+        assert_eq!(map["Intro"], 2.0);
+        assert_eq!(map["Slow part"], 0.5);
+        assert!(parse_speed_map(r#"{"Intro": 0}"#).is_err());
+        assert!(parse_speed_map(r#"{"Intro": "fast"}"#).is_err());
+        assert!(parse_speed_map("[2.0]").is_err());
+    }
+
+    #[test]
+    fn chains_atempo_outside_its_range() {
+        assert_eq!(audio_filter(1.5), "atempo=1.5");
+        assert_eq!(audio_filter(8.0), "atempo=2,atempo=2,atempo=2");
+        assert_eq!(audio_filter(0.25), "atempo=0.5,atempo=0.5");
+    }
+}
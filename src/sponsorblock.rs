@@ -0,0 +1,116 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use url::Url;
+
+const API_URL: &str = "https://sponsor.ajay.app/api/skipSegments";
+
+#[derive(Debug, Deserialize)]
+struct Segment {
+    segment: (f64, f64),
+}
+
+/// Fetches the `(start, end)` times of SponsorBlock segments in `categories`
+/// for `video_id`, sorted by start time.
+pub fn fetch_segments(
+    video_id: &str,
+    categories: &[String],
+    proxy: Option<&Url>,
+) -> Result<Vec<(f64, f64)>> {
+    let categories =
+        serde_json::to_string(categories).context("Failed to encode SponsorBlock categories")?;
+
+    let mut config = ureq::Agent::config_builder();
+    if let Some(proxy) = proxy {
+        let proxy = ureq::Proxy::new(proxy.as_str()).context("Unsupported proxy")?;
+        config = config.proxy(Some(proxy));
+    }
+    let agent = config.build().new_agent();
+
+    let response = agent
+        .get(API_URL)
+        .query("videoID", video_id)
+        .query("categories", &categories)
+        .call();
+
+    let segments: Vec<Segment> = match response {
+        Ok(mut response) => response
+            .body_mut()
+            .read_json()
+            .context("Failed to parse SponsorBlock response")?,
+        // SponsorBlock answers 404 when a video has no segments.
+        Err(ureq::Error::StatusCode(404)) => Vec::new(),
+        Err(e) => return Err(e).context("Failed to query SponsorBlock"),
+    };
+
+    let mut segments: Vec<(f64, f64)> = segments.into_iter().map(|s| s.segment).collect();
+    segments.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    Ok(segments)
+}
+
+/// Returns the parts of `[start, end]` not covered by any of `segments`.
+pub fn keep_ranges(start: f64, end: f64, segments: &[(f64, f64)]) -> Vec<(f64, f64)> {
+    let mut ranges = Vec::new();
+    let mut cursor = start;
+
+    for &(seg_start, seg_end) in segments {
+        if seg_end <= cursor || seg_start >= end {
+            continue;
+        }
+        if seg_start > cursor {
+            ranges.push((cursor, seg_start));
+        }
+        cursor = cursor.max(seg_end);
+    }
+
+    if cursor < end {
+        ranges.push((cursor, end));
+    }
+
+    ranges
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_around_a_segment_in_the_middle() {
+        assert_eq!(
+            keep_ranges(0.0, 100.0, &[(40.0, 50.0)]),
+            [(0.0, 40.0), (50.0, 100.0)]
+        );
+    }
+
+    #[test]
+    fn trims_segments_overlapping_either_end() {
+        assert_eq!(keep_ranges(10.0, 100.0, &[(5.0, 20.0)]), [(20.0, 100.0)]);
+        assert_eq!(keep_ranges(10.0, 100.0, &[(90.0, 120.0)]), [(10.0, 90.0)]);
+    }
+
+    #[test]
+    fn keeps_nothing_of_a_covered_chapter() {
+        assert!(keep_ranges(10.0, 20.0, &[(0.0, 30.0)]).is_empty());
+        assert!(keep_ranges(10.0, 20.0, &[(10.0, 20.0)]).is_empty());
+    }
+
+    #[test]
+    fn ignores_segments_outside_the_chapter() {
+        assert_eq!(
+            keep_ranges(10.0, 20.0, &[(0.0, 10.0), (20.0, 30.0)]),
+            [(10.0, 20.0)]
+        );
+    }
+
+    #[test]
+    fn skips_adjacent_and_overlapping_segments_as_one() {
+        assert_eq!(
+            keep_ranges(
+                0.0,
+                100.0,
+                &[(10.0, 20.0), (20.0, 30.0), (60.0, 75.0), (70.0, 80.0)]
+            ),
+            [(0.0, 10.0), (30.0, 60.0), (80.0, 100.0)]
+        );
+    }
+}
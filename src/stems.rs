@@ -0,0 +1,140 @@
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::Path;
+
+use crate::audio::{self, AudioSettings};
+use crate::executables::Executables;
+use crate::filename::slug;
+use crate::metadata::VideoMetadata;
+use crate::{progress, Chapter};
+
+/// Exports every audio stream of each chapter as its own file, in
+/// `stems_dir/<slug>/`, where the slug comes from the clip name given
+/// alongside the chapter. Streams are named after their language
+/// (`english.mp3`) when the source tags them, and `track_N` otherwise.
+pub fn export_stems(
+    executables: &Executables,
+    video_path: &Path,
+    chapters: &[(&str, &Chapter)],
+    languages: &[Option<String>],
+    stems_dir: &Path,
+    settings: &AudioSettings,
+    metadata: Option<&VideoMetadata>,
+) -> Result<()> {
+    let names = stem_names(languages);
+    let pb = progress::bar(chapters.len() * names.len());
+
+    for (clip_name, chapter) in chapters {
+        pb.set_message(format!("Stems: {}", chapter.title));
+        let output_dir = stems_dir.join(slug(clip_name));
+        fs::create_dir_all(&output_dir)
+            .with_context(|| format!("Failed to create {}", output_dir.display()))?;
+
+        for (index, name) in names.iter().enumerate() {
+            let output = output_dir.join(format!("{}.{}", name, settings.format.extension()));
+            let mut output_args = vec!["-map".to_string(), format!("0:a:{}", index)];
+            output_args.extend(metadata.map_or_else(Vec::new, |m| m.audio_args(chapter)));
+            output_args.extend(executables.ffmpeg_extra_args.iter().cloned());
+
+            let result = audio::encode_audio_clip(
+                executables,
+                video_path,
+                chapter.start_time,
+                chapter.end_time - chapter.start_time,
+                &output,
+                settings,
+                &output_args,
+            );
+            if let Err(e) = result {
+                pb.finish_and_clear();
+                return Err(e).with_context(|| format!("Failed to export {}", output.display()));
+            }
+            pb.inc(1);
+        }
+    }
+
+    pb.finish_and_clear();
+    Ok(())
+}
+
+/// File names of the stems: the language of each stream, or `track_N` when
+/// it has none. Languages shared by several streams, such as an English
+/// vocal and an English instrumental, get the stream number too.
+fn stem_names(languages: &[Option<String>]) -> Vec<String> {
+    let names: Vec<Option<String>> = languages
+        .iter()
+        .map(|language| language.as_deref().and_then(language_name))
+        .collect();
+    let shared = |name: &String| {
+        names
+            .iter()
+            .flatten()
+            .filter(|other| *other == name)
+            .count()
+            > 1
+    };
+    names
+        .iter()
+        .enumerate()
+        .map(|(index, name)| match name {
+            Some(name) if shared(name) => format!("{}_{}", name, index),
+            Some(name) => name.clone(),
+            None => format!("track_{}", index),
+        })
+        .collect()
+}
+
+/// A file name for an ISO 639 language tag: the English name of common
+/// languages, the tag itself otherwise. `und` (undetermined) has none.
+fn language_name(tag: &str) -> Option<String> {
+    let tag = tag.to_ascii_lowercase();
+    let name = match tag.as_str() {
+        "" | "und" => return None,
+        "en" | "eng" => "english",
+        "ja" | "jpn" => "japanese",
+        "es" | "spa" => "spanish",
+        "fr" | "fra" | "fre" => "french",
+        "de" | "deu" | "ger" => "german",
+        "it" | "ita" => "italian",
+        "pt" | "por" => "portuguese",
+        "ru" | "rus" => "russian",
+        "zh" | "zho" | "chi" => "chinese",
+        "ko" | "kor" => "korean",
+        "hi" | "hin" => "hindi",
+        "ar" | "ara" => "arabic",
+        "nl" | "nld" | "dut" => "dutch",
+        "pl" | "pol" => "polish",
+        "tr" | "tur" => "turkish",
+        _ => return Some(slug(&tag)),
+    };
+    Some(name.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_stems_after_their_language() {
+        let languages = [
+            Some("eng".to_string()),
+            Some("ja".to_string()),
+            None,
+            Some("und".to_string()),
+            Some("tlh".to_string()),
+        ];
+        assert_eq!(
+            stem_names(&languages),
+            ["english", "japanese", "track_2", "track_3", "tlh"]
+        );
+    }
+
+    #[test]
+    fn numbers_stems_that_share_a_language() {
+        let languages = [Some("en".to_string()), Some("ENG".to_string()), None];
+        assert_eq!(
+            stem_names(&languages),
+            ["english_0", "english_1", "track_2"]
+        );
+    }
+}
@@ -0,0 +1,330 @@
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::tracks::{self, AudioTrack};
+use crate::Chapter;
+
+/// yt-dlp arguments that download manual subtitles for `lang`, falling back
+/// to auto-generated captions.
+pub fn ytdlp_args(lang: &str) -> Vec<String> {
+    vec![
+        "--write-subs".to_string(),
+        "--write-auto-subs".to_string(),
+        "--sub-langs".to_string(),
+        lang.to_string(),
+    ]
+}
+
+/// Finds the subtitle file yt-dlp wrote next to `video_path`, e.g.
+/// `full_video.en.vtt` for `full_video.mp4`.
+pub fn find_subtitle_file(video_path: &Path, lang: &str) -> Option<PathBuf> {
+    let stem = video_path.file_stem()?.to_str()?;
+    let prefix = format!("{}.{}.", stem, lang);
+    let dir = video_path.parent()?;
+
+    fs::read_dir(dir)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .find(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with(&prefix))
+        })
+}
+
+/// A subtitle cue, with times in seconds from the start of the video.
+#[derive(Debug, Clone)]
+pub struct Cue {
+    pub start: f64,
+    pub end: f64,
+    pub text: String,
+}
+
+/// Reads the cues of a WebVTT or SRT file. Blocks that can't be parsed are
+/// skipped with a warning.
+pub fn read_cues(subtitles: &Path) -> Result<Vec<Cue>> {
+    let contents = fs::read_to_string(subtitles)
+        .with_context(|| format!("Failed to read {}", subtitles.display()))?;
+    let (cues, malformed) = parse_cues(&contents);
+
+    if malformed > 0 {
+        estatus!(
+            "Warning: skipped {} malformed subtitle cue(s) in {}",
+            malformed,
+            subtitles.display()
+        );
+    }
+
+    Ok(cues)
+}
+
+/// Parses WebVTT or SRT cues, returning them with the number of blocks that
+/// looked like cues but couldn't be parsed.
+fn parse_cues(contents: &str) -> (Vec<Cue>, usize) {
+    let contents = contents
+        .trim_start_matches('\u{feff}')
+        .replace("\r\n", "\n");
+    let mut cues = Vec::new();
+    let mut malformed = 0;
+
+    for block in contents.split("\n\n") {
+        let lines: Vec<&str> = block
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .collect();
+        let Some(first) = lines.first() else {
+            continue;
+        };
+        if is_vtt_header_block(first) {
+            continue;
+        }
+
+        // The timing line comes first, or second after an SRT index or a
+        // WebVTT cue identifier.
+        let Some(timing) = lines.iter().take(2).position(|line| line.contains("-->")) else {
+            malformed += 1;
+            continue;
+        };
+        let Some((start, end)) = parse_timing(lines[timing]) else {
+            malformed += 1;
+            continue;
+        };
+
+        let text: Vec<String> = lines[timing + 1..]
+            .iter()
+            .map(|line| clean_text(line))
+            .filter(|line| !line.trim().is_empty())
+            .collect();
+        if text.is_empty() || end <= start {
+            continue;
+        }
+
+        cues.push(Cue {
+            start,
+            end,
+            text: text.join("\n"),
+        });
+    }
+
+    (cues, malformed)
+}
+
+fn is_vtt_header_block(first_line: &str) -> bool {
+    ["WEBVTT", "NOTE", "STYLE", "REGION"]
+        .iter()
+        .any(|keyword| first_line.starts_with(keyword))
+}
+
+/// Parses `00:01:02.500 --> 00:01:04.000`, ignoring any WebVTT cue settings
+/// after the end time.
+fn parse_timing(line: &str) -> Option<(f64, f64)> {
+    let (start, rest) = line.split_once("-->")?;
+    let end = rest.split_whitespace().next()?;
+    Some((parse_cue_time(start.trim())?, parse_cue_time(end)?))
+}
+
+/// Parses `HH:MM:SS.mmm`, `MM:SS.mmm` or SRT's `HH:MM:SS,mmm`.
+fn parse_cue_time(value: &str) -> Option<f64> {
+    let value = value.replace(',', ".");
+    let mut seconds = 0.0;
+    let mut parts = 0;
+
+    for part in value.split(':') {
+        let part: f64 = part.parse().ok()?;
+        if part < 0.0 {
+            return None;
+        }
+        seconds = seconds * 60.0 + part;
+        parts += 1;
+    }
+
+    (2..=3).contains(&parts).then_some(seconds)
+}
+
+/// Strips WebVTT-only markup such as `<c>` spans and inline karaoke
+/// timestamps, which SRT players show literally, keeping `<i>`, `<b>` and
+/// `<u>`.
+fn clean_text(line: &str) -> String {
+    let mut text = String::with_capacity(line.len());
+    let mut rest = line;
+
+    while let Some(open) = rest.find('<') {
+        text.push_str(&rest[..open]);
+        let Some(close) = rest[open..].find('>') else {
+            rest = &rest[open..];
+            break;
+        };
+        let tag = &rest[open..open + close + 1];
+        let name = tag
+            .trim_start_matches(['<', '/'])
+            .trim_end_matches('>')
+            .split(['.', ' '])
+            .next()
+            .unwrap_or("");
+        if matches!(name, "i" | "b" | "u") {
+            text.push_str(&format!(
+                "<{}{}>",
+                if tag.starts_with("</") { "/" } else { "" },
+                name
+            ));
+        }
+        rest = &rest[open + close + 1..];
+    }
+    text.push_str(rest);
+
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&")
+}
+
+/// Writes the cues overlapping `chapter` to `output` as SRT, shifted so the
+/// clip starts at zero. Cues that span a chapter boundary are trimmed to the
+/// chapter rather than dropped.
+pub fn slice_subtitles(cues: &[Cue], chapter: &Chapter, output: &Path) -> Result<()> {
+    let duration = chapter.end_time - chapter.start_time;
+    let mut srt = String::new();
+    let mut index = 0;
+
+    for cue in cues
+        .iter()
+        .filter(|cue| cue.end > chapter.start_time && cue.start < chapter.end_time)
+    {
+        index += 1;
+        let start = (cue.start - chapter.start_time).max(0.0);
+        let end = (cue.end - chapter.start_time).min(duration);
+        srt.push_str(&format!(
+            "{}\n{} --> {}\n{}\n\n",
+            index,
+            srt_time(start),
+            srt_time(end),
+            cue.text
+        ));
+    }
+
+    fs::write(output, srt).with_context(|| format!("Failed to write {}", output.display()))
+}
+
+/// Formats seconds as SRT's `HH:MM:SS,mmm`.
+fn srt_time(seconds: f64) -> String {
+    let millis = (seconds * 1000.0).round() as u64;
+    format!(
+        "{:02}:{:02}:{:02},{:03}",
+        millis / 3_600_000,
+        millis / 60_000 % 60,
+        millis / 1000 % 60,
+        millis % 1000
+    )
+}
+
+/// The `subtitles` filter that burns `subtitles` into the video, with the path
+/// escaped for use inside a filtergraph.
+pub fn burn_filter(subtitles: &Path) -> String {
+    // Escaped once for the filter's option value and again for the filtergraph.
+    let value = escape(&subtitles.to_string_lossy(), &['\\', '\'', ':']);
+    format!(
+        "subtitles={}",
+        escape(&value, &['\\', '\'', '[', ']', ',', ';'])
+    )
+}
+
+pub(crate) fn escape(value: &str, special: &[char]) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if special.contains(&c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Arguments that add `subtitles` (the second ffmpeg input) as a soft
+/// subtitle track stored as `codec`, while stream copying everything else.
+/// Only `audio_tracks` are kept when given, instead of every audio track.
+pub fn soft_track_args(codec: &str, audio_tracks: Option<&[AudioTrack]>) -> Vec<String> {
+    let mut args: Vec<String> = vec!["-map".to_string(), "0:v".to_string()];
+    match audio_tracks {
+        Some(tracks) => args.extend(tracks::audio_map_args(tracks)),
+        None => args.extend(["-map".to_string(), "0:a?".to_string()]),
+    }
+    args.extend(
+        ["-map", "1:s", "-c", "copy", "-avoid_negative_ts", "1"]
+            .iter()
+            .map(|s| s.to_string()),
+    );
+    args.extend(audio_tracks.map(tracks::language_args).unwrap_or_default());
+    args.extend(["-c:s".to_string(), codec.to_string()]);
+    args
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_vtt_with_a_byte_order_mark() {
+        let vtt = "\u{feff}WEBVTT\n\nNOTE made by hand\n\n\
+                   intro\n00:01.000 --> 00:02.500 align:start\n<c.yellow>Hello</c> <i>there</i>\n";
+        let (cues, malformed) = parse_cues(vtt);
+        assert_eq!(malformed, 0);
+        assert_eq!(cues.len(), 1);
+        assert_eq!((cues[0].start, cues[0].end), (1.0, 2.5));
+        assert_eq!(cues[0].text, "Hello <i>there</i>");
+    }
+
+    #[test]
+    fn parses_srt_comma_timestamps() {
+        let srt = "1\r\n00:00:01,500 --> 00:01:02,250\r\nFirst\r\nline two\r\n\r\n";
+        let (cues, malformed) = parse_cues(srt);
+        assert_eq!(malformed, 0);
+        assert_eq!((cues[0].start, cues[0].end), (1.5, 62.25));
+        assert_eq!(cues[0].text, "First\nline two");
+    }
+
+    #[test]
+    fn counts_malformed_blocks_instead_of_failing() {
+        let srt = "1\n00:00:01,000 --> 00:00:02,000\nKept\n\n\
+                   2\nnot a timing line\nLost\n\n\
+                   3\n00:00:xx,000 --> 00:00:04,000\nLost too\n\n\
+                   4\n00:00:05,000 --> 00:00:06,000\nAlso kept\n";
+        let (cues, malformed) = parse_cues(srt);
+        assert_eq!(malformed, 2);
+        let texts: Vec<&str> = cues.iter().map(|cue| cue.text.as_str()).collect();
+        assert_eq!(texts, ["Kept", "Also kept"]);
+    }
+
+    #[test]
+    fn clamps_cues_crossing_the_chapter_boundaries() {
+        let cue = |start, end, text: &str| Cue {
+            start,
+            end,
+            text: text.to_string(),
+        };
+        let cues = [
+            cue(5.0, 9.0, "before"),
+            cue(8.0, 12.0, "across start"),
+            cue(15.0, 16.5, "inside"),
+            cue(19.0, 22.0, "across end"),
+            cue(21.0, 23.0, "after"),
+        ];
+        let chapter = Chapter {
+            title: "Main".to_string(),
+            start_time: 10.0,
+            end_time: 20.0,
+            number: 1,
+        };
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("main.srt");
+        slice_subtitles(&cues, &chapter, &output).unwrap();
+
+        assert_eq!(
+            fs::read_to_string(&output).unwrap(),
+            "1\n00:00:00,000 --> 00:00:02,000\nacross start\n\n\
+             2\n00:00:05,000 --> 00:00:06,500\ninside\n\n\
+             3\n00:00:09,000 --> 00:00:10,000\nacross end\n\n"
+        );
+    }
+}
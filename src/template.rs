@@ -0,0 +1,351 @@
+use anyhow::{Context, Result};
+
+use crate::filename::{self, FilenameOptions, NumberingStyle};
+
+pub const DEFAULT_TEMPLATE: &str = "{num}_{title}";
+
+/// Values available to a template when naming a single chapter clip.
+pub struct TemplateContext<'a> {
+    pub num: usize,
+    /// Zero-padding width of `{num}` when the template gives no format spec
+    /// and chapters are numbered in arabic numerals.
+    pub num_width: usize,
+    pub title: &'a str,
+    pub start: f64,
+    pub end: f64,
+    pub video_title: &'a str,
+    pub filenames: &'a FilenameOptions,
+}
+
+/// A parsed output filename template such as `{num:03}-{title}`.
+///
+/// Literal text (including `/` to create subdirectories) is kept as-is, while
+/// placeholder values are sanitized so they can never escape their path component.
+#[derive(Debug, Clone)]
+pub struct Template {
+    segments: Vec<Segment>,
+}
+
+#[derive(Debug, Clone)]
+enum Segment {
+    Literal(String),
+    Placeholder { field: Field, spec: Spec },
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Field {
+    Num,
+    Title,
+    Start,
+    End,
+    Duration,
+    VideoTitle,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct Spec {
+    zero_pad: bool,
+    width: usize,
+    precision: Option<usize>,
+}
+
+impl Field {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "num" => Some(Field::Num),
+            "title" => Some(Field::Title),
+            "start" => Some(Field::Start),
+            "end" => Some(Field::End),
+            "duration" => Some(Field::Duration),
+            "video_title" => Some(Field::VideoTitle),
+            _ => None,
+        }
+    }
+
+    fn is_numeric(self) -> bool {
+        !matches!(self, Field::Title | Field::VideoTitle)
+    }
+}
+
+impl Spec {
+    fn parse(spec: &str) -> Option<Self> {
+        let (width_part, precision) = match spec.split_once('.') {
+            Some((w, p)) => (w, Some(p.parse().ok()?)),
+            None => (spec, None),
+        };
+
+        let zero_pad = width_part.starts_with('0');
+        let width = if width_part.is_empty() {
+            0
+        } else {
+            width_part.parse().ok()?
+        };
+
+        Some(Spec {
+            zero_pad,
+            width,
+            precision,
+        })
+    }
+
+    fn apply(&self, value: f64) -> String {
+        let precision = self.precision.unwrap_or(0);
+        if self.zero_pad {
+            format!("{:0width$.precision$}", value, width = self.width)
+        } else {
+            format!("{:width$.precision$}", value, width = self.width)
+        }
+    }
+}
+
+impl Template {
+    pub fn parse(template: &str) -> Result<Self> {
+        let segments = tokenize(template)?
+            .into_iter()
+            .map(|token| match token {
+                Token::Literal(text) => Ok(Segment::Literal(text)),
+                Token::Placeholder(placeholder) => parse_placeholder(&placeholder)
+                    .with_context(|| format!("Invalid placeholder in template \"{}\"", template)),
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Template { segments })
+    }
+
+    pub fn render(&self, ctx: &TemplateContext) -> String {
+        let mut output = String::new();
+
+        for segment in &self.segments {
+            match segment {
+                Segment::Literal(text) => output.push_str(text),
+                Segment::Placeholder { field, spec } => {
+                    let value = match field {
+                        Field::Num if *spec == Spec::default() => match ctx.filenames.numbering {
+                            NumberingStyle::Arabic => {
+                                format!("{:0width$}", ctx.num, width = ctx.num_width)
+                            }
+                            style => filename::format_chapter_number(ctx.num, style),
+                        },
+                        Field::Num => spec.apply(ctx.num as f64),
+                        Field::Title => match ctx.filenames.clean_chapter_title(ctx.title) {
+                            title if title.is_empty() => format!("chapter_{}", ctx.num),
+                            title => title,
+                        },
+                        Field::Start => spec.apply(ctx.start),
+                        Field::End => spec.apply(ctx.end),
+                        Field::Duration => spec.apply(ctx.end - ctx.start),
+                        Field::VideoTitle => ctx.filenames.clean(ctx.video_title),
+                    };
+                    output.push_str(&value);
+                }
+            }
+        }
+
+        ctx.filenames.shorten(&output)
+    }
+}
+
+enum Token {
+    Literal(String),
+    /// The text between the braces of a `{...}` placeholder.
+    Placeholder(String),
+}
+
+/// Splits a template into literal text and placeholders. `{{` and `}}` are
+/// literal braces.
+fn tokenize(template: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut literal = String::new();
+    let mut chars = template.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                literal.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                literal.push('}');
+            }
+            '{' => {
+                let mut placeholder = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => placeholder.push(c),
+                        None => anyhow::bail!(
+                            "Unclosed placeholder '{{{}' in template \"{}\"",
+                            placeholder,
+                            template
+                        ),
+                    }
+                }
+
+                if !literal.is_empty() {
+                    tokens.push(Token::Literal(std::mem::take(&mut literal)));
+                }
+                tokens.push(Token::Placeholder(placeholder));
+            }
+            '}' => anyhow::bail!("Unmatched '}}' in template \"{}\"", template),
+            c => literal.push(c),
+        }
+    }
+
+    if !literal.is_empty() {
+        tokens.push(Token::Literal(literal));
+    }
+
+    if tokens.is_empty() {
+        anyhow::bail!("Template must not be empty");
+    }
+
+    Ok(tokens)
+}
+
+fn parse_placeholder(placeholder: &str) -> Result<Segment> {
+    let (name, spec) = match placeholder.split_once(':') {
+        Some((name, spec)) => (name, Some(spec)),
+        None => (placeholder, None),
+    };
+
+    let field = Field::from_name(name).with_context(|| {
+        format!(
+            "Unknown placeholder '{{{}}}'. Available: {{num}}, {{title}}, {{start}}, {{end}}, {{duration}}, {{video_title}}",
+            name
+        )
+    })?;
+
+    let spec = match spec {
+        Some(_) if !field.is_numeric() => {
+            anyhow::bail!("Placeholder '{{{}}}' does not accept a format spec", name)
+        }
+        Some(spec) => Spec::parse(spec)
+            .with_context(|| format!("Invalid format spec '{}' for '{{{}}}'", spec, name))?,
+        None => Spec::default(),
+    };
+
+    Ok(Segment::Placeholder { field, spec })
+}
+
+pub const DEFAULT_DIR_TEMPLATE: &str = "{title}";
+
+/// Values available to a `--dir-template`.
+pub struct DirContext<'a> {
+    pub title: &'a str,
+    pub uploader: Option<&'a str>,
+    pub upload_date: Option<&'a str>,
+    pub id: &'a str,
+    pub filenames: &'a FilenameOptions,
+}
+
+/// A parsed output directory template such as `{uploader}/{upload_date}_{title}`.
+/// Like [`Template`], literal `/` creates subdirectories while placeholder
+/// values are sanitized.
+#[derive(Debug, Clone)]
+pub struct DirTemplate {
+    segments: Vec<DirSegment>,
+}
+
+#[derive(Debug, Clone)]
+enum DirSegment {
+    Literal(String),
+    Title,
+    Uploader,
+    UploadDate,
+    Id,
+}
+
+impl DirTemplate {
+    pub fn parse(template: &str) -> Result<Self> {
+        let segments = tokenize(template)?
+            .into_iter()
+            .map(|token| match token {
+                Token::Literal(text) => Ok(DirSegment::Literal(text)),
+                Token::Placeholder(name) => match name.as_str() {
+                    "title" => Ok(DirSegment::Title),
+                    "uploader" => Ok(DirSegment::Uploader),
+                    "upload_date" => Ok(DirSegment::UploadDate),
+                    "id" => Ok(DirSegment::Id),
+                    _ => anyhow::bail!(
+                        "Unknown placeholder '{{{}}}' in directory template \"{}\". Available: {{title}}, {{uploader}}, {{upload_date}}, {{id}}",
+                        name,
+                        template
+                    ),
+                },
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(DirTemplate { segments })
+    }
+
+    pub fn render(&self, ctx: &DirContext) -> String {
+        let clean = |value: Option<&str>, fallback: &str| match value
+            .map(|value| ctx.filenames.clean(value))
+        {
+            Some(value) if !value.is_empty() => value,
+            _ => fallback.to_string(),
+        };
+
+        let rendered: String = self
+            .segments
+            .iter()
+            .map(|segment| match segment {
+                DirSegment::Literal(text) => text.clone(),
+                DirSegment::Title => clean(Some(ctx.title), "video"),
+                DirSegment::Uploader => clean(ctx.uploader, "unknown"),
+                DirSegment::UploadDate => clean(ctx.upload_date, "unknown"),
+                DirSegment::Id => clean(Some(ctx.id), "unknown"),
+            })
+            .collect();
+        ctx.filenames.shorten(&rendered)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(template: &str, title: &str) -> String {
+        Template::parse(template).unwrap().render(&TemplateContext {
+            num: 3,
+            num_width: 2,
+            title,
+            start: 65.34,
+            end: 125.7,
+            video_title: "My Video",
+            filenames: &FilenameOptions::default(),
+        })
+    }
+
+    #[test]
+    fn renders_placeholders() {
+        assert_eq!(render(DEFAULT_TEMPLATE, "Intro"), "03_Intro");
+        assert_eq!(
+            render("{video_title} - {num:03} {title}", "Intro"),
+            "My Video - 003 Intro"
+        );
+        assert_eq!(
+            render("{start:.1}-{end}-{duration:06.2}", "Intro"),
+            "65.3-126-060.36"
+        );
+        assert_eq!(render("{{{num}}}", "Intro"), "{03}");
+    }
+
+    #[test]
+    fn keeps_placeholder_values_inside_their_path_component() {
+        assert_eq!(render("{title}", "AC/DC: Live?"), "ACDC Live");
+        assert_eq!(render("{title}", "???"), "chapter_3");
+    }
+
+    #[test]
+    fn rejects_invalid_templates() {
+        for template in ["", "{nope}", "{title:03}", "{num:x}", "{num", "num}"] {
+            assert!(
+                Template::parse(template).is_err(),
+                "{} was accepted",
+                template
+            );
+        }
+    }
+}
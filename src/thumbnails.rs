@@ -0,0 +1,205 @@
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+use crate::executables::Executables;
+use crate::plan::format_timestamp;
+use crate::progress;
+use crate::{create_parent_dir, Chapter};
+
+pub const SPRITESHEET_FILE: &str = "spritesheet.jpg";
+pub const SPRITES_VTT_FILE: &str = "sprites.vtt";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ThumbnailFormat {
+    Webp,
+    Jpg,
+}
+
+impl ThumbnailFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            ThumbnailFormat::Webp => "webp",
+            ThumbnailFormat::Jpg => "jpg",
+        }
+    }
+
+    /// Encoder quality: WebP runs from 0 to 100 (best), JPEG from 31 to 2 (best).
+    fn quality(self) -> &'static str {
+        match self {
+            ThumbnailFormat::Webp => "80",
+            ThumbnailFormat::Jpg => "2",
+        }
+    }
+}
+
+/// Parses a position within a chapter, from 0.0 (start) to 1.0 (end).
+pub fn parse_offset(value: &str) -> Result<f64, String> {
+    let offset: f64 = value
+        .parse()
+        .map_err(|_| format!("'{}' is not a number", value))?;
+
+    if !(0.0..=1.0).contains(&offset) {
+        return Err("offset must be between 0.0 and 1.0".to_string());
+    }
+
+    Ok(offset)
+}
+
+/// Extracts one frame per chapter, named after the chapter's clip.
+pub fn generate_thumbnails(
+    executables: &Executables,
+    video_path: &Path,
+    chapters: &[Chapter],
+    clip_names: &[String],
+    thumbnails_dir: &Path,
+    format: ThumbnailFormat,
+    offset: f64,
+) -> Result<()> {
+    fs::create_dir_all(thumbnails_dir).context("Failed to create thumbnails directory")?;
+
+    let pb = progress::bar(chapters.len());
+
+    for (chapter, name) in chapters.iter().zip(clip_names) {
+        pb.set_message(format!("Thumbnail: {}", chapter.title));
+
+        let output = thumbnails_dir.join(format!("{}.{}", name, format.extension()));
+        create_parent_dir(&output)?;
+
+        // Never seek exactly to the end, which would produce no frame at all.
+        let duration = chapter.end_time - chapter.start_time;
+        let timestamp = chapter.start_time + (duration * offset).min((duration - 0.1).max(0.0));
+
+        let result = executables.run(executables.ffmpeg().args([
+            "-ss",
+            &format!("{:.3}", timestamp),
+            "-i",
+            video_path.to_str().unwrap(),
+            "-vf",
+            "scale=1280:-1",
+            "-frames:v",
+            "1",
+            "-q:v",
+            format.quality(),
+            "-y",
+            output.to_str().unwrap(),
+        ]));
+
+        if let Err(e) = result {
+            pb.finish_and_clear();
+            return Err(e).with_context(|| {
+                format!("Failed to extract thumbnail for chapter: {}", chapter.title)
+            });
+        }
+
+        pb.inc(1);
+    }
+
+    pb.finish_with_message("All thumbnails extracted");
+
+    Ok(())
+}
+
+/// Layout of the `--spritesheet` contact sheet.
+#[derive(Debug, Clone, Copy)]
+pub struct SpriteLayout {
+    /// Size of every tile in pixels.
+    pub width: u32,
+    pub height: u32,
+    pub columns: u32,
+    pub rows: u32,
+}
+
+impl SpriteLayout {
+    /// Tiles of `width` pixels keeping the video's `aspect_ratio`, arranged in
+    /// `columns` columns, or a roughly square grid when that is `None`.
+    pub fn new(count: usize, width: u32, aspect_ratio: f64, columns: Option<u32>) -> Self {
+        let columns = columns
+            .unwrap_or_else(|| (count as f64).sqrt().ceil() as u32)
+            .clamp(1, count.max(1) as u32);
+        // Encoders need even dimensions.
+        let height = ((f64::from(width) / aspect_ratio / 2.0).round() as u32 * 2).max(2);
+
+        SpriteLayout {
+            width,
+            height,
+            columns,
+            rows: (count as u32).div_ceil(columns),
+        }
+    }
+}
+
+/// Tiles the middle frame of every chapter into `spritesheet.jpg`, and writes
+/// `sprites.vtt` mapping each chapter's time range to its tile for use as a
+/// player preview track. `time_offset` is added to the VTT timestamps when the
+/// downloaded file starts partway into the video.
+pub fn generate_spritesheet(
+    executables: &Executables,
+    video_path: &Path,
+    chapters: &[Chapter],
+    output_dir: &Path,
+    layout: SpriteLayout,
+    time_offset: f64,
+) -> Result<()> {
+    let frames_dir = tempfile::tempdir().context("Failed to create temporary directory")?;
+    let scale = format!("scale={}:{}", layout.width, layout.height);
+
+    for (i, chapter) in chapters.iter().enumerate() {
+        let timestamp = (chapter.start_time + chapter.end_time) / 2.0;
+        let frame = frames_dir.path().join(format!("frame_{:03}.jpg", i));
+
+        executables
+            .run(executables.ffmpeg().args([
+                "-ss",
+                &format!("{:.3}", timestamp),
+                "-i",
+                video_path.to_str().unwrap(),
+                "-vf",
+                &scale,
+                "-frames:v",
+                "1",
+                "-q:v",
+                "2",
+                "-y",
+                frame.to_str().unwrap(),
+            ]))
+            .with_context(|| format!("Failed to extract sprite for chapter: {}", chapter.title))?;
+    }
+
+    let spritesheet = output_dir.join(SPRITESHEET_FILE);
+    executables
+        .run(executables.ffmpeg().args([
+            "-i",
+            frames_dir.path().join("frame_%03d.jpg").to_str().unwrap(),
+            "-vf",
+            &format!("tile={}x{}", layout.columns, layout.rows),
+            "-frames:v",
+            "1",
+            "-q:v",
+            "2",
+            "-y",
+            spritesheet.to_str().unwrap(),
+        ]))
+        .context("Failed to tile sprite sheet")?;
+
+    let mut vtt = String::from("WEBVTT\n");
+    for (i, chapter) in chapters.iter().enumerate() {
+        let i = i as u32;
+        vtt.push_str(&format!(
+            "\n{} --> {}\n{}#xywh={},{},{},{}\n",
+            format_timestamp(chapter.start_time + time_offset),
+            format_timestamp(chapter.end_time + time_offset),
+            SPRITESHEET_FILE,
+            i % layout.columns * layout.width,
+            i / layout.columns * layout.height,
+            layout.width,
+            layout.height
+        ));
+    }
+
+    let vtt_path = output_dir.join(SPRITES_VTT_FILE);
+    fs::write(&vtt_path, vtt).with_context(|| format!("Failed to write {}", vtt_path.display()))
+}
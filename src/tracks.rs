@@ -0,0 +1,142 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// Which audio tracks of the source `--audio-tracks` keeps in every clip.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(into = "String", try_from = "String")]
+pub enum AudioTracks {
+    All,
+    /// 0-based positions among the source's audio tracks.
+    Selected(Vec<usize>),
+}
+
+/// An audio track kept in the clips.
+#[derive(Debug, Clone)]
+pub struct AudioTrack {
+    /// Position among the source's audio tracks, as in `0:a:N`.
+    pub index: usize,
+    /// Language tag from the source, e.g. `eng`.
+    pub language: Option<String>,
+}
+
+impl FromStr for AudioTracks {
+    type Err = String;
+
+    /// Parses `all` or a comma-separated list of track numbers.
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        if value.eq_ignore_ascii_case("all") {
+            return Ok(AudioTracks::All);
+        }
+
+        let mut indices = Vec::new();
+        for part in value.split(',') {
+            let index = part.trim().parse::<usize>().map_err(|_| {
+                format!(
+                    "invalid audio tracks \"{}\", expected \"all\" or track numbers such as \"0,2\"",
+                    value
+                )
+            })?;
+            if !indices.contains(&index) {
+                indices.push(index);
+            }
+        }
+        Ok(AudioTracks::Selected(indices))
+    }
+}
+
+impl TryFrom<String> for AudioTracks {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl fmt::Display for AudioTracks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AudioTracks::All => f.write_str("all"),
+            AudioTracks::Selected(indices) => {
+                let indices: Vec<String> = indices.iter().map(usize::to_string).collect();
+                f.write_str(&indices.join(","))
+            }
+        }
+    }
+}
+
+impl From<AudioTracks> for String {
+    fn from(tracks: AudioTracks) -> Self {
+        tracks.to_string()
+    }
+}
+
+impl AudioTracks {
+    /// The tracks to keep, given the language of each of the source's audio
+    /// tracks in order.
+    pub fn select(&self, languages: &[Option<String>]) -> Result<Vec<AudioTrack>> {
+        let track = |index: usize| AudioTrack {
+            index,
+            language: languages[index].clone(),
+        };
+
+        match self {
+            AudioTracks::All => Ok((0..languages.len()).map(track).collect()),
+            AudioTracks::Selected(indices) => {
+                if let Some(index) = indices.iter().find(|&&index| index >= languages.len()) {
+                    anyhow::bail!(
+                        "--audio-tracks: there is no audio track {}, the video has {} ({})",
+                        index,
+                        languages.len(),
+                        describe(languages)
+                    );
+                }
+                Ok(indices.iter().copied().map(track).collect())
+            }
+        }
+    }
+}
+
+/// `0: eng, 1: spa, 2: unknown` for messages.
+fn describe(languages: &[Option<String>]) -> String {
+    languages
+        .iter()
+        .enumerate()
+        .map(|(i, language)| format!("{}: {}", i, language.as_deref().unwrap_or("unknown")))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// `-map` arguments for `tracks` of the first input.
+pub fn audio_map_args(tracks: &[AudioTrack]) -> Vec<String> {
+    tracks
+        .iter()
+        .flat_map(|track| ["-map".to_string(), format!("0:a:{}", track.index)])
+        .collect()
+}
+
+/// `-map` arguments for the first video stream and `tracks`, with each
+/// output track tagged with its source language.
+pub fn map_args(tracks: &[AudioTrack]) -> Vec<String> {
+    let mut args = vec!["-map".to_string(), "0:v:0".to_string()];
+    args.extend(audio_map_args(tracks));
+    args.extend(language_args(tracks));
+    args
+}
+
+/// `-metadata:s:a:N language=...` for every output track with a known language.
+pub fn language_args(tracks: &[AudioTrack]) -> Vec<String> {
+    tracks
+        .iter()
+        .enumerate()
+        .filter_map(|(i, track)| {
+            let language = track.language.as_deref()?;
+            Some([
+                format!("-metadata:s:a:{}", i),
+                format!("language={}", language),
+            ])
+        })
+        .flatten()
+        .collect()
+}
@@ -0,0 +1,95 @@
+use anyhow::{Context, Result};
+use aws_sdk_s3::primitives::ByteStream;
+use std::path::{Path, PathBuf};
+
+use crate::checksums::collect_files;
+use crate::progress;
+
+/// Where `--upload-s3` sends the output directory.
+#[derive(Debug, Clone)]
+pub struct S3Options {
+    pub bucket: String,
+    /// Key prefix, e.g. `videos/2024`.
+    pub prefix: Option<String>,
+    /// Endpoint of an S3-compatible service such as Backblaze B2 or MinIO.
+    pub endpoint: Option<String>,
+    /// Only upload these subdirectories (`clips`, `vertical`, `audio_only`,
+    /// ...). Everything is uploaded when empty.
+    pub only: Vec<String>,
+}
+
+/// Uploads every file under `output_dir`, keyed by its path relative to it.
+/// Credentials come from the standard AWS chain (environment, profile,
+/// instance metadata, ...).
+pub async fn upload_dir(output_dir: &Path, options: &S3Options) -> Result<()> {
+    let mut files = Vec::new();
+    collect_files(output_dir, &mut files)?;
+    files.sort();
+
+    let files: Vec<(PathBuf, String)> = files
+        .into_iter()
+        .filter_map(|path| {
+            let relative = path.strip_prefix(output_dir).ok()?;
+            let components: Vec<String> = relative
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy().into_owned())
+                .collect();
+            let selected = options.only.is_empty()
+                || components[..components.len() - 1]
+                    .iter()
+                    .any(|dir| options.only.contains(dir));
+            selected.then(|| {
+                let key = components.join("/");
+                let key = match options.prefix.as_deref().map(|p| p.trim_matches('/')) {
+                    Some(prefix) if !prefix.is_empty() => format!("{}/{}", prefix, key),
+                    _ => key,
+                };
+                (path, key)
+            })
+        })
+        .collect();
+
+    if files.is_empty() {
+        status!("Nothing to upload");
+        return Ok(());
+    }
+
+    let config = aws_config::load_from_env().await;
+    let mut s3_config = aws_sdk_s3::config::Builder::from(&config);
+    if let Some(endpoint) = &options.endpoint {
+        // Most S3-compatible services don't support virtual-hosted buckets.
+        s3_config = s3_config.endpoint_url(endpoint).force_path_style(true);
+    }
+    let client = aws_sdk_s3::Client::from_conf(s3_config.build());
+
+    let pb = progress::bar(files.len());
+    for (path, key) in &files {
+        pb.set_message(format!("Uploading: {}", key));
+
+        let body = ByteStream::from_path(path)
+            .await
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let result = client
+            .put_object()
+            .bucket(&options.bucket)
+            .key(key)
+            .body(body)
+            .send()
+            .await;
+
+        if let Err(e) = result {
+            pb.finish_and_clear();
+            anyhow::bail!(
+                "Failed to upload {} to s3://{}: {}",
+                key,
+                options.bucket,
+                aws_sdk_s3::error::DisplayErrorContext(e)
+            );
+        }
+
+        pb.inc(1);
+    }
+    pb.finish_with_message(format!("Uploaded {} files", files.len()));
+
+    Ok(())
+}
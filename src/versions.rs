@@ -0,0 +1,118 @@
+use anyhow::Result;
+use std::cmp::Ordering;
+use std::process::Command;
+
+use crate::executables::program_name;
+
+/// Oldest yt-dlp release that supports every option yt-clipper passes it.
+pub const MIN_YTDLP_VERSION: &str = "2023.03.04";
+
+/// Oldest ffmpeg release that supports every filter yt-clipper uses.
+pub const MIN_FFMPEG_VERSION: &str = "5.1";
+
+/// Runs `command --version` and returns the version it reports, or `None`
+/// when the output has no recognizable version (e.g. ffmpeg's git builds).
+/// Fails when the program can't be run at all.
+pub fn detect(mut command: Command) -> Result<Option<String>> {
+    let program = program_name(&command);
+    let output = command.arg("--version").output().map_err(|_| {
+        anyhow::anyhow!(
+            "{} is not installed or not in PATH. Please install it first.\n\
+             For yt-dlp: https://github.com/yt-dlp/yt-dlp#installation\n\
+             For ffmpeg: https://ffmpeg.org/download.html",
+            program
+        )
+    })?;
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    Ok(stdout.lines().next().and_then(version_token))
+}
+
+/// Checks that the program is installed, warning when its version is older
+/// than `minimum`. Returns the detected version.
+pub fn check(command: Command, minimum: &str) -> Result<Option<String>> {
+    let program = program_name(&command);
+    let version = detect(command)?;
+    if let Some(version) = &version {
+        if compare(version, minimum) == Some(Ordering::Less) {
+            estatus!(
+                "Warning: {} {} is older than the oldest supported version {}, some options may fail. Consider updating it",
+                program,
+                version,
+                minimum
+            );
+        }
+    }
+    Ok(version)
+}
+
+/// Finds the version on the first line of `--version` output: all of yt-dlp's
+/// (`2024.03.10`), or the word after ffmpeg's `ffmpeg version` (`6.0.1`,
+/// `n6.1` or `6.0.1-3ubuntu1`). Only that word is looked at, so a git build
+/// (`N-112345-gabc`) has no version rather than one from its copyright years.
+fn version_token(line: &str) -> Option<String> {
+    let mut words = line.split_whitespace();
+    let word = match line.split_whitespace().position(|word| word == "version") {
+        Some(index) => words.nth(index + 1)?,
+        None => words.next()?,
+    };
+    let word = word.strip_prefix('n').unwrap_or(word);
+    let end = word
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(word.len());
+    let version = word[..end].trim_end_matches('.');
+    parse_version(version).map(|_| version.to_string())
+}
+
+/// Splits a dotted version into its numbers, so `2024.03.10` becomes
+/// `[2024, 3, 10]`.
+fn parse_version(version: &str) -> Option<Vec<u64>> {
+    version.split('.').map(|part| part.parse().ok()).collect()
+}
+
+fn compare(version: &str, other: &str) -> Option<Ordering> {
+    Some(parse_version(version)?.cmp(&parse_version(other)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_versions_on_the_first_line() {
+        assert_eq!(version_token("2024.03.10").as_deref(), Some("2024.03.10"));
+        assert_eq!(
+            version_token("ffmpeg version 6.0.1 Copyright (c) 2000-2023").as_deref(),
+            Some("6.0.1")
+        );
+        assert_eq!(
+            version_token("ffmpeg version n6.1 Copyright").as_deref(),
+            Some("6.1")
+        );
+        assert_eq!(
+            version_token("ffmpeg version 6.0.1-3ubuntu1 Copyright").as_deref(),
+            Some("6.0.1")
+        );
+        assert_eq!(
+            version_token(
+                "ffmpeg version N-112345-gabcdef1234 Copyright (c) 2000-2023 the FFmpeg developers"
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn compares_versions_numerically() {
+        assert_eq!(
+            compare("2023.10.07", MIN_YTDLP_VERSION),
+            Some(Ordering::Greater)
+        );
+        assert_eq!(
+            compare("2023.03.04", MIN_YTDLP_VERSION),
+            Some(Ordering::Equal)
+        );
+        assert_eq!(compare("5.0.3", MIN_FFMPEG_VERSION), Some(Ordering::Less));
+        assert_eq!(compare("5.10", "5.9"), Some(Ordering::Greater));
+        assert_eq!(compare("git", "5.1"), None);
+    }
+}
@@ -0,0 +1,60 @@
+use anyhow::{Context, Result};
+use notify_rust::Notification;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+use crate::VideoInfo;
+
+pub const STATE_FILE: &str = "watch_state.json";
+
+/// What `--watch` saw of a video the last time it was processed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WatchState {
+    pub chapter_count: usize,
+    /// yt-dlp's `YYYYMMDD` date the video was last modified, when known.
+    pub modified_date: Option<String>,
+}
+
+impl WatchState {
+    pub fn of(video_info: &VideoInfo) -> Self {
+        WatchState {
+            chapter_count: video_info.chapters.as_ref().map_or(0, Vec::len),
+            modified_date: video_info.modified_date.clone(),
+        }
+    }
+}
+
+/// Reads the state saved in `output_dir`, or `None` if the video hasn't
+/// been processed there yet.
+pub fn load_state(output_dir: &Path) -> Result<Option<WatchState>> {
+    let path = output_dir.join(STATE_FILE);
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let contents =
+        fs::read_to_string(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let state =
+        serde_json::from_str(&contents).with_context(|| format!("Invalid {}", path.display()))?;
+    Ok(Some(state))
+}
+
+pub fn save_state(output_dir: &Path, state: &WatchState) -> Result<()> {
+    let path = output_dir.join(STATE_FILE);
+    let json = serde_json::to_string_pretty(state)?;
+    fs::write(&path, json).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// Shows a desktop notification, warning if there is no notification
+/// service to show it.
+pub fn notify(summary: &str, body: &str) {
+    if let Err(e) = Notification::new()
+        .appname("yt-clipper")
+        .summary(summary)
+        .body(body)
+        .show()
+    {
+        estatus!("Warning: failed to show a notification: {}", e);
+    }
+}
@@ -0,0 +1,201 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::subtitles::escape;
+use crate::Args;
+
+/// Distance in pixels between a corner-placed watermark and the frame edge.
+const MARGIN: u32 = 10;
+
+/// Where `--watermark-position` places the watermark.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(into = "String", try_from = "String")]
+pub enum WatermarkPosition {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+    /// Top-left corner of the watermark, in pixels from the top-left of the frame.
+    Custom(u32, u32),
+}
+
+impl FromStr for WatermarkPosition {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "top-left" => Ok(WatermarkPosition::TopLeft),
+            "top-right" => Ok(WatermarkPosition::TopRight),
+            "bottom-left" => Ok(WatermarkPosition::BottomLeft),
+            "bottom-right" => Ok(WatermarkPosition::BottomRight),
+            _ => value
+                .split_once(':')
+                .and_then(|(x, y)| Some(WatermarkPosition::Custom(x.parse().ok()?, y.parse().ok()?)))
+                .ok_or_else(|| {
+                    format!(
+                        "invalid position \"{}\", expected top-left, top-right, bottom-left, bottom-right or X:Y",
+                        value
+                    )
+                }),
+        }
+    }
+}
+
+impl TryFrom<String> for WatermarkPosition {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl fmt::Display for WatermarkPosition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WatermarkPosition::TopLeft => f.write_str("top-left"),
+            WatermarkPosition::TopRight => f.write_str("top-right"),
+            WatermarkPosition::BottomLeft => f.write_str("bottom-left"),
+            WatermarkPosition::BottomRight => f.write_str("bottom-right"),
+            WatermarkPosition::Custom(x, y) => write!(f, "{}:{}", x, y),
+        }
+    }
+}
+
+impl From<WatermarkPosition> for String {
+    fn from(position: WatermarkPosition) -> Self {
+        position.to_string()
+    }
+}
+
+impl WatermarkPosition {
+    /// `x` and `y` expressions for a watermark of size `w`x`h` in a frame of
+    /// size `frame_w`x`frame_h`, using the variable names of the filter at hand.
+    fn coordinates(self, w: &str, h: &str, frame_w: &str, frame_h: &str) -> (String, String) {
+        let left = MARGIN.to_string();
+        let top = MARGIN.to_string();
+        let right = format!("{}-{}-{}", frame_w, w, MARGIN);
+        let bottom = format!("{}-{}-{}", frame_h, h, MARGIN);
+        match self {
+            WatermarkPosition::TopLeft => (left, top),
+            WatermarkPosition::TopRight => (right, top),
+            WatermarkPosition::BottomLeft => (left, bottom),
+            WatermarkPosition::BottomRight => (right, bottom),
+            WatermarkPosition::Custom(x, y) => (x.to_string(), y.to_string()),
+        }
+    }
+}
+
+/// Parses a `--watermark-opacity` value between 0 and 1.
+pub fn parse_opacity(value: &str) -> Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(opacity) if (0.0..=1.0).contains(&opacity) => Ok(opacity),
+        _ => Err(format!(
+            "invalid opacity \"{}\", expected 0.0 to 1.0",
+            value
+        )),
+    }
+}
+
+/// An image and/or text stamped onto every encoded clip.
+#[derive(Debug, Clone)]
+pub struct Watermark {
+    pub image: Option<PathBuf>,
+    pub text: Option<String>,
+    pub font: Option<String>,
+    pub font_size: u32,
+    pub color: String,
+    pub position: WatermarkPosition,
+    pub opacity: f64,
+}
+
+impl Watermark {
+    /// Returns `None` unless `--watermark-image` or `--watermark-text` is given.
+    pub fn from_args(args: &Args) -> Option<Self> {
+        (args.watermark_image.is_some() || args.watermark_text.is_some()).then(|| Watermark {
+            image: args.watermark_image.clone(),
+            text: args.watermark_text.clone(),
+            font: args.watermark_font.clone(),
+            font_size: args.watermark_font_size,
+            color: args.watermark_color.clone(),
+            position: args.watermark_position,
+            opacity: args.watermark_opacity,
+        })
+    }
+
+    /// Adds the watermark to the end of `graph`, a filtergraph (possibly
+    /// empty) whose last chain has an unlabeled output. The result also ends
+    /// in such a chain, so more filters can be appended with a comma.
+    pub fn apply(&self, graph: &str) -> String {
+        let mut graph = graph.to_string();
+
+        if let Some(image) = &self.image {
+            let (x, y) = self.position.coordinates("w", "h", "W", "H");
+            if graph.is_empty() {
+                graph.push_str("null");
+            }
+            graph = format!(
+                "{}[base];{}[watermark];[base][watermark]overlay={}:{}",
+                graph,
+                self.image_source(image),
+                x,
+                y
+            );
+        }
+
+        if let Some(text) = &self.text {
+            if !graph.is_empty() {
+                graph.push(',');
+            }
+            graph.push_str(&self.drawtext(text));
+        }
+
+        graph
+    }
+
+    /// Loads the image with the `movie` source and fades it to `opacity`.
+    fn image_source(&self, image: &Path) -> String {
+        let mut source = format!(
+            "movie={},format=rgba",
+            filter_value(&image.to_string_lossy())
+        );
+        if self.opacity < 1.0 {
+            source.push_str(&format!(",colorchannelmixer=aa={}", self.opacity));
+        }
+        source
+    }
+
+    fn drawtext(&self, text: &str) -> String {
+        let (x, y) = self.position.coordinates("tw", "th", "w", "h");
+        let color = if self.color.contains('@') || self.opacity >= 1.0 {
+            self.color.clone()
+        } else {
+            format!("{}@{}", self.color, self.opacity)
+        };
+
+        let mut filter = format!(
+            "drawtext=text={}:expansion=none:fontsize={}:fontcolor={}:x={}:y={}",
+            filter_value(text),
+            self.font_size,
+            filter_value(&color),
+            x,
+            y
+        );
+        // A font file is used as is; anything else is looked up by name.
+        match self.font.as_deref() {
+            Some(font) if Path::new(font).is_file() => {
+                filter.push_str(&format!(":fontfile={}", filter_value(font)));
+            }
+            Some(font) => filter.push_str(&format!(":font={}", filter_value(font))),
+            None => {}
+        }
+        filter
+    }
+}
+
+/// `value` escaped once for a filter option and again for the filtergraph.
+fn filter_value(value: &str) -> String {
+    let value = escape(value, &['\\', '\'', ':']);
+    escape(&value, &['\\', '\'', '[', ']', ',', ';'])
+}
@@ -0,0 +1,68 @@
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::executables::Executables;
+use crate::progress;
+
+/// Parses a `WxH` image size such as `1280x200`.
+pub fn parse_size(value: &str) -> Result<(u32, u32), String> {
+    let (width, height) = value
+        .split_once('x')
+        .ok_or_else(|| format!("'{}' is not a size like 1280x200", value))?;
+
+    let parse = |n: &str| {
+        n.parse::<u32>()
+            .ok()
+            .filter(|&n| n > 0)
+            .ok_or_else(|| format!("'{}' is not a size like 1280x200", value))
+    };
+
+    Ok((parse(width)?, parse(height)?))
+}
+
+/// Draws the waveform of every clip in `clips` into a PNG of the same name
+/// in `waveforms_dir`.
+pub fn generate_waveforms(
+    executables: &Executables,
+    clips: &[PathBuf],
+    waveforms_dir: &Path,
+    size: (u32, u32),
+    color: &str,
+) -> Result<()> {
+    fs::create_dir_all(waveforms_dir).context("Failed to create waveforms directory")?;
+
+    let filter = format!("showwavespic=s={}x{}:colors={}", size.0, size.1, color);
+
+    let pb = progress::bar(clips.len());
+
+    for clip in clips {
+        let name = clip.file_stem().unwrap_or_default();
+        pb.set_message(format!("Waveform: {}", name.to_string_lossy()));
+
+        let output = waveforms_dir.join(name).with_extension("png");
+
+        let result = executables.run(executables.ffmpeg().args([
+            "-i",
+            clip.to_str().unwrap(),
+            "-filter_complex",
+            &filter,
+            "-frames:v",
+            "1",
+            "-y",
+            output.to_str().unwrap(),
+        ]));
+
+        if let Err(e) = result {
+            pb.finish_and_clear();
+            return Err(e)
+                .with_context(|| format!("Failed to draw waveform for {}", clip.display()));
+        }
+
+        pb.inc(1);
+    }
+
+    pb.finish_with_message("All waveforms drawn");
+
+    Ok(())
+}